@@ -0,0 +1,120 @@
+use super::format::ByteWriter;
+use super::{encode_cache, write_file, CacheError, CacheFile, CacheKind, CacheOptions};
+use render::prelude::{Context, TextureAtlas, TextureRect};
+use std::path::Path;
+
+/// Named region of an atlas layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtlasFrame {
+    pub name: String,
+    pub layer: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Atlas pixels and frames. Layers hold `width * height` pixels in
+/// [`render::prelude::DEFAULT_TEXTURE_FORMAT`] and borrow the cache payload when loaded.
+#[derive(Debug, Clone)]
+pub struct AtlasView<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<&'a [u8]>,
+    pub frames: Vec<AtlasFrame>,
+}
+
+impl<'a> AtlasView<'a> {
+    /// Upload layers straight from the payload and register every frame
+    pub fn create_texture_atlas(&self, gpu: &mut Context) -> TextureAtlas {
+        let mut atlas = TextureAtlas::new(gpu, self.width, self.height, self.layers.len() as u32);
+        for (layer, bytes) in self.layers.iter().enumerate() {
+            let layer = layer as u32;
+            atlas.append_bytes(gpu, bytes, self.width, self.height, |from_bytes| {
+                for frame in self.frames.iter().filter(|frame| frame.layer == layer) {
+                    from_bytes.add(
+                        frame.name.as_str(),
+                        layer,
+                        TextureRect::new(frame.x, frame.y, frame.width, frame.height),
+                        Some((frame.x, frame.y)),
+                    );
+                }
+            });
+        }
+        atlas
+    }
+}
+
+pub fn encode_atlas(atlas: &AtlasView, options: CacheOptions) -> Vec<u8> {
+    let mut writer = ByteWriter::default();
+    writer.u32(atlas.width);
+    writer.u32(atlas.height);
+    writer.u32(atlas.frames.len() as u32);
+    for frame in atlas.frames.iter() {
+        writer.str(&frame.name);
+        for value in [frame.layer, frame.x, frame.y, frame.width, frame.height] {
+            writer.u32(value);
+        }
+    }
+    writer.u32(atlas.layers.len() as u32);
+    for layer in atlas.layers.iter() {
+        writer.array(layer);
+    }
+    encode_cache(CacheKind::Atlas, &writer.bytes, options)
+}
+
+pub fn save_atlas<P>(path: P, atlas: &AtlasView, options: CacheOptions) -> Result<(), CacheError>
+where
+    P: AsRef<Path>,
+{
+    write_file(path.as_ref(), &encode_atlas(atlas, options))
+}
+
+impl CacheFile {
+    /// Atlas of a [`CacheKind::Atlas`] file, layers are borrowed from the payload
+    pub fn atlas(&self) -> Result<AtlasView<'_>, CacheError> {
+        let mut reader = self.reader(CacheKind::Atlas)?;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+
+        // Name length and five values
+        let frame_count = reader.count(24)?;
+        let mut frames = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let name = reader.str()?.to_string();
+            let [layer, x, y, width, height] = [
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+            ];
+            frames.push(AtlasFrame {
+                name,
+                layer,
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+
+        let layer_count = reader.count(4)?;
+        let layers = (0..layer_count)
+            .map(|_| reader.array::<u8>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let layer_size = width as usize * height as usize * 4;
+        if layers.iter().any(|layer| layer.len() != layer_size) {
+            return Err(CacheError::Invalid(format!(
+                "atlas layers must hold {layer_size} bytes"
+            )));
+        }
+
+        Ok(AtlasView {
+            width,
+            height,
+            layers,
+            frames,
+        })
+    }
+}
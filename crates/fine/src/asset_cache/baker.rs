@@ -0,0 +1,189 @@
+use super::{
+    encode_geometries, encode_obj, encode_scene, write_file, CacheError, CacheKind, CacheOptions,
+};
+use crate::asset_loader::{load_gltf, load_obj, load_ply, GltfError, ObjError, PlyError};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+/// Extension of baked files, appended to the source file name
+pub const CACHE_EXTENSION: &str = "cache";
+
+#[derive(Debug)]
+pub enum BakeError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Unsupported(PathBuf),
+    Cache(CacheError),
+    Gltf(GltfError),
+    Obj(ObjError),
+    Ply(PlyError),
+}
+
+impl std::fmt::Display for BakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BakeError::Io { path, error } => {
+                let path = path.display();
+                write!(f, "{path}: {error}")
+            }
+            BakeError::Unsupported(path) => {
+                let path = path.display();
+                write!(f, "{path}: no baker for this file type")
+            }
+            BakeError::Cache(error) => write!(f, "{error}"),
+            BakeError::Gltf(error) => write!(f, "{error}"),
+            BakeError::Obj(error) => write!(f, "{error}"),
+            BakeError::Ply(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for BakeError {}
+
+impl From<CacheError> for BakeError {
+    fn from(error: CacheError) -> Self {
+        BakeError::Cache(error)
+    }
+}
+
+impl From<GltfError> for BakeError {
+    fn from(error: GltfError) -> Self {
+        BakeError::Gltf(error)
+    }
+}
+
+impl From<ObjError> for BakeError {
+    fn from(error: ObjError) -> Self {
+        BakeError::Obj(error)
+    }
+}
+
+impl From<PlyError> for BakeError {
+    fn from(error: PlyError) -> Self {
+        BakeError::Ply(error)
+    }
+}
+
+/// Converts source assets into cache files.
+/// glTF files become scenes, OBJ and PLY files become geometries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Baker {
+    pub options: CacheOptions,
+}
+
+impl Baker {
+    pub fn new(options: CacheOptions) -> Self {
+        Self { options }
+    }
+
+    /// Kind of cache produced for `source`, `None` when it cannot be baked
+    pub fn kind_of(source: &Path) -> Option<CacheKind> {
+        let extension = source.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gltf" | "glb" => Some(CacheKind::Scene),
+            "obj" | "ply" => Some(CacheKind::Geometry),
+            _ => None,
+        }
+    }
+
+    /// Path of the cache baked from `source` in `directory`
+    pub fn cache_path(source: &Path, directory: &Path) -> PathBuf {
+        let mut name = source.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(CACHE_EXTENSION);
+        directory.join(name)
+    }
+
+    fn is_obj(source: &Path) -> bool {
+        source
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("obj"))
+            .unwrap_or(false)
+    }
+
+    /// Files read along with `source`, the material libraries of OBJ files
+    pub fn dependencies(source: &Path) -> Vec<PathBuf> {
+        let file = match std::fs::File::open(source) {
+            Ok(file) if Self::is_obj(source) => file,
+            _ => return Vec::new(),
+        };
+        let base = source.parent().unwrap_or_else(|| Path::new(""));
+        std::io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| {
+                let library = line.trim().strip_prefix("mtllib")?;
+                library
+                    .starts_with(char::is_whitespace)
+                    .then(|| base.join(library.trim()))
+            })
+            .collect()
+    }
+
+    /// Whether `destination` exists and is newer than `source` and its
+    /// [`dependencies`](Baker::dependencies). Missing dependencies are ignored.
+    pub fn is_up_to_date(source: &Path, destination: &Path) -> bool {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|meta| meta.modified());
+        let destination = match (modified(source), modified(destination)) {
+            (Ok(source), Ok(destination)) if destination >= source => destination,
+            _ => return false,
+        };
+        Self::dependencies(source).iter().all(|dependency| {
+            modified(dependency)
+                .map(|modified| destination >= modified)
+                .unwrap_or(true)
+        })
+    }
+
+    /// Bake `source` into `destination`
+    pub fn bake(&self, source: &Path, destination: &Path) -> Result<CacheKind, BakeError> {
+        let kind = Self::kind_of(source).ok_or_else(|| BakeError::Unsupported(source.into()))?;
+
+        let bytes = match kind {
+            CacheKind::Scene => encode_scene(&load_gltf(source)?, self.options),
+            CacheKind::Geometry if Self::is_obj(source) => {
+                encode_obj(&load_obj(source)?, self.options)
+            }
+            CacheKind::Geometry => encode_geometries(&[load_ply(source)?], self.options),
+            CacheKind::Atlas => unreachable!(),
+        };
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| BakeError::Io {
+                path: parent.into(),
+                error,
+            })?;
+        }
+        write_file(destination, &bytes)?;
+        Ok(kind)
+    }
+
+    /// Bake every supported file of `source` into `destination`, skipping
+    /// up-to-date caches. Returns the written caches.
+    pub fn bake_dir(&self, source: &Path, destination: &Path) -> Result<Vec<PathBuf>, BakeError> {
+        let io = |error| BakeError::Io {
+            path: source.into(),
+            error,
+        };
+        let mut baked = Vec::new();
+        for entry in std::fs::read_dir(source).map_err(io)? {
+            let path = entry.map_err(io)?.path();
+            if path.is_dir() {
+                let directory = destination.join(path.file_name().unwrap_or_default());
+                baked.extend(self.bake_dir(&path, &directory)?);
+                continue;
+            }
+            if Self::kind_of(&path).is_none() {
+                continue;
+            }
+            let cache = Self::cache_path(&path, destination);
+            if !Self::is_up_to_date(&path, &cache) {
+                self.bake(&path, &cache)?;
+                baked.push(cache);
+            }
+        }
+        Ok(baked)
+    }
+}
@@ -0,0 +1,405 @@
+use std::path::{Path, PathBuf};
+
+/// First bytes of every cache file
+pub const CACHE_MAGIC: [u8; 8] = *b"FINECACH";
+/// Bumped whenever the payload layout changes
+pub const CACHE_VERSION: u32 = 2;
+
+/// Payloads start after the header, aligned so that arrays are borrowed in place
+const HEADER_SIZE: usize = 48;
+/// Alignment of every array in a payload
+const ARRAY_ALIGNMENT: usize = 16;
+
+const FLAG_LZ4: u32 = 1;
+/// Largest ratio of an lz4 block decompressed size to its compressed size
+const LZ4_MAX_RATIO: usize = 255;
+
+#[derive(Debug)]
+pub enum CacheError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    WrongKind {
+        expected: CacheKind,
+        found: u32,
+    },
+    Checksum {
+        expected: u32,
+        found: u32,
+    },
+    Decompress(lz4_flex::block::DecompressError),
+    /// The payload ends before `offset + len`
+    Truncated {
+        offset: usize,
+        len: usize,
+    },
+    Invalid(String),
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheError::Io { path, error } => {
+                let path = path.display();
+                write!(f, "{path}: {error}")
+            }
+            CacheError::InvalidMagic => write!(f, "not a cache file"),
+            CacheError::UnsupportedVersion(version) => write!(
+                f,
+                "cache version {version} is not supported, expected {CACHE_VERSION}"
+            ),
+            CacheError::WrongKind { expected, found } => {
+                write!(f, "expected a {expected:?} cache, found kind {found}")
+            }
+            CacheError::Checksum { expected, found } => write!(
+                f,
+                "checksum mismatch, expected {expected:08x}, found {found:08x}"
+            ),
+            CacheError::Decompress(error) => write!(f, "{error}"),
+            CacheError::Truncated { offset, len } => {
+                write!(f, "payload truncated reading {len} bytes at {offset}")
+            }
+            CacheError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<lz4_flex::block::DecompressError> for CacheError {
+    fn from(error: lz4_flex::block::DecompressError) -> Self {
+        CacheError::Decompress(error)
+    }
+}
+
+/// Content stored in a cache file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Geometry = 1,
+    Atlas = 2,
+    Scene = 3,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheOptions {
+    /// Compress the payload with LZ4. Compressed files are decompressed on load
+    /// instead of being read in place.
+    pub compress: bool,
+}
+
+enum Storage {
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(memmap2::Mmap),
+    /// `u64` words keep the payload aligned
+    Owned(Vec<u64>),
+}
+
+/// A validated cache file. Uncompressed files are memory mapped and read in place.
+pub struct CacheFile {
+    kind: CacheKind,
+    storage: Storage,
+    offset: usize,
+    len: usize,
+}
+
+impl CacheFile {
+    /// Map a cache file in memory
+    pub fn open<P>(path: P) -> Result<Self, CacheError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let io = |error| CacheError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = std::fs::File::open(path).map_err(io)?;
+            // The mapping is read only and cache files are replaced, not edited in place
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io)?;
+            Self::from_storage(Storage::Mapped(map))
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let bytes = std::fs::read(path).map_err(io)?;
+            Self::from_bytes(&bytes)
+        }
+    }
+
+    /// Read a cache from bytes, copying them to aligned storage
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CacheError> {
+        let mut words = vec![0u64; bytes.len().div_ceil(8)];
+        bytemuck::cast_slice_mut(&mut words)[..bytes.len()].copy_from_slice(bytes);
+        Self::from_storage(Storage::Owned(words))
+    }
+
+    fn from_storage(storage: Storage) -> Result<Self, CacheError> {
+        let bytes = match &storage {
+            #[cfg(not(target_arch = "wasm32"))]
+            Storage::Mapped(map) => &map[..],
+            Storage::Owned(words) => bytemuck::cast_slice(words),
+        };
+        if bytes.len() < HEADER_SIZE || bytes[0..8] != CACHE_MAGIC {
+            return Err(CacheError::InvalidMagic);
+        }
+
+        let mut header = ByteReader::new(&bytes[8..HEADER_SIZE]);
+        let version = header.u32()?;
+        if version != CACHE_VERSION {
+            return Err(CacheError::UnsupportedVersion(version));
+        }
+        let kind = match header.u32()? {
+            1 => CacheKind::Geometry,
+            2 => CacheKind::Atlas,
+            3 => CacheKind::Scene,
+            found => {
+                return Err(CacheError::Invalid(format!("unknown cache kind {found}")));
+            }
+        };
+        let flags = header.u32()?;
+        let checksum = header.u32()?;
+        let stored_len = header.u64()? as usize;
+        let raw_len = header.u64()? as usize;
+
+        let stored = HEADER_SIZE
+            .checked_add(stored_len)
+            .and_then(|end| bytes.get(HEADER_SIZE..end))
+            .ok_or(CacheError::Truncated {
+                offset: HEADER_SIZE,
+                len: stored_len,
+            })?;
+        let found = crc32fast::hash(stored);
+        if found != checksum {
+            return Err(CacheError::Checksum {
+                expected: checksum,
+                found,
+            });
+        }
+
+        if flags & FLAG_LZ4 == 0 {
+            return Ok(Self {
+                kind,
+                storage,
+                offset: HEADER_SIZE,
+                len: stored_len,
+            });
+        }
+
+        // The header is not checksummed, do not allocate more than lz4 can
+        // expand the payload to
+        if raw_len > stored_len.saturating_mul(LZ4_MAX_RATIO) {
+            return Err(CacheError::Invalid(format!(
+                "{raw_len} bytes cannot be decompressed from {stored_len}"
+            )));
+        }
+        let mut words = vec![0u64; raw_len.div_ceil(8)];
+        let raw = &mut bytemuck::cast_slice_mut(&mut words)[..raw_len];
+        let len = lz4_flex::block::decompress_into(stored, raw)?;
+        if len != raw_len {
+            return Err(CacheError::Invalid(format!(
+                "decompressed {len} bytes, expected {raw_len}"
+            )));
+        }
+        Ok(Self {
+            kind,
+            storage: Storage::Owned(words),
+            offset: 0,
+            len: raw_len,
+        })
+    }
+
+    pub fn kind(&self) -> CacheKind {
+        self.kind
+    }
+
+    /// Whether the payload is read from a memory mapping
+    pub fn is_mapped(&self) -> bool {
+        match self.storage {
+            #[cfg(not(target_arch = "wasm32"))]
+            Storage::Mapped(_) => true,
+            Storage::Owned(_) => false,
+        }
+    }
+
+    /// Decompressed payload
+    pub fn payload(&self) -> &[u8] {
+        let bytes = match &self.storage {
+            #[cfg(not(target_arch = "wasm32"))]
+            Storage::Mapped(map) => &map[..],
+            Storage::Owned(words) => bytemuck::cast_slice(words),
+        };
+        &bytes[self.offset..self.offset + self.len]
+    }
+
+    pub(super) fn reader(&self, expected: CacheKind) -> Result<ByteReader<'_>, CacheError> {
+        if self.kind != expected {
+            return Err(CacheError::WrongKind {
+                expected,
+                found: self.kind as u32,
+            });
+        }
+        Ok(ByteReader::new(self.payload()))
+    }
+}
+
+/// Wrap a payload in a cache file
+pub fn encode_cache(kind: CacheKind, payload: &[u8], options: CacheOptions) -> Vec<u8> {
+    let compressed;
+    let (flags, stored) = if options.compress {
+        compressed = lz4_flex::block::compress(payload);
+        (FLAG_LZ4, &compressed[..])
+    } else {
+        (0, payload)
+    };
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + stored.len());
+    bytes.extend_from_slice(&CACHE_MAGIC);
+    bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(kind as u32).to_le_bytes());
+    bytes.extend_from_slice(&flags.to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(stored).to_le_bytes());
+    bytes.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.resize(HEADER_SIZE, 0);
+    bytes.extend_from_slice(stored);
+    bytes
+}
+
+pub(super) fn write_file(path: &Path, bytes: &[u8]) -> Result<(), CacheError> {
+    // Write aside and rename so mapped readers never see a partial file
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, bytes)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .map_err(|error| CacheError::Io {
+            path: path.to_path_buf(),
+            error,
+        })
+}
+
+/// Little endian payload writer
+#[derive(Default)]
+pub(super) struct ByteWriter {
+    pub bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn option(&mut self, value: Option<usize>) {
+        self.u32(value.map(|value| value as u32).unwrap_or(u32::MAX));
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.resize(self.bytes.len().div_ceil(4) * 4, 0);
+    }
+
+    pub fn optional_str(&mut self, value: Option<&str>) {
+        self.u32(value.is_some() as u32);
+        if let Some(value) = value {
+            self.str(value);
+        }
+    }
+
+    /// Length followed by the aligned values
+    pub fn array<T: bytemuck::Pod>(&mut self, values: &[T]) {
+        self.u32(values.len() as u32);
+        self.bytes
+            .resize(self.bytes.len().next_multiple_of(ARRAY_ALIGNMENT), 0);
+        self.bytes.extend_from_slice(bytemuck::cast_slice(values));
+        self.bytes.resize(self.bytes.len().div_ceil(4) * 4, 0);
+    }
+}
+
+/// Reads a payload in place
+pub(super) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CacheError> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or(CacheError::Truncated {
+                offset: self.offset,
+                len,
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, CacheError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, CacheError> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    /// Number of items taking at least `item_size` bytes each, checked against
+    /// the rest of the payload before anything is allocated for them
+    pub fn count(&mut self, item_size: usize) -> Result<usize, CacheError> {
+        let count = self.u32()? as usize;
+        let len = count.saturating_mul(item_size);
+        if len > self.bytes.len().saturating_sub(self.offset) {
+            return Err(CacheError::Truncated {
+                offset: self.offset,
+                len,
+            });
+        }
+        Ok(count)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, CacheError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub fn option(&mut self) -> Result<Option<usize>, CacheError> {
+        let value = self.u32()?;
+        Ok((value != u32::MAX).then_some(value as usize))
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, CacheError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        self.offset = self.offset.div_ceil(4) * 4;
+        std::str::from_utf8(bytes).map_err(|error| CacheError::Invalid(error.to_string()))
+    }
+
+    pub fn optional_str(&mut self) -> Result<Option<&'a str>, CacheError> {
+        if self.u32()? == 0 {
+            return Ok(None);
+        }
+        self.str().map(Some)
+    }
+
+    /// Borrow an array written by [`ByteWriter::array`]
+    pub fn array<T: bytemuck::Pod>(&mut self) -> Result<&'a [T], CacheError> {
+        let len = self.u32()? as usize;
+        self.offset = self.offset.next_multiple_of(ARRAY_ALIGNMENT);
+        let bytes = self.take(len.saturating_mul(std::mem::size_of::<T>()))?;
+        self.offset = self.offset.div_ceil(4) * 4;
+        bytemuck::try_cast_slice(bytes).map_err(|error| CacheError::Invalid(error.to_string()))
+    }
+}
@@ -0,0 +1,322 @@
+use super::format::{ByteReader, ByteWriter};
+use super::{encode_cache, write_file, CacheError, CacheFile, CacheKind, CacheOptions};
+use crate::asset_loader::ObjScene;
+use render::prelude::{wgpu, GeometryData, IndiceValues, MorphTarget, VertexValues};
+use std::path::Path;
+
+/// Vertex values borrowed from a cache payload
+#[derive(Debug, Clone, Copy)]
+pub enum VertexSlice<'a> {
+    Float32(&'a [f32]),
+    Float32x2(&'a [[f32; 2]]),
+    Float32x3(&'a [[f32; 3]]),
+    Float32x4(&'a [[f32; 4]]),
+}
+
+impl<'a> VertexSlice<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            VertexSlice::Float32(values) => values.len(),
+            VertexSlice::Float32x2(values) => values.len(),
+            VertexSlice::Float32x3(values) => values.len(),
+            VertexSlice::Float32x4(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_bytes(&self) -> &'a [u8] {
+        match self {
+            VertexSlice::Float32(values) => bytemuck::cast_slice(values),
+            VertexSlice::Float32x2(values) => bytemuck::cast_slice(values),
+            VertexSlice::Float32x3(values) => bytemuck::cast_slice(values),
+            VertexSlice::Float32x4(values) => bytemuck::cast_slice(values),
+        }
+    }
+
+    /// Copy into owned values, a single memcpy per attribute
+    pub fn to_values(&self) -> VertexValues {
+        match self {
+            VertexSlice::Float32(values) => values.to_vec().into(),
+            VertexSlice::Float32x2(values) => values.to_vec().into(),
+            VertexSlice::Float32x3(values) => values.to_vec().into(),
+            VertexSlice::Float32x4(values) => values.to_vec().into(),
+        }
+    }
+}
+
+/// Indices borrowed from a cache payload
+#[derive(Debug, Clone, Copy)]
+pub enum IndiceSlice<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl<'a> IndiceSlice<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            IndiceSlice::U16(values) => values.len(),
+            IndiceSlice::U32(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        match self {
+            IndiceSlice::U16(_) => wgpu::IndexFormat::Uint16,
+            IndiceSlice::U32(_) => wgpu::IndexFormat::Uint32,
+        }
+    }
+
+    pub fn get_bytes(&self) -> &'a [u8] {
+        match self {
+            IndiceSlice::U16(values) => bytemuck::cast_slice(values),
+            IndiceSlice::U32(values) => bytemuck::cast_slice(values),
+        }
+    }
+
+    pub fn to_values(&self) -> IndiceValues {
+        match self {
+            IndiceSlice::U16(values) => IndiceValues::U16(values.to_vec()),
+            IndiceSlice::U32(values) => IndiceValues::U32(values.to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MorphTargetView<'a> {
+    pub name: &'a str,
+    pub positions: Option<&'a [[f32; 3]]>,
+    pub normals: Option<&'a [[f32; 3]]>,
+    pub tangents: Option<&'a [[f32; 3]]>,
+}
+
+impl<'a> MorphTargetView<'a> {
+    pub fn to_morph_target(&self) -> MorphTarget {
+        MorphTarget {
+            name: self.name.to_string(),
+            positions: self.positions.map(<[_]>::to_vec),
+            normals: self.normals.map(<[_]>::to_vec),
+            tangents: self.tangents.map(<[_]>::to_vec),
+        }
+    }
+}
+
+/// Geometry read in place from a [`CacheFile`]
+#[derive(Debug, Clone)]
+pub struct GeometryView<'a> {
+    /// Object or group name, only kept by geometry caches
+    pub name: Option<&'a str>,
+    /// Material name, only kept by geometry caches
+    pub material: Option<&'a str>,
+    pub topology: wgpu::PrimitiveTopology,
+    pub attributes: Vec<(&'a str, VertexSlice<'a>)>,
+    pub indices: Option<IndiceSlice<'a>>,
+    pub morph_targets: Vec<MorphTargetView<'a>>,
+}
+
+impl<'a> GeometryView<'a> {
+    pub fn attribute(&self, name: &str) -> Option<VertexSlice<'a>> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| *attribute == name)
+            .map(|(_, values)| *values)
+    }
+
+    /// Copy the borrowed arrays into owned values. Read the view itself, for
+    /// example to upload its bytes, to avoid the copy.
+    pub fn to_geometry_data(&self) -> GeometryData {
+        let mut data = GeometryData::new();
+        for (name, values) in self.attributes.iter() {
+            data.set_attribute(*name, values.to_values());
+        }
+        data.set_indices(self.indices.map(|indices| indices.to_values()));
+        data.set_topology(self.topology);
+        for target in self.morph_targets.iter() {
+            data.add_morph_target(target.to_morph_target());
+        }
+        data
+    }
+}
+
+fn topology_code(topology: wgpu::PrimitiveTopology) -> u32 {
+    match topology {
+        wgpu::PrimitiveTopology::PointList => 0,
+        wgpu::PrimitiveTopology::LineList => 1,
+        wgpu::PrimitiveTopology::LineStrip => 2,
+        wgpu::PrimitiveTopology::TriangleList => 3,
+        wgpu::PrimitiveTopology::TriangleStrip => 4,
+    }
+}
+
+pub(super) fn write_geometry(writer: &mut ByteWriter, data: &GeometryData) {
+    writer.u32(topology_code(data.topology()));
+
+    writer.u32(data.attribute_names().count() as u32);
+    for (name, values) in data.attributes() {
+        writer.str(name);
+        writer.u32(values.components() as u32);
+        writer.array(values.as_floats());
+    }
+
+    match data.indices() {
+        None => writer.u32(0),
+        Some(IndiceValues::U16(values)) => {
+            writer.u32(1);
+            writer.array(values);
+        }
+        Some(IndiceValues::U32(values)) => {
+            writer.u32(2);
+            writer.array(values);
+        }
+    }
+
+    writer.u32(data.morph_targets().len() as u32);
+    for target in data.morph_targets() {
+        writer.str(&target.name);
+        for deltas in [&target.positions, &target.normals, &target.tangents] {
+            writer.u32(deltas.is_some() as u32);
+            if let Some(deltas) = deltas {
+                writer.array(deltas);
+            }
+        }
+    }
+}
+
+fn cast<T: bytemuck::Pod>(floats: &[f32]) -> Result<&[T], CacheError> {
+    bytemuck::try_cast_slice(floats).map_err(|error| CacheError::Invalid(error.to_string()))
+}
+
+pub(super) fn read_geometry<'a>(
+    reader: &mut ByteReader<'a>,
+) -> Result<GeometryView<'a>, CacheError> {
+    let topology = match reader.u32()? {
+        0 => wgpu::PrimitiveTopology::PointList,
+        1 => wgpu::PrimitiveTopology::LineList,
+        2 => wgpu::PrimitiveTopology::LineStrip,
+        3 => wgpu::PrimitiveTopology::TriangleList,
+        4 => wgpu::PrimitiveTopology::TriangleStrip,
+        code => return Err(CacheError::Invalid(format!("unknown topology {code}"))),
+    };
+
+    // Name, components and array lengths
+    let attribute_count = reader.count(12)?;
+    let mut attributes = Vec::with_capacity(attribute_count);
+    for _ in 0..attribute_count {
+        let name = reader.str()?;
+        let components = reader.u32()?;
+        let floats = reader.array::<f32>()?;
+        let values = match components {
+            1 => VertexSlice::Float32(floats),
+            2 => VertexSlice::Float32x2(cast(floats)?),
+            3 => VertexSlice::Float32x3(cast(floats)?),
+            4 => VertexSlice::Float32x4(cast(floats)?),
+            _ => {
+                return Err(CacheError::Invalid(format!(
+                    "attribute {name} has {components} components"
+                )))
+            }
+        };
+        attributes.push((name, values));
+    }
+
+    let indices = match reader.u32()? {
+        0 => None,
+        1 => Some(IndiceSlice::U16(reader.array()?)),
+        2 => Some(IndiceSlice::U32(reader.array()?)),
+        code => return Err(CacheError::Invalid(format!("unknown index format {code}"))),
+    };
+
+    // Name length and three delta flags
+    let target_count = reader.count(16)?;
+    let mut morph_targets = Vec::with_capacity(target_count);
+    for _ in 0..target_count {
+        let name = reader.str()?;
+        let mut deltas = [None; 3];
+        for delta in deltas.iter_mut() {
+            if reader.u32()? != 0 {
+                *delta = Some(reader.array()?);
+            }
+        }
+        let [positions, normals, tangents] = deltas;
+        morph_targets.push(MorphTargetView {
+            name,
+            positions,
+            normals,
+            tangents,
+        });
+    }
+
+    Ok(GeometryView {
+        name: None,
+        material: None,
+        topology,
+        attributes,
+        indices,
+        morph_targets,
+    })
+}
+
+/// Encode geometries in a cache file
+pub fn encode_geometries(geometries: &[GeometryData], options: CacheOptions) -> Vec<u8> {
+    let mut writer = ByteWriter::default();
+    writer.u32(geometries.len() as u32);
+    for geometry in geometries {
+        writer.optional_str(None);
+        writer.optional_str(None);
+        write_geometry(&mut writer, geometry);
+    }
+    encode_cache(CacheKind::Geometry, &writer.bytes, options)
+}
+
+/// Encode the meshes of an OBJ file in a geometry cache, with their group and
+/// material names. Material properties are not cached.
+pub fn encode_obj(scene: &ObjScene, options: CacheOptions) -> Vec<u8> {
+    let mut writer = ByteWriter::default();
+    writer.u32(scene.meshes.len() as u32);
+    for mesh in scene.meshes.iter() {
+        let material = mesh
+            .material
+            .and_then(|material| scene.materials.get(material));
+        writer.optional_str(mesh.name.as_deref());
+        writer.optional_str(material.map(|material| material.name.as_str()));
+        write_geometry(&mut writer, &mesh.geometry);
+    }
+    encode_cache(CacheKind::Geometry, &writer.bytes, options)
+}
+
+pub fn save_geometries<P>(
+    path: P,
+    geometries: &[GeometryData],
+    options: CacheOptions,
+) -> Result<(), CacheError>
+where
+    P: AsRef<Path>,
+{
+    write_file(path.as_ref(), &encode_geometries(geometries, options))
+}
+
+impl CacheFile {
+    /// Geometries of a [`CacheKind::Geometry`] file, borrowed from the payload
+    pub fn geometries(&self) -> Result<Vec<GeometryView<'_>>, CacheError> {
+        let mut reader = self.reader(CacheKind::Geometry)?;
+        // Name flags and the four geometry counts
+        let count = reader.count(24)?;
+        let mut geometries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let name = reader.optional_str()?;
+            let material = reader.optional_str()?;
+            geometries.push(GeometryView {
+                name,
+                material,
+                ..read_geometry(&mut reader)?
+            });
+        }
+        Ok(geometries)
+    }
+}
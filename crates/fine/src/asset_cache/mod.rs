@@ -0,0 +1,14 @@
+mod atlas;
+mod baker;
+mod format;
+mod geometry;
+mod scene;
+pub use atlas::*;
+pub use baker::*;
+pub use format::{
+    encode_cache, CacheError, CacheFile, CacheKind, CacheOptions, CACHE_MAGIC, CACHE_VERSION,
+};
+pub use geometry::*;
+pub use scene::*;
+
+use format::write_file;
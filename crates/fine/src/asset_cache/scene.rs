@@ -0,0 +1,171 @@
+use super::format::ByteWriter;
+use super::geometry::{read_geometry, write_geometry, GeometryView};
+use super::{encode_cache, write_file, CacheError, CacheFile, CacheKind, CacheOptions};
+use crate::asset_loader::{GltfMesh, GltfNode, GltfPrimitive, GltfScene};
+use math::{Quat, Vec3};
+use std::path::Path;
+use transform::Node;
+
+/// Encode the node hierarchy and meshes of a scene. Materials, textures,
+/// cameras, skins and animations are not cached and primitives lose their material.
+pub fn encode_scene(scene: &GltfScene, options: CacheOptions) -> Vec<u8> {
+    let mut writer = ByteWriter::default();
+
+    writer.u32(scene.nodes.len() as u32);
+    for node in scene.nodes.iter() {
+        writer.optional_str(node.name.as_deref());
+        writer.option(node.parent);
+        writer.option(node.mesh);
+        let transform = node.node.get_transform();
+        let values = transform
+            .translation
+            .to_array()
+            .into_iter()
+            .chain(transform.rotation.to_array())
+            .chain(transform.scale.to_array());
+        for value in values {
+            writer.f32(value);
+        }
+    }
+
+    writer.u32(scene.meshes.len() as u32);
+    for mesh in scene.meshes.iter() {
+        writer.optional_str(mesh.name.as_deref());
+        writer.array(&mesh.weights);
+        writer.u32(mesh.primitives.len() as u32);
+        for primitive in mesh.primitives.iter() {
+            write_geometry(&mut writer, &primitive.geometry);
+        }
+    }
+
+    encode_cache(CacheKind::Scene, &writer.bytes, options)
+}
+
+pub fn save_scene<P>(path: P, scene: &GltfScene, options: CacheOptions) -> Result<(), CacheError>
+where
+    P: AsRef<Path>,
+{
+    write_file(path.as_ref(), &encode_scene(scene, options))
+}
+
+/// Mesh of a scene cache, primitives borrowed from the payload
+#[derive(Debug, Clone)]
+pub struct MeshView<'a> {
+    pub name: Option<&'a str>,
+    pub weights: &'a [f32],
+    pub primitives: Vec<GeometryView<'a>>,
+}
+
+/// Scene read from a [`CacheFile`]. Nodes are rebuilt, meshes stay borrowed
+/// from the payload.
+#[derive(Debug)]
+pub struct SceneView<'a> {
+    /// Parents are stored before their children
+    pub nodes: Vec<GltfNode>,
+    pub roots: Vec<usize>,
+    pub meshes: Vec<MeshView<'a>>,
+}
+
+impl<'a> SceneView<'a> {
+    /// Scene owning copies of the mesh data
+    pub fn into_scene(self) -> GltfScene {
+        let meshes = self
+            .meshes
+            .iter()
+            .map(|mesh| GltfMesh {
+                name: mesh.name.map(str::to_string),
+                primitives: mesh
+                    .primitives
+                    .iter()
+                    .map(|view| GltfPrimitive {
+                        geometry: view.to_geometry_data(),
+                        material: None,
+                    })
+                    .collect(),
+                weights: mesh.weights.to_vec(),
+            })
+            .collect();
+        GltfScene {
+            nodes: self.nodes,
+            roots: self.roots,
+            meshes,
+            ..GltfScene::new()
+        }
+    }
+}
+
+impl CacheFile {
+    /// Scene of a [`CacheKind::Scene`] file, meshes borrowed from the payload
+    pub fn scene_view(&self) -> Result<SceneView<'_>, CacheError> {
+        let mut reader = self.reader(CacheKind::Scene)?;
+        let mut scene = GltfScene::new();
+
+        // Name flag, parent, mesh and ten transform values
+        let node_count = reader.count(52)?;
+        for index in 0..node_count {
+            let name = reader.optional_str()?.map(str::to_string);
+            let parent = reader.option()?;
+            if parent.map(|parent| parent >= index).unwrap_or(false) {
+                return Err(CacheError::Invalid(format!(
+                    "node {index} comes before its parent"
+                )));
+            }
+            let mesh = reader.option()?;
+
+            let mut values = [0.0; 10];
+            for value in values.iter_mut() {
+                *value = reader.f32()?;
+            }
+            let mut node = Node::new();
+            let transform = node.get_mut_transform();
+            transform.translation = Vec3::from_slice(&values[0..3]);
+            transform.rotation = Quat::from_slice(&values[3..7]);
+            transform.scale = Vec3::from_slice(&values[7..10]);
+
+            let mut node = GltfNode::new(name, node);
+            node.mesh = mesh;
+            scene.add_node(node, parent);
+        }
+
+        // Name flag, weight and primitive counts
+        let mesh_count = reader.count(12)?;
+        let mut meshes = Vec::with_capacity(mesh_count);
+        for _ in 0..mesh_count {
+            let name = reader.optional_str()?;
+            let weights = reader.array::<f32>()?;
+            // Topology and the three geometry counts
+            let primitive_count = reader.count(16)?;
+            let primitives = (0..primitive_count)
+                .map(|_| read_geometry(&mut reader))
+                .collect::<Result<Vec<_>, _>>()?;
+            meshes.push(MeshView {
+                name,
+                weights,
+                primitives,
+            });
+        }
+
+        if let Some(node) = scene
+            .nodes
+            .iter()
+            .find(|node| node.mesh.map(|mesh| mesh >= meshes.len()).unwrap_or(false))
+        {
+            let name = &node.name;
+            return Err(CacheError::Invalid(format!(
+                "node {name:?} references a missing mesh"
+            )));
+        }
+
+        Ok(SceneView {
+            nodes: scene.nodes,
+            roots: scene.roots,
+            meshes,
+        })
+    }
+
+    /// Scene of a [`CacheKind::Scene`] file. Geometries are copied out of the
+    /// payload, see [`CacheFile::scene_view`] to borrow them.
+    pub fn scene(&self) -> Result<GltfScene, CacheError> {
+        self.scene_view().map(SceneView::into_scene)
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum BmFontError {
+    Io(std::io::Error),
+    /// A line of a text font could not be parsed
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A binary font ends inside a block
+    UnexpectedEof {
+        block: u8,
+    },
+    UnsupportedVersion(u8),
+}
+
+impl fmt::Display for BmFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BmFontError::Io(error) => write!(f, "{error}"),
+            BmFontError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            BmFontError::UnexpectedEof { block } => {
+                write!(f, "unexpected end of file in block {block}")
+            }
+            BmFontError::UnsupportedVersion(version) => {
+                write!(f, "binary version {version} is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BmFontError {}
+
+impl From<std::io::Error> for BmFontError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Glyph of a page, in pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BitmapGlyph {
+    pub id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub x_advance: i32,
+    /// Page, stored in the atlas layer of the same index
+    pub page: u32,
+}
+
+/// An AngelCode BMFont
+#[derive(Debug, Clone, Default)]
+pub struct BitmapFont {
+    pub face: String,
+    pub size: i32,
+    /// Distance between two lines
+    pub line_height: u32,
+    /// Distance from the top of a line to the baseline
+    pub base: u32,
+    /// Width of the pages
+    pub scale_w: u32,
+    /// Height of the pages
+    pub scale_h: u32,
+    /// Up, right, down, left
+    pub padding: [u32; 4],
+    pub spacing: [u32; 2],
+    /// Page files, relative to the font
+    pub pages: Vec<String>,
+    pub glyphs: HashMap<u32, BitmapGlyph>,
+    pub kernings: HashMap<(u32, u32), i32>,
+}
+
+impl BitmapFont {
+    pub fn glyph(&self, character: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&(character as u32))
+    }
+
+    /// Advance adjustment between two characters
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        self.kernings
+            .get(&(first as u32, second as u32))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Load a text or binary BMFont file
+pub fn load_bmfont<P>(path: P) -> Result<BitmapFont, BmFontError>
+where
+    P: AsRef<Path>,
+{
+    parse_bmfont(&std::fs::read(path)?)
+}
+
+/// Parse a BMFont, binary files are detected by their `BMF` signature
+pub fn parse_bmfont(bytes: &[u8]) -> Result<BitmapFont, BmFontError> {
+    if bytes.starts_with(b"BMF") {
+        parse_bmfont_binary(bytes)
+    } else {
+        let text = std::str::from_utf8(bytes).map_err(|error| BmFontError::Parse {
+            line: 0,
+            message: error.to_string(),
+        })?;
+        parse_bmfont_text(text)
+    }
+}
+
+/// Split `key=value` pairs, values may be quoted
+fn pairs(text: &str) -> Vec<(&str, &str)> {
+    let mut pairs = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(equal) = rest.find('=') {
+        let key = rest[..equal].trim();
+        let after = &rest[equal + 1..];
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        pairs.push((key, value));
+        rest = next.trim_start();
+    }
+    pairs
+}
+
+fn numbers<const N: usize>(line: usize, value: &str) -> Result<[i64; N], BmFontError> {
+    let mut result = [0; N];
+    let mut parts = value.split(',');
+    for number in result.iter_mut() {
+        let part = parts.next().ok_or_else(|| BmFontError::Parse {
+            line,
+            message: format!("expected {N} values in {value:?}"),
+        })?;
+        *number = part.trim().parse().map_err(|_| BmFontError::Parse {
+            line,
+            message: format!("invalid number {part:?}"),
+        })?;
+    }
+    Ok(result)
+}
+
+/// Parse the text format
+pub fn parse_bmfont_text(text: &str) -> Result<BitmapFont, BmFontError> {
+    let mut font = BitmapFont::default();
+    // Pages announced by `common`, page ids must stay below it. Each page has
+    // its own line, which bounds ids in files lying about the count.
+    let mut page_count = None;
+    let line_count = text.lines().count() as i64;
+
+    for (number, text) in text.lines().enumerate() {
+        let line = number + 1;
+        let text = text.trim();
+        let (tag, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let pairs = pairs(rest);
+        let number = |value: &str| numbers::<1>(line, value).map(|[number]| number);
+
+        match tag {
+            "info" => {
+                for (key, value) in pairs {
+                    match key {
+                        "face" => font.face = value.to_string(),
+                        "size" => font.size = number(value)? as i32,
+                        "padding" => font.padding = numbers(line, value)?.map(|v| v as u32),
+                        "spacing" => font.spacing = numbers(line, value)?.map(|v| v as u32),
+                        _ => {}
+                    }
+                }
+            }
+            "common" => {
+                for (key, value) in pairs {
+                    match key {
+                        "lineHeight" => font.line_height = number(value)? as u32,
+                        "base" => font.base = number(value)? as u32,
+                        "scaleW" => font.scale_w = number(value)? as u32,
+                        "scaleH" => font.scale_h = number(value)? as u32,
+                        "pages" => page_count = Some(number(value)?),
+                        _ => {}
+                    }
+                }
+            }
+            "page" => {
+                let mut id = font.pages.len() as i64;
+                let mut file = String::new();
+                for (key, value) in pairs {
+                    match key {
+                        "id" => id = number(value)?,
+                        "file" => file = value.to_string(),
+                        _ => {}
+                    }
+                }
+                // Without a page count, pages come in order
+                let limit = page_count
+                    .unwrap_or(font.pages.len() as i64 + 1)
+                    .min(line_count);
+                if id < 0 || id >= limit {
+                    return Err(BmFontError::Parse {
+                        line,
+                        message: format!("page id {id} is out of range"),
+                    });
+                }
+                let id = id as usize;
+                if font.pages.len() <= id {
+                    font.pages.resize(id + 1, String::new());
+                }
+                font.pages[id] = file;
+            }
+            "char" => {
+                let mut glyph = BitmapGlyph::default();
+                for (key, value) in pairs {
+                    let value = number(value)?;
+                    match key {
+                        "id" => glyph.id = value as u32,
+                        "x" => glyph.x = value as u32,
+                        "y" => glyph.y = value as u32,
+                        "width" => glyph.width = value as u32,
+                        "height" => glyph.height = value as u32,
+                        "xoffset" => glyph.x_offset = value as i32,
+                        "yoffset" => glyph.y_offset = value as i32,
+                        "xadvance" => glyph.x_advance = value as i32,
+                        "page" => glyph.page = value as u32,
+                        _ => {}
+                    }
+                }
+                font.glyphs.insert(glyph.id, glyph);
+            }
+            "kerning" => {
+                let (mut first, mut second, mut amount) = (0, 0, 0);
+                for (key, value) in pairs {
+                    match key {
+                        "first" => first = number(value)? as u32,
+                        "second" => second = number(value)? as u32,
+                        "amount" => amount = number(value)? as i32,
+                        _ => {}
+                    }
+                }
+                font.kernings.insert((first, second), amount);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(font)
+}
+
+/// Little endian reader over a binary block
+struct Block<'a> {
+    id: u8,
+    bytes: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], BmFontError> {
+        if self.bytes.len() < N {
+            return Err(BmFontError::UnexpectedEof { block: self.id });
+        }
+        let (value, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(value.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, BmFontError> {
+        self.take::<1>().map(|[value]| value)
+    }
+
+    fn u16(&mut self) -> Result<u16, BmFontError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn i16(&mut self) -> Result<i16, BmFontError> {
+        self.take().map(i16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, BmFontError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    /// Null terminated string
+    fn string(&mut self) -> Result<String, BmFontError> {
+        let end = self
+            .bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(BmFontError::UnexpectedEof { block: self.id })?;
+        let value = String::from_utf8_lossy(&self.bytes[..end]).into_owned();
+        self.bytes = &self.bytes[end + 1..];
+        Ok(value)
+    }
+}
+
+/// Parse the binary format, version 3
+pub fn parse_bmfont_binary(bytes: &[u8]) -> Result<BitmapFont, BmFontError> {
+    let mut header = Block { id: 0, bytes };
+    let signature = header.take::<3>()?;
+    debug_assert_eq!(&signature, b"BMF");
+    let version = header.u8()?;
+    if version != 3 {
+        return Err(BmFontError::UnsupportedVersion(version));
+    }
+
+    let mut font = BitmapFont::default();
+    let mut rest = header.bytes;
+    while !rest.is_empty() {
+        let mut block = Block { id: 0, bytes: rest };
+        let id = block.u8()?;
+        let size = block.u32()? as usize;
+        if block.bytes.len() < size {
+            return Err(BmFontError::UnexpectedEof { block: id });
+        }
+        rest = &block.bytes[size..];
+        let mut block = Block {
+            id,
+            bytes: &block.bytes[..size],
+        };
+
+        match id {
+            1 => {
+                font.size = block.i16()? as i32;
+                // Bit field, char set, stretchH, aa
+                block.take::<5>()?;
+                font.padding = block.take::<4>()?.map(u32::from);
+                font.spacing = block.take::<2>()?.map(u32::from);
+                block.u8()?;
+                font.face = block.string()?;
+            }
+            2 => {
+                font.line_height = block.u16()? as u32;
+                font.base = block.u16()? as u32;
+                font.scale_w = block.u16()? as u32;
+                font.scale_h = block.u16()? as u32;
+            }
+            3 => {
+                while !block.bytes.is_empty() {
+                    font.pages.push(block.string()?);
+                }
+            }
+            4 => {
+                while !block.bytes.is_empty() {
+                    let glyph = BitmapGlyph {
+                        id: block.u32()?,
+                        x: block.u16()? as u32,
+                        y: block.u16()? as u32,
+                        width: block.u16()? as u32,
+                        height: block.u16()? as u32,
+                        x_offset: block.i16()? as i32,
+                        y_offset: block.i16()? as i32,
+                        x_advance: block.i16()? as i32,
+                        page: block.u8()? as u32,
+                    };
+                    // Channel
+                    block.u8()?;
+                    font.glyphs.insert(glyph.id, glyph);
+                }
+            }
+            5 => {
+                while !block.bytes.is_empty() {
+                    let first = block.u32()?;
+                    let second = block.u32()?;
+                    let amount = block.i16()? as i32;
+                    font.kernings.insert((first, second), amount);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_FONT: &str = r#"info face="Test Font" size=32 padding=1,2,3,4 spacing=1,1
+common lineHeight=36 base=29 scaleW=256 scaleH=128 pages=2
+page id=0 file="test_0.png"
+page id=1 file="test_1.png"
+chars count=2
+char id=65 x=10 y=20 width=18 height=24 xoffset=-1 yoffset=5 xadvance=17 page=1 chnl=15
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=8 page=0 chnl=15
+kernings count=1
+kerning first=65 second=65 amount=-2
+"#;
+
+    fn assert_test_font(font: &BitmapFont) {
+        assert_eq!(font.face, "Test Font");
+        assert_eq!(font.size, 32);
+        assert_eq!(font.padding, [1, 2, 3, 4]);
+        assert_eq!(font.spacing, [1, 1]);
+        assert_eq!((font.line_height, font.base), (36, 29));
+        assert_eq!((font.scale_w, font.scale_h), (256, 128));
+        assert_eq!(font.pages, ["test_0.png", "test_1.png"]);
+        assert_eq!(
+            font.glyph('A'),
+            Some(&BitmapGlyph {
+                id: 65,
+                x: 10,
+                y: 20,
+                width: 18,
+                height: 24,
+                x_offset: -1,
+                y_offset: 5,
+                x_advance: 17,
+                page: 1,
+            })
+        );
+        assert_eq!(font.glyph(' ').map(|glyph| glyph.x_advance), Some(8));
+        assert_eq!(font.kerning('A', 'A'), -2);
+        assert_eq!(font.kerning('A', ' '), 0);
+    }
+
+    #[test]
+    fn text_font() {
+        assert_test_font(&parse_bmfont(TEXT_FONT.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_font() {
+        fn block(bytes: &mut Vec<u8>, id: u8, content: &[u8]) {
+            bytes.push(id);
+            bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(content);
+        }
+
+        let mut bytes = b"BMF\x03".to_vec();
+        let mut info = 32i16.to_le_bytes().to_vec();
+        info.extend_from_slice(&[0, 0, 100, 0, 1, 1, 2, 3, 4, 1, 1, 0]);
+        info.extend_from_slice(b"Test Font\0");
+        block(&mut bytes, 1, &info);
+        let common = [36u16, 29, 256, 128, 2].map(u16::to_le_bytes).concat();
+        block(&mut bytes, 2, &[&common[..], &[0, 0, 0, 0, 0]].concat());
+        block(&mut bytes, 3, b"test_0.png\0test_1.png\0");
+        let mut chars = Vec::new();
+        for (id, [x, y, width, height], [x_offset, y_offset, x_advance], page) in [
+            (65u32, [10u16, 20, 18, 24], [-1i16, 5, 17], 1u8),
+            (32, [0, 0, 0, 0], [0, 0, 8], 0),
+        ] {
+            chars.extend_from_slice(&id.to_le_bytes());
+            for value in [x, y, width, height] {
+                chars.extend_from_slice(&value.to_le_bytes());
+            }
+            for value in [x_offset, y_offset, x_advance] {
+                chars.extend_from_slice(&value.to_le_bytes());
+            }
+            chars.extend_from_slice(&[page, 15]);
+        }
+        block(&mut bytes, 4, &chars);
+        let kerning = [
+            &65u32.to_le_bytes()[..],
+            &65u32.to_le_bytes(),
+            &(-2i16).to_le_bytes(),
+        ];
+        block(&mut bytes, 5, &kerning.concat());
+
+        assert_test_font(&parse_bmfont(&bytes).unwrap());
+        assert!(matches!(
+            parse_bmfont(&bytes[..bytes.len() - 1]),
+            Err(BmFontError::UnexpectedEof { block: 5 })
+        ));
+    }
+
+    #[test]
+    fn page_ids_are_bounded() {
+        for page in ["page id=-1 file=\"a.png\"", "page id=2 file=\"a.png\""] {
+            let text = TEXT_FONT.replace("page id=1 file=\"test_1.png\"", page);
+            match parse_bmfont_text(&text) {
+                Err(BmFontError::Parse { line, .. }) => assert_eq!(line, 4),
+                other => panic!("{page} parsed as {other:?}"),
+            }
+        }
+
+        // Page ids of a font without page count come in order
+        assert!(parse_bmfont_text("page id=0 file=\"a.png\"\npage id=1 file=\"b.png\"").is_ok());
+        assert!(parse_bmfont_text("page id=4000000000 file=\"a.png\"").is_err());
+    }
+}
@@ -0,0 +1,1042 @@
+use crate::camera::{Camera, CameraProjection, OrthographicOptions, PerspectiveOptions};
+use ::gltf::{accessor, animation, buffer, camera, image, material, mesh, texture, Document, Gltf};
+use glam::{Mat4, Quat, Vec3};
+use render::prelude::{wgpu, GeometryData, GeometryError, IndiceValues, MorphTarget, VertexValues};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use transform::{Node, NodeVisitor, Transform};
+
+#[derive(Debug)]
+pub enum GltfError {
+    /// A file, or a buffer or image next to it, could not be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    /// The document is not valid glTF
+    Gltf(::gltf::Error),
+    /// The document could not be written
+    Json(serde_json::Error),
+    /// A data URI is not valid base64
+    Base64(base64::DecodeError),
+    /// A buffer refers to the binary chunk of a file which has none
+    MissingBlob,
+    /// A buffer or image URI is neither a data URI nor a relative path staying
+    /// in the directory of the file
+    UnsupportedUri(String),
+    /// A buffer has less data than declared
+    BufferTooSmall {
+        buffer: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A node is its own ancestor
+    NodeCycle { node: usize },
+    /// A primitive has no position attribute
+    MissingPositions { mesh: usize, primitive: usize },
+    /// A primitive does not pass [`GeometryData::validate`]
+    Geometry {
+        mesh: usize,
+        primitive: usize,
+        error: GeometryError,
+    },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, error } => write!(f, "cannot read {}: {error}", path.display()),
+            GltfError::Gltf(error) => write!(f, "invalid glTF: {error}"),
+            GltfError::Json(error) => write!(f, "cannot write glTF: {error}"),
+            GltfError::Base64(error) => write!(f, "invalid data URI: {error}"),
+            GltfError::MissingBlob => write!(f, "buffer refers to a missing binary chunk"),
+            GltfError::UnsupportedUri(uri) => write!(f, "unsupported URI \"{uri}\""),
+            GltfError::BufferTooSmall {
+                buffer,
+                expected,
+                found,
+            } => write!(f, "buffer {buffer} has {found} bytes, expected {expected}"),
+            GltfError::NodeCycle { node } => write!(f, "node {node} is its own ancestor"),
+            GltfError::MissingPositions { mesh, primitive } => {
+                write!(f, "primitive {primitive} of mesh {mesh} has no positions")
+            }
+            GltfError::Geometry {
+                mesh,
+                primitive,
+                error,
+            } => write!(f, "primitive {primitive} of mesh {mesh}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<::gltf::Error> for GltfError {
+    fn from(error: ::gltf::Error) -> Self {
+        Self::Gltf(error)
+    }
+}
+
+impl From<serde_json::Error> for GltfError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<base64::DecodeError> for GltfError {
+    fn from(error: base64::DecodeError) -> Self {
+        Self::Base64(error)
+    }
+}
+
+/// Node of a [`GltfScene`]. Parents are always stored before their children.
+#[derive(Debug)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub node: Node,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+    /// Morph target weights overriding the mesh weights
+    pub weights: Option<Vec<f32>>,
+}
+
+#[derive(Debug)]
+pub struct GltfPrimitive {
+    pub geometry: GeometryData,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+    /// Default morph target weights
+    pub weights: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GltfAlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GltfTextureRef {
+    /// Index in [`GltfScene::textures`]
+    pub texture: usize,
+    /// Set of texture coordinates, `tex_coord` for 0 and `tex_coord_<n>` after
+    pub tex_coord: u32,
+}
+
+/// Metallic-roughness material
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<GltfTextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<GltfTextureRef>,
+    pub normal_texture: Option<GltfTextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<GltfTextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<GltfTextureRef>,
+    pub emissive_factor: [f32; 3],
+    pub alpha_mode: GltfAlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfSampler {
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfTexture {
+    pub name: Option<String>,
+    /// Index in [`GltfScene::images`]
+    pub image: usize,
+    pub sampler: GltfSampler,
+}
+
+/// Encoded image, embedded or read next to the file
+#[derive(Debug, Clone)]
+pub struct GltfImage {
+    pub name: Option<String>,
+    pub uri: Option<String>,
+    pub mime_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfSkin {
+    pub name: Option<String>,
+    /// Indices in [`GltfScene::nodes`]
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GltfInterpolation {
+    Linear,
+    Step,
+    /// Values are stored as in-tangent, value, out-tangent triplets
+    CubicSpline,
+}
+
+#[derive(Debug, Clone)]
+pub enum GltfAnimationValues {
+    Translations(Vec<Vec3>),
+    Rotations(Vec<Quat>),
+    Scales(Vec<Vec3>),
+    /// One weight per morph target per keyframe
+    MorphWeights(Vec<f32>),
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfChannel {
+    /// Index in [`GltfScene::nodes`]
+    pub node: usize,
+    pub interpolation: GltfInterpolation,
+    pub times: Vec<f32>,
+    pub values: GltfAnimationValues,
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfAnimation {
+    pub name: Option<String>,
+    pub channels: Vec<GltfChannel>,
+}
+
+impl GltfAnimation {
+    /// Time of the last keyframe, in seconds
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |duration, time| duration.max(*time))
+    }
+}
+
+/// Content of a glTF file. Nodes of the default scene come first.
+#[derive(Debug, Default)]
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    /// Cameras, referenced by nodes. Updated with the world matrix of their node.
+    pub cameras: Vec<Camera>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub images: Vec<GltfImage>,
+    pub skins: Vec<GltfSkin>,
+    pub animations: Vec<GltfAnimation>,
+}
+
+impl GltfNode {
+    pub fn new(name: Option<String>, node: Node) -> Self {
+        Self {
+            name,
+            node,
+            parent: None,
+            children: Vec::new(),
+            mesh: None,
+            camera: None,
+            skin: None,
+            weights: None,
+        }
+    }
+}
+
+impl GltfScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a node under `parent`, or as a root. Parents must be added first.
+    pub fn add_node(&mut self, mut node: GltfNode, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        node.parent = parent;
+        match parent {
+            Some(parent) => self.nodes[parent].children.push(index),
+            None => self.roots.push(index),
+        }
+        self.nodes.push(node);
+        index
+    }
+
+    /// Append a mesh with one primitive per geometry
+    pub fn add_mesh(&mut self, name: Option<String>, geometries: Vec<GeometryData>) -> usize {
+        self.meshes.push(GltfMesh {
+            name,
+            primitives: geometries
+                .into_iter()
+                .map(|geometry| GltfPrimitive {
+                    geometry,
+                    material: None,
+                })
+                .collect(),
+            weights: Vec::new(),
+        });
+        self.meshes.len() - 1
+    }
+
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.as_deref() == Some(name))
+    }
+
+    /// Update every world matrix from the roots down, cameras included
+    pub fn update_world_matrices(&mut self) {
+        for index in 0..self.nodes.len() {
+            let (parents, nodes) = self.nodes.split_at_mut(index);
+            let node = &mut nodes[0];
+            let parent = node.parent.map(|parent| &parents[parent].node);
+            node.node.update_world_matrix(parent);
+
+            if let Some(camera) = node.camera.and_then(|camera| self.cameras.get_mut(camera)) {
+                camera.node.set_transform(node.node.get_transform().clone());
+                camera.update_projection();
+                camera.update_world_matrix(parent);
+            }
+        }
+    }
+}
+
+/// Load a `.gltf` or `.glb` file. External buffers and images are read relative to it.
+pub fn load_gltf<P>(path: P) -> Result<GltfScene, GltfError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let bytes = read_file(path)?;
+    load_gltf_from_slice(&bytes, path.parent())
+}
+
+/// Load a `.gltf` or `.glb` file from memory. External buffers and images are read
+/// relative to `base`, and refused without it.
+pub fn load_gltf_from_slice(bytes: &[u8], base: Option<&Path>) -> Result<GltfScene, GltfError> {
+    let Gltf { document, blob } = Gltf::from_slice(bytes)?;
+    let buffers = load_buffers(&document, blob, base)?;
+    let images = load_images(&document, &buffers, base)?;
+
+    let (nodes, roots, node_map) = load_nodes(&document)?;
+    let mut scene = GltfScene {
+        nodes,
+        roots,
+        meshes: load_meshes(&document, &buffers)?,
+        cameras: document.cameras().map(load_camera).collect(),
+        materials: document.materials().map(load_material).collect(),
+        textures: document.textures().map(load_texture).collect(),
+        images,
+        skins: document
+            .skins()
+            .map(|skin| load_skin(skin, &buffers, &node_map))
+            .collect(),
+        animations: document
+            .animations()
+            .map(|animation| load_animation(animation, &buffers, &node_map))
+            .collect(),
+    };
+    scene.update_world_matrices();
+    Ok(scene)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, GltfError> {
+    std::fs::read(path).map_err(|error| GltfError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Decode the `%XX` escapes of a URI
+fn percent_decode(uri: &str) -> Option<String> {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = bytes.get(index + 1..index + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Path of a relative URI, `None` when it leaves the directory it is relative to
+fn relative_path(uri: &str) -> Option<PathBuf> {
+    if uri.contains("://") {
+        return None;
+    }
+    let path = PathBuf::from(percent_decode(uri)?);
+    path.components()
+        .all(|component| {
+            matches!(
+                component,
+                std::path::Component::Normal(_) | std::path::Component::CurDir
+            )
+        })
+        .then_some(path)
+}
+
+/// Read a data URI or a file relative to `base`
+fn read_uri(uri: &str, base: Option<&Path>) -> Result<Vec<u8>, GltfError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        return match data.split_once(";base64,") {
+            Some((_, encoded)) => Ok(base64::decode(encoded)?),
+            None => Err(GltfError::UnsupportedUri(uri.to_string())),
+        };
+    }
+    match (base, relative_path(uri)) {
+        (Some(base), Some(path)) => read_file(&base.join(path)),
+        _ => Err(GltfError::UnsupportedUri(uri.to_string())),
+    }
+}
+
+fn load_buffers(
+    document: &Document,
+    mut blob: Option<Vec<u8>>,
+    base: Option<&Path>,
+) -> Result<Vec<Vec<u8>>, GltfError> {
+    document
+        .buffers()
+        .map(|buffer| {
+            let mut data = match buffer.source() {
+                buffer::Source::Bin => blob.take().ok_or(GltfError::MissingBlob)?,
+                buffer::Source::Uri(uri) => read_uri(uri, base)?,
+            };
+            if data.len() < buffer.length() {
+                return Err(GltfError::BufferTooSmall {
+                    buffer: buffer.index(),
+                    expected: buffer.length(),
+                    found: data.len(),
+                });
+            }
+            // The binary chunk may be padded
+            data.truncate(buffer.length());
+            Ok(data)
+        })
+        .collect()
+}
+
+fn load_images(
+    document: &Document,
+    buffers: &[Vec<u8>],
+    base: Option<&Path>,
+) -> Result<Vec<GltfImage>, GltfError> {
+    document
+        .images()
+        .map(|image| {
+            let name = image.name().map(String::from);
+            Ok(match image.source() {
+                image::Source::View { view, mime_type } => {
+                    let buffer = view.buffer().index();
+                    let found = buffers.get(buffer).map_or(0, Vec::len);
+                    let end = view.offset().saturating_add(view.length());
+                    let data = buffers
+                        .get(buffer)
+                        .and_then(|bytes| bytes.get(view.offset()..end))
+                        .ok_or(GltfError::BufferTooSmall {
+                            buffer,
+                            expected: end,
+                            found,
+                        })?
+                        .to_vec();
+                    GltfImage {
+                        name,
+                        uri: None,
+                        mime_type: Some(mime_type.to_string()),
+                        data,
+                    }
+                }
+                image::Source::Uri { uri, mime_type } => GltfImage {
+                    name,
+                    uri: Some(uri.to_string()),
+                    mime_type: mime_type.map(String::from),
+                    data: read_uri(uri, base)?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Flattened nodes, root nodes and the flattened index of each document node
+type FlatNodes = (Vec<GltfNode>, Vec<usize>, Vec<usize>);
+
+/// Flatten the node tree depth first so parents come before children. Nodes of the
+/// default scene come first, then nodes of other scenes and orphans.
+fn load_nodes(document: &Document) -> Result<FlatNodes, GltfError> {
+    let mut node_map = vec![usize::MAX; document.nodes().len()];
+    let mut nodes = Vec::with_capacity(node_map.len());
+
+    let mut is_child = vec![false; node_map.len()];
+    for node in document.nodes() {
+        for child in node.children() {
+            is_child[child.index()] = true;
+        }
+    }
+
+    let mut roots = Vec::new();
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next());
+    if let Some(scene) = scene {
+        for node in scene.nodes() {
+            if node_map[node.index()] == usize::MAX {
+                roots.push(push_node(node, None, &mut nodes, &mut node_map));
+            }
+        }
+    }
+    for node in document.nodes() {
+        if !is_child[node.index()] && node_map[node.index()] == usize::MAX {
+            push_node(node, None, &mut nodes, &mut node_map);
+        }
+    }
+
+    // Nodes of a cycle are all children and never reached from a root
+    if let Some(node) = node_map.iter().position(|index| *index == usize::MAX) {
+        return Err(GltfError::NodeCycle { node });
+    }
+    Ok((nodes, roots, node_map))
+}
+
+fn push_node(
+    node: ::gltf::Node,
+    parent: Option<usize>,
+    nodes: &mut Vec<GltfNode>,
+    node_map: &mut [usize],
+) -> usize {
+    let index = nodes.len();
+    node_map[node.index()] = index;
+
+    let (translation, rotation, scale) = node.transform().decomposed();
+    let mut transform = Node::new();
+    transform.set_transform(Transform {
+        translation: Vec3::from(translation),
+        rotation: Quat::from_array(rotation),
+        scale: Vec3::from(scale),
+    });
+
+    nodes.push(GltfNode {
+        name: node.name().map(String::from),
+        node: transform,
+        parent,
+        children: Vec::new(),
+        mesh: node.mesh().map(|mesh| mesh.index()),
+        camera: node.camera().map(|camera| camera.index()),
+        skin: node.skin().map(|skin| skin.index()),
+        weights: node.weights().map(|weights| weights.to_vec()),
+    });
+
+    for child in node.children() {
+        // A malformed file could share a child between parents
+        if node_map[child.index()] == usize::MAX {
+            let child = push_node(child, Some(index), nodes, node_map);
+            nodes[index].children.push(child);
+        }
+    }
+    index
+}
+
+fn load_meshes(document: &Document, buffers: &[Vec<u8>]) -> Result<Vec<GltfMesh>, GltfError> {
+    document
+        .meshes()
+        .map(|mesh| {
+            let target_names = morph_target_names(&mesh);
+            let primitives = mesh
+                .primitives()
+                .map(|primitive| {
+                    let geometry = load_geometry(&primitive, buffers, &target_names).ok_or(
+                        GltfError::MissingPositions {
+                            mesh: mesh.index(),
+                            primitive: primitive.index(),
+                        },
+                    )?;
+                    geometry.validate().map_err(|error| GltfError::Geometry {
+                        mesh: mesh.index(),
+                        primitive: primitive.index(),
+                        error,
+                    })?;
+                    Ok(GltfPrimitive {
+                        geometry,
+                        material: primitive.material().index(),
+                    })
+                })
+                .collect::<Result<Vec<_>, GltfError>>()?;
+
+            Ok(GltfMesh {
+                name: mesh.name().map(String::from),
+                primitives,
+                weights: mesh
+                    .weights()
+                    .map(|weights| weights.to_vec())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Names stored by most exporters in `mesh.extras.targetNames`
+fn morph_target_names(mesh: &mesh::Mesh) -> Vec<String> {
+    mesh.extras()
+        .as_ref()
+        .and_then(|extras| serde_json::from_str::<serde_json::Value>(extras.get()).ok())
+        .and_then(|extras| {
+            extras.get("targetNames").and_then(|names| {
+                names.as_array().map(|names| {
+                    names
+                        .iter()
+                        .map(|name| name.as_str().unwrap_or_default().to_string())
+                        .collect()
+                })
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Name of the attribute for a set, `tex_coord`, `tex_coord_1`...
+fn set_name(name: &str, set: u32) -> String {
+    if set == 0 {
+        name.to_string()
+    } else {
+        format!("{name}_{set}")
+    }
+}
+
+fn load_geometry(
+    primitive: &mesh::Primitive,
+    buffers: &[Vec<u8>],
+    target_names: &[String],
+) -> Option<GeometryData> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let mut data = GeometryData::new();
+
+    let positions = reader.read_positions()?.collect::<Vec<_>>();
+    // Other attributes may be shorter in broken files, validation reports it
+    let vertex_count = positions.len() as u32;
+    data.set_attribute(
+        GeometryData::ATTRIBUTE_POSITION,
+        VertexValues::Float32x3(positions),
+    );
+    if let Some(values) = reader.read_normals() {
+        data.set_attribute(
+            GeometryData::ATTRIBUTE_NORMAL,
+            VertexValues::Float32x3(values.collect()),
+        );
+    }
+    if let Some(values) = reader.read_tangents() {
+        data.set_attribute(
+            GeometryData::ATTRIBUTE_TANGENT,
+            VertexValues::Float32x4(values.collect()),
+        );
+    }
+
+    let mut set = 0;
+    while let Some(values) = reader.read_tex_coords(set) {
+        data.set_attribute(
+            set_name(GeometryData::ATTRIBUTE_TEX_COORD, set),
+            VertexValues::Float32x2(values.into_f32().collect()),
+        );
+        set += 1;
+    }
+    let mut set = 0;
+    while let Some(values) = reader.read_colors(set) {
+        data.set_attribute(
+            set_name(GeometryData::ATTRIBUTE_COLOR, set),
+            VertexValues::Float32x4(values.into_rgba_f32().collect()),
+        );
+        set += 1;
+    }
+    let mut set = 0;
+    while let Some(values) = reader.read_joints(set) {
+        let joints = values
+            .into_u16()
+            .map(|joints| joints.map(|joint| joint as f32))
+            .collect();
+        data.set_attribute(
+            set_name(GeometryData::ATTRIBUTE_JOINTS, set),
+            VertexValues::Float32x4(joints),
+        );
+        set += 1;
+    }
+    let mut set = 0;
+    while let Some(values) = reader.read_weights(set) {
+        data.set_attribute(
+            set_name(GeometryData::ATTRIBUTE_WEIGHTS, set),
+            VertexValues::Float32x4(values.into_f32().collect()),
+        );
+        set += 1;
+    }
+
+    // Application specific attributes, `_FOO` is named `foo`
+    for (semantic, accessor) in primitive.attributes() {
+        if let mesh::Semantic::Extras(name) = semantic {
+            let get = |buffer: ::gltf::Buffer| buffers.get(buffer.index()).map(Vec::as_slice);
+            if accessor.data_type() != accessor::DataType::F32 {
+                continue;
+            }
+            let values =
+                match accessor.dimensions() {
+                    accessor::Dimensions::Scalar => accessor::Iter::new(accessor, get)
+                        .map(|v| VertexValues::Float32(v.collect())),
+                    accessor::Dimensions::Vec2 => accessor::Iter::new(accessor, get)
+                        .map(|v| VertexValues::Float32x2(v.collect())),
+                    accessor::Dimensions::Vec3 => accessor::Iter::new(accessor, get)
+                        .map(|v| VertexValues::Float32x3(v.collect())),
+                    accessor::Dimensions::Vec4 => accessor::Iter::new(accessor, get)
+                        .map(|v| VertexValues::Float32x4(v.collect())),
+                    _ => None,
+                };
+            if let Some(values) = values {
+                data.set_attribute(name.to_lowercase(), values);
+            }
+        }
+    }
+
+    for (index, (positions, normals, tangents)) in reader.read_morph_targets().enumerate() {
+        let name = target_names
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| index.to_string());
+        data.add_morph_target(MorphTarget {
+            name,
+            positions: positions.map(Iterator::collect),
+            normals: normals.map(Iterator::collect),
+            tangents: tangents.map(Iterator::collect),
+        });
+    }
+
+    let indices = reader
+        .read_indices()
+        .map(|indices| indices.into_u32().collect::<Vec<_>>());
+    let (topology, indices) = match primitive.mode() {
+        mesh::Mode::Points => (wgpu::PrimitiveTopology::PointList, indices),
+        mesh::Mode::Lines => (wgpu::PrimitiveTopology::LineList, indices),
+        mesh::Mode::LineStrip => (wgpu::PrimitiveTopology::LineStrip, indices),
+        mesh::Mode::Triangles => (wgpu::PrimitiveTopology::TriangleList, indices),
+        mesh::Mode::TriangleStrip => (wgpu::PrimitiveTopology::TriangleStrip, indices),
+        mesh::Mode::LineLoop => {
+            // Close the loop on a strip
+            let mut indices = indices.unwrap_or_else(|| (0..vertex_count).collect());
+            if let Some(first) = indices.first().copied() {
+                indices.push(first);
+            }
+            (wgpu::PrimitiveTopology::LineStrip, Some(indices))
+        }
+        mesh::Mode::TriangleFan => {
+            let fan = indices.unwrap_or_else(|| (0..vertex_count).collect());
+            let mut indices = Vec::with_capacity(fan.len().saturating_sub(2) * 3);
+            for window in fan.windows(2).skip(1) {
+                indices.extend([fan[0], window[0], window[1]]);
+            }
+            (wgpu::PrimitiveTopology::TriangleList, Some(indices))
+        }
+    };
+    data.set_topology(topology);
+    data.set_indices(indices.map(IndiceValues::auto));
+
+    Some(data)
+}
+
+fn load_camera(camera: camera::Camera) -> Camera {
+    let mut result = Camera::new();
+    *result.get_mut_projection() = match camera.projection() {
+        camera::Projection::Perspective(perspective) => {
+            CameraProjection::Perspective(PerspectiveOptions {
+                fovy: perspective.yfov(),
+                aspect: perspective.aspect_ratio().unwrap_or(1.0),
+                near: perspective.znear(),
+                // No far plane means an infinite projection
+                far: perspective.zfar().unwrap_or(f32::MAX),
+            })
+        }
+        camera::Projection::Orthographic(orthographic) => {
+            CameraProjection::Orthographic(OrthographicOptions {
+                left: -orthographic.xmag(),
+                right: orthographic.xmag(),
+                bottom: -orthographic.ymag(),
+                top: orthographic.ymag(),
+                near: orthographic.znear(),
+                far: orthographic.zfar(),
+                zoom: 1.0,
+            })
+        }
+    };
+    result
+}
+
+fn texture_ref(info: Option<texture::Info>) -> Option<GltfTextureRef> {
+    info.map(|info| GltfTextureRef {
+        texture: info.texture().index(),
+        tex_coord: info.tex_coord(),
+    })
+}
+
+fn load_material(material: material::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+    let normal = material.normal_texture();
+    let occlusion = material.occlusion_texture();
+
+    GltfMaterial {
+        name: material.name().map(String::from),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_texture: texture_ref(pbr.base_color_texture()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        metallic_roughness_texture: texture_ref(pbr.metallic_roughness_texture()),
+        normal_texture: normal.as_ref().map(|normal| GltfTextureRef {
+            texture: normal.texture().index(),
+            tex_coord: normal.tex_coord(),
+        }),
+        normal_scale: normal.as_ref().map(|normal| normal.scale()).unwrap_or(1.0),
+        occlusion_texture: occlusion.as_ref().map(|occlusion| GltfTextureRef {
+            texture: occlusion.texture().index(),
+            tex_coord: occlusion.tex_coord(),
+        }),
+        occlusion_strength: occlusion
+            .as_ref()
+            .map(|occlusion| occlusion.strength())
+            .unwrap_or(1.0),
+        emissive_texture: texture_ref(material.emissive_texture()),
+        emissive_factor: material.emissive_factor(),
+        alpha_mode: match material.alpha_mode() {
+            material::AlphaMode::Opaque => GltfAlphaMode::Opaque,
+            material::AlphaMode::Mask => GltfAlphaMode::Mask,
+            material::AlphaMode::Blend => GltfAlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        double_sided: material.double_sided(),
+    }
+}
+
+fn address_mode(mode: texture::WrappingMode) -> wgpu::AddressMode {
+    match mode {
+        texture::WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        texture::WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        texture::WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    }
+}
+
+fn load_texture(texture: texture::Texture) -> GltfTexture {
+    let sampler = texture.sampler();
+    let mag_filter = match sampler.mag_filter() {
+        Some(texture::MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(texture::MinFilter::Nearest) | Some(texture::MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        }
+        Some(texture::MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear)
+        }
+        Some(texture::MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+        }
+        _ => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear),
+    };
+
+    GltfTexture {
+        name: texture.name().map(String::from),
+        image: texture.source().index(),
+        sampler: GltfSampler {
+            mag_filter,
+            min_filter,
+            mipmap_filter,
+            address_mode_u: address_mode(sampler.wrap_s()),
+            address_mode_v: address_mode(sampler.wrap_t()),
+        },
+    }
+}
+
+fn load_skin(skin: ::gltf::Skin, buffers: &[Vec<u8>], node_map: &[usize]) -> GltfSkin {
+    let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+    let joints = skin
+        .joints()
+        .map(|joint| node_map[joint.index()])
+        .collect::<Vec<_>>();
+    let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+        Some(matrices) => matrices
+            .map(|matrix| Mat4::from_cols_array_2d(&matrix))
+            .collect(),
+        None => vec![Mat4::IDENTITY; joints.len()],
+    };
+
+    GltfSkin {
+        name: skin.name().map(String::from),
+        joints,
+        inverse_bind_matrices,
+        skeleton: skin.skeleton().map(|node| node_map[node.index()]),
+    }
+}
+
+fn load_animation(
+    animation: ::gltf::Animation,
+    buffers: &[Vec<u8>],
+    node_map: &[usize],
+) -> GltfAnimation {
+    let channels = animation
+        .channels()
+        .filter_map(|channel| {
+            let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let times = reader.read_inputs()?.collect();
+            let values = match reader.read_outputs()? {
+                animation::util::ReadOutputs::Translations(values) => {
+                    GltfAnimationValues::Translations(values.map(Vec3::from).collect())
+                }
+                animation::util::ReadOutputs::Rotations(values) => GltfAnimationValues::Rotations(
+                    values.into_f32().map(Quat::from_array).collect(),
+                ),
+                animation::util::ReadOutputs::Scales(values) => {
+                    GltfAnimationValues::Scales(values.map(Vec3::from).collect())
+                }
+                animation::util::ReadOutputs::MorphTargetWeights(values) => {
+                    GltfAnimationValues::MorphWeights(values.into_f32().collect())
+                }
+            };
+
+            Some(GltfChannel {
+                node: node_map[channel.target().node().index()],
+                interpolation: match channel.sampler().interpolation() {
+                    animation::Interpolation::Linear => GltfInterpolation::Linear,
+                    animation::Interpolation::Step => GltfInterpolation::Step,
+                    animation::Interpolation::CubicSpline => GltfInterpolation::CubicSpline,
+                },
+                times,
+                values,
+            })
+        })
+        .collect();
+
+    GltfAnimation {
+        name: animation.name().map(String::from),
+        channels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Triangle with 16-bit indices in a data URI, drawn by the child of a root node
+    fn triangle(nodes: &str) -> String {
+        let mut buffer = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2, 0] {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&buffer)
+        );
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": {nodes},
+                "meshes": [{{
+                    "name": "triangle",
+                    "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}]
+                }}],
+                "buffers": [{{ "byteLength": 44, "uri": "{uri}" }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "accessors": [
+                    {{
+                        "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                        "min": [0, 0, 0], "max": [1, 1, 0]
+                    }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ]
+            }}"#
+        )
+    }
+
+    #[test]
+    fn embedded_triangle() {
+        let nodes = r#"[
+            { "name": "root", "children": [1], "translation": [1, 0, 0] },
+            { "name": "child", "mesh": 0, "scale": [2, 2, 2] }
+        ]"#;
+        let scene = load_gltf_from_slice(triangle(nodes).as_bytes(), None).unwrap();
+
+        assert_eq!(scene.roots, [0]);
+        assert_eq!(scene.find_node("child"), Some(1));
+        assert_eq!(scene.nodes[1].parent, Some(0));
+        assert_eq!(scene.nodes[0].children, [1]);
+        assert_eq!(scene.nodes[1].mesh, Some(0));
+        assert_eq!(scene.nodes[0].node.get_transform().translation, Vec3::X);
+        assert_eq!(scene.nodes[1].node.get_transform().scale, Vec3::splat(2.0));
+
+        let mesh = &scene.meshes[0];
+        assert_eq!(mesh.name.as_deref(), Some("triangle"));
+        let geometry = &mesh.primitives[0].geometry;
+        let positions = geometry
+            .attribute(GeometryData::ATTRIBUTE_POSITION)
+            .unwrap();
+        assert_eq!(
+            positions.as_floats(),
+            [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+        let indices = geometry.indices().unwrap();
+        assert_eq!(indices.index_format(), wgpu::IndexFormat::Uint16);
+        assert_eq!(indices.iter().collect::<Vec<_>>(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn node_cycles_are_rejected() {
+        let nodes = r#"[{ "children": [] }, { "children": [2] }, { "children": [1] }]"#;
+        assert!(matches!(
+            load_gltf_from_slice(triangle(nodes).as_bytes(), None),
+            Err(GltfError::NodeCycle { node: 1 })
+        ));
+    }
+
+    #[test]
+    fn uris_stay_in_the_directory() {
+        assert_eq!(
+            relative_path("textures/my%20image.png"),
+            Some(PathBuf::from("textures/my image.png"))
+        );
+        assert_eq!(relative_path("./a.bin"), Some(PathBuf::from("./a.bin")));
+        for uri in [
+            "../a.bin",
+            "textures/../../a.bin",
+            "/etc/passwd",
+            "%2E%2E/a.bin",
+        ] {
+            assert_eq!(relative_path(uri), None, "{uri}");
+        }
+        assert_eq!(relative_path("https://example.com/a.bin"), None);
+        assert_eq!(relative_path("a%2"), None);
+        assert!(matches!(
+            read_uri("../a.bin", Some(Path::new("."))),
+            Err(GltfError::UnsupportedUri(_))
+        ));
+    }
+}
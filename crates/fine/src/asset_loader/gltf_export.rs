@@ -0,0 +1,770 @@
+use super::{
+    GltfAlphaMode, GltfAnimationValues, GltfError, GltfInterpolation, GltfScene, GltfTextureRef,
+};
+use crate::camera::CameraProjection;
+use render::prelude::{wgpu, GeometryData, IndiceValues, VertexValues};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Accumulates the binary buffer, its views and accessors
+#[derive(Default)]
+struct BufferWriter {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl BufferWriter {
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        // Accessors need offsets aligned to their component size
+        self.data.resize(self.data.len().div_ceil(4) * 4, 0);
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.data.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    fn push_accessor(
+        &mut self,
+        bytes: &[u8],
+        component_type: u32,
+        count: usize,
+        ty: &str,
+        target: Option<u32>,
+    ) -> usize {
+        let view = self.push_view(bytes, target);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": ty,
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// Push floats, `components` per element, with their bounds
+    fn push_floats(
+        &mut self,
+        values: &[f32],
+        components: usize,
+        target: Option<u32>,
+        bounds: bool,
+    ) -> usize {
+        let ty = match components {
+            1 => "SCALAR",
+            2 => "VEC2",
+            3 => "VEC3",
+            4 => "VEC4",
+            _ => "MAT4",
+        };
+        let count = values.len() / components;
+        let accessor = self.push_accessor(bytemuck::cast_slice(values), FLOAT, count, ty, target);
+        if bounds && count > 0 {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+            for element in values.chunks_exact(components) {
+                for (component, value) in element.iter().enumerate() {
+                    min[component] = min[component].min(*value);
+                    max[component] = max[component].max(*value);
+                }
+            }
+            self.accessors[accessor]["min"] = json!(min);
+            self.accessors[accessor]["max"] = json!(max);
+        }
+        accessor
+    }
+
+    fn push_indices(&mut self, indices: &IndiceValues) -> usize {
+        let component_type = match indices.index_format() {
+            wgpu::IndexFormat::Uint16 => UNSIGNED_SHORT,
+            wgpu::IndexFormat::Uint32 => UNSIGNED_INT,
+        };
+        self.push_accessor(
+            indices.get_bytes(),
+            component_type,
+            indices.len(),
+            "SCALAR",
+            Some(ELEMENT_ARRAY_BUFFER),
+        )
+    }
+}
+
+/// glTF semantic of an attribute name, `tex_coord_1` is `TEXCOORD_1`,
+/// unknown names are written as application specific `_NAME`
+fn semantic(name: &str) -> String {
+    let (base, set) = match name.rsplit_once('_') {
+        Some((base, set)) if set.chars().all(|c| c.is_ascii_digit()) => (base, set),
+        _ => (name, "0"),
+    };
+    match base {
+        GeometryData::ATTRIBUTE_POSITION if set == "0" => "POSITION".to_string(),
+        GeometryData::ATTRIBUTE_NORMAL if set == "0" => "NORMAL".to_string(),
+        GeometryData::ATTRIBUTE_TANGENT if set == "0" => "TANGENT".to_string(),
+        GeometryData::ATTRIBUTE_TEX_COORD => format!("TEXCOORD_{set}"),
+        GeometryData::ATTRIBUTE_COLOR => format!("COLOR_{set}"),
+        GeometryData::ATTRIBUTE_JOINTS => format!("JOINTS_{set}"),
+        GeometryData::ATTRIBUTE_WEIGHTS => format!("WEIGHTS_{set}"),
+        _ => format!("_{}", name.to_uppercase()),
+    }
+}
+
+/// Values of `components` floats widened to four, zeros after them
+fn pad_to_vec4(values: &[f32], components: usize) -> Vec<f32> {
+    values
+        .chunks_exact(components)
+        .flat_map(|value| {
+            let mut padded = [0.0; 4];
+            padded[..components].copy_from_slice(value);
+            padded
+        })
+        .collect()
+}
+
+fn write_attribute(writer: &mut BufferWriter, semantic: &str, values: &VertexValues) -> usize {
+    let components = values.components();
+    // Skins influence VEC4 only, padded joints have no weight
+    if semantic.starts_with("JOINTS_") {
+        let joints = pad_to_vec4(values.as_floats(), components)
+            .into_iter()
+            .map(|joint| joint as u16)
+            .collect::<Vec<_>>();
+        return writer.push_accessor(
+            bytemuck::cast_slice(&joints),
+            UNSIGNED_SHORT,
+            joints.len() / 4,
+            "VEC4",
+            Some(ARRAY_BUFFER),
+        );
+    }
+    if semantic.starts_with("WEIGHTS_") && components != 4 {
+        let weights = pad_to_vec4(values.as_floats(), components);
+        return writer.push_floats(&weights, 4, Some(ARRAY_BUFFER), false);
+    }
+    if semantic == "TANGENT" && components == 3 {
+        // Tangents need their handedness
+        let tangents = values
+            .as_floats()
+            .chunks_exact(3)
+            .flat_map(|t| [t[0], t[1], t[2], 1.0])
+            .collect::<Vec<_>>();
+        return writer.push_floats(&tangents, 4, Some(ARRAY_BUFFER), false);
+    }
+    writer.push_floats(
+        values.as_floats(),
+        components,
+        Some(ARRAY_BUFFER),
+        semantic == "POSITION",
+    )
+}
+
+/// Whether indices actually contain a primitive restart
+fn has_restart(geometry: &GeometryData) -> bool {
+    geometry.has_restart()
+        && geometry
+            .indices()
+            .map(|indices| indices.iter().any(|index| index == indices.restart_value()))
+            .unwrap_or(false)
+}
+
+fn write_primitive(
+    writer: &mut BufferWriter,
+    geometry: &GeometryData,
+    material: Option<usize>,
+) -> Value {
+    let mut attributes = Map::new();
+    for (name, values) in geometry.attributes() {
+        let semantic = semantic(name);
+        let accessor = write_attribute(writer, &semantic, values);
+        attributes.insert(semantic, json!(accessor));
+    }
+
+    // glTF has no primitive restart, strips with restarts are written as lists
+    let (mode, indices) = match geometry.topology() {
+        wgpu::PrimitiveTopology::PointList => (0, geometry.indices().cloned()),
+        wgpu::PrimitiveTopology::LineList => (1, geometry.indices().cloned()),
+        wgpu::PrimitiveTopology::LineStrip if has_restart(geometry) => {
+            (1, Some(IndiceValues::auto(geometry.line_list_indices())))
+        }
+        wgpu::PrimitiveTopology::LineStrip => (3, geometry.indices().cloned()),
+        wgpu::PrimitiveTopology::TriangleList => (4, geometry.indices().cloned()),
+        wgpu::PrimitiveTopology::TriangleStrip if has_restart(geometry) => (
+            4,
+            Some(IndiceValues::auto(geometry.triangle_list_indices())),
+        ),
+        wgpu::PrimitiveTopology::TriangleStrip => (5, geometry.indices().cloned()),
+    };
+
+    let mut primitive = json!({ "attributes": attributes, "mode": mode });
+    if let Some(indices) = indices {
+        primitive["indices"] = json!(writer.push_indices(&indices));
+    }
+    if let Some(material) = material {
+        primitive["material"] = json!(material);
+    }
+
+    let targets = geometry
+        .morph_targets()
+        .iter()
+        .map(|target| {
+            let mut attributes = Map::new();
+            for (name, deltas) in target.deltas() {
+                let floats = bytemuck::cast_slice(deltas);
+                let bounds = name == GeometryData::ATTRIBUTE_POSITION;
+                let accessor = writer.push_floats(floats, 3, Some(ARRAY_BUFFER), bounds);
+                attributes.insert(semantic(name), json!(accessor));
+            }
+            Value::Object(attributes)
+        })
+        .collect::<Vec<_>>();
+    if !targets.is_empty() {
+        primitive["targets"] = json!(targets);
+    }
+    primitive
+}
+
+fn texture_info(texture: &Option<GltfTextureRef>) -> Option<Value> {
+    texture.map(|texture| json!({ "index": texture.texture, "texCoord": texture.tex_coord }))
+}
+
+/// Remove null values so optional properties are omitted
+fn compact(mut value: Value) -> Value {
+    if let Value::Object(map) = &mut value {
+        map.retain(|_, value| !value.is_null());
+    }
+    value
+}
+
+fn filter(mode: wgpu::FilterMode) -> u32 {
+    match mode {
+        wgpu::FilterMode::Nearest => 9728,
+        wgpu::FilterMode::Linear => 9729,
+    }
+}
+
+fn min_filter(min: wgpu::FilterMode, mipmap: wgpu::FilterMode) -> u32 {
+    match (min, mipmap) {
+        (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest) => 9984,
+        (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest) => 9985,
+        (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear) => 9986,
+        (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear) => 9987,
+    }
+}
+
+fn wrap(mode: wgpu::AddressMode) -> u32 {
+    match mode {
+        wgpu::AddressMode::ClampToEdge | wgpu::AddressMode::ClampToBorder => 33071,
+        wgpu::AddressMode::MirrorRepeat => 33648,
+        wgpu::AddressMode::Repeat => 10497,
+    }
+}
+
+/// Build the JSON document and its binary buffer. The buffer is left without `uri`.
+fn write_document(scene: &GltfScene) -> (Value, Vec<u8>) {
+    let mut writer = BufferWriter::default();
+
+    let nodes = scene
+        .nodes
+        .iter()
+        .map(|node| {
+            let transform = node.node.get_transform();
+            compact(json!({
+                "name": node.name,
+                "translation": transform.translation.to_array(),
+                "rotation": transform.rotation.to_array(),
+                "scale": transform.scale.to_array(),
+                "children": (!node.children.is_empty()).then_some(&node.children),
+                "mesh": node.mesh,
+                "camera": node.camera,
+                "skin": node.skin,
+                "weights": node.weights,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let meshes = scene
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let primitives = mesh
+                .primitives
+                .iter()
+                .map(|primitive| {
+                    write_primitive(&mut writer, &primitive.geometry, primitive.material)
+                })
+                .collect::<Vec<_>>();
+            let target_names = mesh.primitives.first().map(|primitive| {
+                primitive
+                    .geometry
+                    .morph_targets()
+                    .iter()
+                    .map(|target| target.name.clone())
+                    .collect::<Vec<_>>()
+            });
+            compact(json!({
+                "name": mesh.name,
+                "primitives": primitives,
+                "weights": (!mesh.weights.is_empty()).then_some(&mesh.weights),
+                "extras": target_names
+                    .filter(|names| !names.is_empty())
+                    .map(|names| json!({ "targetNames": names })),
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let cameras = scene
+        .cameras
+        .iter()
+        .map(|camera| match camera.get_projection() {
+            CameraProjection::Perspective(options) => json!({
+                "type": "perspective",
+                "perspective": compact(json!({
+                    "yfov": options.fovy,
+                    "aspectRatio": options.aspect,
+                    "znear": options.near,
+                    "zfar": (options.far != f32::MAX).then_some(options.far),
+                })),
+            }),
+            CameraProjection::Orthographic(options) => json!({
+                "type": "orthographic",
+                "orthographic": {
+                    "xmag": (options.right - options.left) / 2.0 / options.zoom,
+                    "ymag": (options.top - options.bottom) / 2.0 / options.zoom,
+                    "znear": options.near,
+                    "zfar": options.far,
+                },
+            }),
+        })
+        .collect::<Vec<_>>();
+
+    let materials = scene
+        .materials
+        .iter()
+        .map(|material| {
+            compact(json!({
+                "name": material.name,
+                "pbrMetallicRoughness": compact(json!({
+                    "baseColorFactor": material.base_color_factor,
+                    "baseColorTexture": texture_info(&material.base_color_texture),
+                    "metallicFactor": material.metallic_factor,
+                    "roughnessFactor": material.roughness_factor,
+                    "metallicRoughnessTexture": texture_info(&material.metallic_roughness_texture),
+                })),
+                "normalTexture": texture_info(&material.normal_texture).map(|mut info| {
+                    info["scale"] = json!(material.normal_scale);
+                    info
+                }),
+                "occlusionTexture": texture_info(&material.occlusion_texture).map(|mut info| {
+                    info["strength"] = json!(material.occlusion_strength);
+                    info
+                }),
+                "emissiveTexture": texture_info(&material.emissive_texture),
+                "emissiveFactor": material.emissive_factor,
+                "alphaMode": match material.alpha_mode {
+                    GltfAlphaMode::Opaque => "OPAQUE",
+                    GltfAlphaMode::Mask => "MASK",
+                    GltfAlphaMode::Blend => "BLEND",
+                },
+                "alphaCutoff": (material.alpha_mode == GltfAlphaMode::Mask)
+                    .then_some(material.alpha_cutoff),
+                "doubleSided": material.double_sided,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let samplers = scene
+        .textures
+        .iter()
+        .map(|texture| {
+            let sampler = &texture.sampler;
+            json!({
+                "magFilter": filter(sampler.mag_filter),
+                "minFilter": min_filter(sampler.min_filter, sampler.mipmap_filter),
+                "wrapS": wrap(sampler.address_mode_u),
+                "wrapT": wrap(sampler.address_mode_v),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let textures = scene
+        .textures
+        .iter()
+        .enumerate()
+        .map(|(index, texture)| {
+            compact(json!({
+                "name": texture.name,
+                "sampler": index,
+                "source": texture.image,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let images = scene
+        .images
+        .iter()
+        .map(|image| {
+            let mime_type = image.mime_type.clone().unwrap_or_else(|| {
+                match image.uri.as_deref().and_then(|uri| uri.rsplit_once('.')) {
+                    Some((_, "jpg")) | Some((_, "jpeg")) => "image/jpeg".to_string(),
+                    _ => "image/png".to_string(),
+                }
+            });
+            compact(json!({
+                "name": image.name,
+                "bufferView": writer.push_view(&image.data, None),
+                "mimeType": mime_type,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let skins = scene
+        .skins
+        .iter()
+        .map(|skin| {
+            let matrices = skin
+                .inverse_bind_matrices
+                .iter()
+                .flat_map(|matrix| matrix.to_cols_array())
+                .collect::<Vec<_>>();
+            compact(json!({
+                "name": skin.name,
+                "joints": skin.joints,
+                "skeleton": skin.skeleton,
+                "inverseBindMatrices": writer.push_floats(&matrices, 16, None, false),
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let animations = scene
+        .animations
+        .iter()
+        .map(|animation| {
+            let mut samplers = Vec::new();
+            let mut channels = Vec::new();
+            for channel in animation.channels.iter() {
+                let input = writer.push_floats(&channel.times, 1, None, true);
+                let (path, output) = match &channel.values {
+                    GltfAnimationValues::Translations(values) => (
+                        "translation",
+                        writer.push_floats(bytemuck::cast_slice(values), 3, None, false),
+                    ),
+                    GltfAnimationValues::Rotations(values) => (
+                        "rotation",
+                        writer.push_floats(bytemuck::cast_slice(values), 4, None, false),
+                    ),
+                    GltfAnimationValues::Scales(values) => (
+                        "scale",
+                        writer.push_floats(bytemuck::cast_slice(values), 3, None, false),
+                    ),
+                    GltfAnimationValues::MorphWeights(values) => {
+                        ("weights", writer.push_floats(values, 1, None, false))
+                    }
+                };
+                channels.push(json!({
+                    "sampler": samplers.len(),
+                    "target": { "node": channel.node, "path": path },
+                }));
+                samplers.push(json!({
+                    "input": input,
+                    "output": output,
+                    "interpolation": match channel.interpolation {
+                        GltfInterpolation::Linear => "LINEAR",
+                        GltfInterpolation::Step => "STEP",
+                        GltfInterpolation::CubicSpline => "CUBICSPLINE",
+                    },
+                }));
+            }
+            compact(json!({
+                "name": animation.name,
+                "channels": channels,
+                "samplers": samplers,
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    // Padding keeps the binary chunk aligned
+    writer.data.resize(writer.data.len().div_ceil(4) * 4, 0);
+
+    let mut document = Map::new();
+    document.insert(
+        "asset".to_string(),
+        json!({ "version": "2.0", "generator": "fine" }),
+    );
+    document.insert("scene".to_string(), json!(0));
+    document.insert("scenes".to_string(), json!([{ "nodes": scene.roots }]));
+    let arrays = [
+        ("nodes", nodes),
+        ("meshes", meshes),
+        ("cameras", cameras),
+        ("materials", materials),
+        ("samplers", samplers),
+        ("textures", textures),
+        ("images", images),
+        ("skins", skins),
+        ("animations", animations),
+        ("accessors", writer.accessors),
+        ("bufferViews", writer.views),
+    ];
+    for (name, values) in arrays {
+        // Top level arrays must not be empty
+        if !values.is_empty() {
+            document.insert(name.to_string(), Value::Array(values));
+        }
+    }
+    if !writer.data.is_empty() {
+        document.insert(
+            "buffers".to_string(),
+            json!([{ "byteLength": writer.data.len() }]),
+        );
+    }
+
+    (Value::Object(document), writer.data)
+}
+
+/// Write a `.gltf` document with its buffer embedded as a data URI
+pub fn write_gltf(scene: &GltfScene) -> Result<Vec<u8>, GltfError> {
+    let (mut document, data) = write_document(scene);
+    if let Some(buffer) = document
+        .get_mut("buffers")
+        .and_then(|buffers| buffers.get_mut(0))
+    {
+        buffer["uri"] = json!(format!(
+            "data:application/octet-stream;base64,{}",
+            base64::encode(&data)
+        ));
+    }
+    Ok(serde_json::to_vec_pretty(&document)?)
+}
+
+/// Write a binary `.glb` file
+pub fn write_glb(scene: &GltfScene) -> Result<Vec<u8>, GltfError> {
+    let (document, data) = write_document(scene);
+    let mut json = serde_json::to_vec(&document)?;
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+
+    let mut chunks = 12 + 8 + json.len();
+    if !data.is_empty() {
+        chunks += 8 + data.len();
+    }
+
+    let mut glb = Vec::with_capacity(chunks);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(chunks as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    if !data.is_empty() {
+        glb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&data);
+    }
+    Ok(glb)
+}
+
+/// Save a scene as `.glb` when the path has this extension, `.gltf` otherwise
+pub fn save_gltf<P>(scene: &GltfScene, path: P) -> Result<(), GltfError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let binary = path
+        .extension()
+        .map(|extension| extension.eq_ignore_ascii_case("glb"))
+        .unwrap_or(false);
+    let bytes = if binary {
+        write_glb(scene)?
+    } else {
+        write_gltf(scene)?
+    };
+    std::fs::write(path, bytes).map_err(|error| GltfError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{load_gltf_from_slice, GltfNode};
+    use super::*;
+    use math::{Quat, Vec3};
+    use render::prelude::MorphTarget;
+    use transform::Node;
+
+    fn quad() -> GeometryData {
+        let mut geometry = GeometryData::new();
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_POSITION,
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0f32],
+            ]
+            .into(),
+        );
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_NORMAL,
+            vec![[0.0, 0.0, 1.0f32]; 4].into(),
+        );
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_TEX_COORD,
+            vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0f32]].into(),
+        );
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_COLOR,
+            vec![[1.0, 0.0, 0.0, 1.0f32]; 4].into(),
+        );
+        geometry.set_attribute("temperature", vec![0.0, 0.25, 0.5, 1.0f32].into());
+        geometry.set_indices(Some(IndiceValues::auto(vec![0, 1, 2, 0, 2, 3])));
+        geometry
+    }
+
+    fn large_strip() -> GeometryData {
+        let positions = (0..70_000)
+            .map(|i| [(i / 2) as f32, (i % 2) as f32, 0.0])
+            .collect::<Vec<_>>();
+        let mut geometry = GeometryData::new();
+        geometry.set_attribute(GeometryData::ATTRIBUTE_POSITION, positions.into());
+        geometry.set_indices(Some(IndiceValues::auto((0..70_000).collect())));
+        geometry.set_topology(wgpu::PrimitiveTopology::TriangleStrip);
+        geometry.add_morph_target(
+            MorphTarget::new("lift").with_positions(vec![[0.0, 0.0, 1.0]; 70_000]),
+        );
+        geometry
+    }
+
+    fn scene() -> GltfScene {
+        let mut scene = GltfScene::new();
+        let quad = scene.add_mesh(Some("quad".to_string()), vec![quad()]);
+        let strip = scene.add_mesh(None, vec![large_strip()]);
+
+        let mut node = Node::new();
+        node.get_mut_transform().translation = Vec3::new(1.0, 2.0, 3.0);
+        let root = scene.add_node(GltfNode::new(Some("root".to_string()), node), None);
+
+        let mut node = Node::new();
+        node.get_mut_transform().rotation = Quat::from_rotation_y(0.5);
+        node.get_mut_transform().scale = Vec3::splat(2.0);
+        let mut child = GltfNode::new(Some("child".to_string()), node);
+        child.mesh = Some(quad);
+        let child = scene.add_node(child, Some(root));
+
+        let mut leaf = GltfNode::new(Some("leaf".to_string()), Node::new());
+        leaf.mesh = Some(strip);
+        scene.add_node(leaf, Some(child));
+        scene.add_node(GltfNode::new(Some("other".to_string()), Node::new()), None);
+        scene
+    }
+
+    fn assert_same(a: &GltfScene, b: &GltfScene) {
+        assert_eq!(a.roots, b.roots);
+        assert_eq!(a.nodes.len(), b.nodes.len());
+        for (a, b) in a.nodes.iter().zip(b.nodes.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.parent, b.parent);
+            assert_eq!(a.children, b.children);
+            assert_eq!(a.mesh, b.mesh);
+            let (a, b) = (a.node.get_transform(), b.node.get_transform());
+            assert!(a.translation.abs_diff_eq(b.translation, 1e-6));
+            assert!(a.rotation.abs_diff_eq(b.rotation, 1e-6));
+            assert!(a.scale.abs_diff_eq(b.scale, 1e-6));
+        }
+
+        assert_eq!(a.meshes.len(), b.meshes.len());
+        for (a, b) in a.meshes.iter().zip(b.meshes.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.primitives.len(), b.primitives.len());
+            for (a, b) in a.primitives.iter().zip(b.primitives.iter()) {
+                let (a, b) = (&a.geometry, &b.geometry);
+                assert_eq!(a.topology(), b.topology());
+
+                let mut names_a = a.attribute_names().collect::<Vec<_>>();
+                let mut names_b = b.attribute_names().collect::<Vec<_>>();
+                names_a.sort_unstable();
+                names_b.sort_unstable();
+                assert_eq!(names_a, names_b);
+                for (name, values) in a.attributes() {
+                    let other = b.attribute(name).unwrap();
+                    assert_eq!(values.attribute_format(), other.attribute_format());
+                    assert_eq!(values.as_floats(), other.as_floats(), "{name}");
+                }
+
+                let (ia, ib) = (a.indices().unwrap(), b.indices().unwrap());
+                assert_eq!(ia.index_format(), ib.index_format());
+                assert_eq!(ia.iter().collect::<Vec<_>>(), ib.iter().collect::<Vec<_>>());
+
+                assert_eq!(a.morph_targets().len(), b.morph_targets().len());
+                for (a, b) in a.morph_targets().iter().zip(b.morph_targets()) {
+                    assert_eq!(a.name, b.name);
+                    assert_eq!(a.positions, b.positions);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gltf_round_trip() {
+        let scene = scene();
+        let bytes = write_gltf(&scene).unwrap();
+        let imported = load_gltf_from_slice(&bytes, None).unwrap();
+        assert_same(&scene, &imported);
+
+        let bytes = write_gltf(&imported).unwrap();
+        assert_same(&scene, &load_gltf_from_slice(&bytes, None).unwrap());
+    }
+
+    #[test]
+    fn joints_are_padded_to_vec4() {
+        let mut geometry = quad();
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_JOINTS,
+            vec![[1.0, 2.0f32], [3.0, 0.0], [0.0, 1.0], [2.0, 3.0]].into(),
+        );
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_WEIGHTS,
+            vec![[0.75, 0.25f32], [1.0, 0.0], [0.5, 0.5], [0.0, 1.0]].into(),
+        );
+        let mut scene = GltfScene::new();
+        scene.add_mesh(None, vec![geometry]);
+
+        let imported = load_gltf_from_slice(&write_gltf(&scene).unwrap(), None).unwrap();
+        let geometry = &imported.meshes[0].primitives[0].geometry;
+        let joints = geometry.attribute(GeometryData::ATTRIBUTE_JOINTS).unwrap();
+        assert_eq!(joints.components(), 4);
+        assert_eq!(
+            &joints.as_floats()[..8],
+            [1.0, 2.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0]
+        );
+        let weights = geometry.attribute(GeometryData::ATTRIBUTE_WEIGHTS).unwrap();
+        assert_eq!(&weights.as_floats()[..4], [0.75, 0.25, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn glb_round_trip() {
+        let scene = scene();
+        let bytes = write_glb(&scene).unwrap();
+        assert_eq!(&bytes[0..4], b"glTF");
+        assert_eq!(bytes.len() % 4, 0);
+        let imported = load_gltf_from_slice(&bytes, None).unwrap();
+        assert_same(&scene, &imported);
+
+        let indices = imported.meshes[0].primitives[0].geometry.indices().unwrap();
+        assert_eq!(indices.index_format(), wgpu::IndexFormat::Uint16);
+        let indices = imported.meshes[1].primitives[0].geometry.indices().unwrap();
+        assert_eq!(indices.index_format(), wgpu::IndexFormat::Uint32);
+    }
+}
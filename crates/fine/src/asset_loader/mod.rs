@@ -0,0 +1,12 @@
+mod bmfont;
+mod gltf;
+mod gltf_export;
+mod obj;
+mod ply;
+mod sprite_sheet;
+pub use bmfont::*;
+pub use self::gltf::*;
+pub use gltf_export::*;
+pub use obj::*;
+pub use ply::*;
+pub use sprite_sheet::*;
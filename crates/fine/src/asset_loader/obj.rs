@@ -0,0 +1,500 @@
+use render::prelude::{GeometryData, IndiceValues, VertexValues};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    /// A line could not be parsed
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A material library referenced by the file failed to load
+    Material {
+        path: PathBuf,
+        error: Box<ObjError>,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(error) => write!(f, "{error}"),
+            ObjError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ObjError::Material { path, error } => write!(f, "{}: {error}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<std::io::Error> for ObjError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub emissive: [f32; 3],
+    pub shininess: f32,
+    /// Opacity, `1.0` is opaque
+    pub dissolve: f32,
+    pub optical_density: f32,
+    pub illumination: u32,
+    pub ambient_texture: Option<String>,
+    pub diffuse_texture: Option<String>,
+    pub specular_texture: Option<String>,
+    pub emissive_texture: Option<String>,
+    pub normal_texture: Option<String>,
+    pub alpha_texture: Option<String>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ambient: [0.0; 3],
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.0,
+            illumination: 2,
+            ambient_texture: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            emissive_texture: None,
+            normal_texture: None,
+            alpha_texture: None,
+        }
+    }
+}
+
+/// Faces of one group sharing one material
+#[derive(Debug)]
+pub struct ObjMesh {
+    /// Object or group name
+    pub name: Option<String>,
+    /// Index in [`ObjScene::materials`]
+    pub material: Option<usize>,
+    pub geometry: GeometryData,
+}
+
+#[derive(Debug, Default)]
+pub struct ObjScene {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+    /// Material libraries named by `mtllib`
+    pub material_libraries: Vec<String>,
+}
+
+/// Load an OBJ file and the material libraries next to it
+pub fn load_obj<P>(path: P) -> Result<ObjScene, ObjError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)?;
+    let mut scene = parse_obj(std::io::BufReader::new(file))?;
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = Vec::new();
+    for library in scene.material_libraries.iter() {
+        let path = base.join(library);
+        let loaded = std::fs::File::open(&path)
+            .map_err(ObjError::from)
+            .and_then(|file| parse_mtl(std::io::BufReader::new(file)))
+            .map_err(|error| ObjError::Material {
+                path: path.clone(),
+                error: Box::new(error),
+            })?;
+        materials.extend(loaded);
+    }
+    scene.resolve_materials(materials);
+    Ok(scene)
+}
+
+impl ObjScene {
+    /// Replace the materials named by `usemtl` with loaded ones of the same name.
+    /// Materials missing from `materials` are kept with default values.
+    pub fn resolve_materials(&mut self, materials: Vec<ObjMaterial>) {
+        let placeholders = std::mem::replace(&mut self.materials, materials);
+        let remap = placeholders
+            .into_iter()
+            .map(|placeholder| {
+                match self
+                    .materials
+                    .iter()
+                    .position(|m| m.name == placeholder.name)
+                {
+                    Some(material) => material,
+                    None => {
+                        self.materials.push(placeholder);
+                        self.materials.len() - 1
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        for mesh in self.meshes.iter_mut() {
+            mesh.material = mesh.material.map(|material| remap[material]);
+        }
+    }
+}
+
+/// Vertex references of a face corner, already resolved to zero-based indices
+type Corner = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct MeshBuilder {
+    name: Option<String>,
+    material: Option<usize>,
+    corners: HashMap<Corner, u32>,
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    has_colors: bool,
+    has_tex_coords: bool,
+    has_normals: bool,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn new(name: Option<String>, material: Option<usize>) -> Self {
+        Self {
+            name,
+            material,
+            ..Default::default()
+        }
+    }
+
+    fn vertex(&mut self, corner: Corner, data: &ObjData) -> u32 {
+        if let Some(index) = self.corners.get(&corner) {
+            return *index;
+        }
+        let (position, tex_coord, normal) = corner;
+        let index = self.positions.len() as u32;
+        self.positions.push(data.positions[position]);
+        self.colors.push(data.colors[position]);
+        self.has_colors |= data.has_colors;
+        self.tex_coords
+            .push(tex_coord.map(|t| data.tex_coords[t]).unwrap_or_default());
+        self.has_tex_coords |= tex_coord.is_some();
+        self.normals
+            .push(normal.map(|n| data.normals[n]).unwrap_or_default());
+        self.has_normals |= normal.is_some();
+        self.corners.insert(corner, index);
+        index
+    }
+
+    fn build(self) -> Option<ObjMesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        let mut geometry = GeometryData::new();
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_POSITION,
+            VertexValues::Float32x3(self.positions),
+        );
+        if self.has_normals {
+            geometry.set_attribute(
+                GeometryData::ATTRIBUTE_NORMAL,
+                VertexValues::Float32x3(self.normals),
+            );
+        }
+        if self.has_tex_coords {
+            geometry.set_attribute(
+                GeometryData::ATTRIBUTE_TEX_COORD,
+                VertexValues::Float32x2(self.tex_coords),
+            );
+        }
+        if self.has_colors {
+            geometry.set_attribute(
+                GeometryData::ATTRIBUTE_COLOR,
+                VertexValues::Float32x4(self.colors),
+            );
+        }
+        geometry.set_indices(Some(IndiceValues::auto(self.indices)));
+
+        Some(ObjMesh {
+            name: self.name,
+            material: self.material,
+            geometry,
+        })
+    }
+}
+
+/// Vertex data shared by every mesh of the file
+#[derive(Default)]
+struct ObjData {
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    has_colors: bool,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> ObjError {
+    ObjError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+/// `r g b` color, a single value applying to every channel
+fn parse_color(line: usize, values: &mut std::str::SplitWhitespace) -> Result<[f32; 3], ObjError> {
+    let count = values.clone().count();
+    let [r, g, b] = parse_floats(line, values, [0.0; 3])?;
+    Ok(if count == 1 { [r; 3] } else { [r, g, b] })
+}
+
+fn parse_floats<const N: usize>(
+    line: usize,
+    values: &mut std::str::SplitWhitespace,
+    defaults: [f32; N],
+) -> Result<[f32; N], ObjError> {
+    let mut result = defaults;
+    for (index, value) in result.iter_mut().enumerate() {
+        match values.next() {
+            Some(text) => {
+                *value = text
+                    .parse()
+                    .map_err(|_| parse_error(line, format!("invalid number \"{text}\"")))?
+            }
+            // Values past the first are optional when a default makes sense
+            None if index > 0 => break,
+            None => return Err(parse_error(line, "missing value")),
+        }
+    }
+    Ok(result)
+}
+
+/// Resolve a one-based or negative relative index
+fn parse_index(line: usize, text: &str, len: usize) -> Result<usize, ObjError> {
+    let index: i64 = text
+        .parse()
+        .map_err(|_| parse_error(line, format!("invalid index \"{text}\"")))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(parse_error(line, format!("index {index} is out of bounds")));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_corner(line: usize, text: &str, data: &ObjData) -> Result<Corner, ObjError> {
+    let mut parts = text.split('/');
+    let position = parse_index(line, parts.next().unwrap_or(""), data.positions.len())?;
+    let tex_coord = match parts.next() {
+        Some(text) if !text.is_empty() => Some(parse_index(line, text, data.tex_coords.len())?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(text) if !text.is_empty() => Some(parse_index(line, text, data.normals.len())?),
+        _ => None,
+    };
+    Ok((position, tex_coord, normal))
+}
+
+/// Parse an OBJ file line by line. Faces are split into one mesh per group and
+/// material, polygons are triangulated as fans. Materials only carry their names until
+/// [`ObjScene::resolve_materials`] is called.
+pub fn parse_obj<R: BufRead>(reader: R) -> Result<ObjScene, ObjError> {
+    let mut scene = ObjScene::default();
+    let mut data = ObjData::default();
+    let mut builder = MeshBuilder::new(None, None);
+    let mut corners = Vec::new();
+
+    for (number, text) in reader.lines().enumerate() {
+        let line = number + 1;
+        let text = text?;
+        let text = text.split('#').next().unwrap_or("");
+        let mut values = text.split_whitespace();
+        let keyword = match values.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        match keyword {
+            "v" => {
+                // A fourth value alone is a weight, three more are a color
+                let has_color = text.split_whitespace().count() >= 7;
+                let [x, y, z] = parse_floats(line, &mut values, [0.0, 0.0, 0.0])?;
+                let [r, g, b] = if has_color {
+                    parse_floats(line, &mut values, [1.0, 1.0, 1.0])?
+                } else {
+                    [1.0, 1.0, 1.0]
+                };
+                data.positions.push([x, y, z]);
+                data.colors.push([r, g, b, 1.0]);
+                data.has_colors |= has_color;
+            }
+            "vt" => {
+                let [u, v] = parse_floats(line, &mut values, [0.0, 0.0])?;
+                // OBJ puts the origin at the bottom left
+                data.tex_coords.push([u, 1.0 - v]);
+            }
+            "vn" => data
+                .normals
+                .push(parse_floats(line, &mut values, [0.0, 0.0, 0.0])?),
+            "f" => {
+                corners.clear();
+                for value in values {
+                    let corner = parse_corner(line, value, &data)?;
+                    corners.push(builder.vertex(corner, &data));
+                }
+                if corners.len() < 3 {
+                    return Err(parse_error(line, "face with less than 3 vertices"));
+                }
+                for window in corners.windows(2).skip(1) {
+                    builder.indices.extend([corners[0], window[0], window[1]]);
+                }
+            }
+            "o" | "g" => {
+                let name = values.collect::<Vec<_>>().join(" ");
+                let material = builder.material;
+                let previous = std::mem::replace(
+                    &mut builder,
+                    MeshBuilder::new(Some(name).filter(|name| !name.is_empty()), material),
+                );
+                scene.meshes.extend(previous.build());
+            }
+            "usemtl" => {
+                let name = values.collect::<Vec<_>>().join(" ");
+                let material = match scene.materials.iter().position(|m| m.name == name) {
+                    Some(material) => material,
+                    None => {
+                        scene.materials.push(ObjMaterial::new(name));
+                        scene.materials.len() - 1
+                    }
+                };
+                let name = builder.name.clone();
+                let previous =
+                    std::mem::replace(&mut builder, MeshBuilder::new(name, Some(material)));
+                scene.meshes.extend(previous.build());
+            }
+            "mtllib" => scene
+                .material_libraries
+                .push(values.collect::<Vec<_>>().join(" ")),
+            // Smoothing groups, lines, points and curves are not supported
+            _ => {}
+        }
+    }
+
+    scene.meshes.extend(builder.build());
+    Ok(scene)
+}
+
+/// Least and most arguments of a texture option
+fn texture_option_arguments(option: &str) -> Option<(usize, usize)> {
+    match option {
+        "-blendu" | "-blendv" | "-boost" | "-texres" | "-clamp" | "-bm" | "-imfchan" | "-type"
+        | "-cc" => Some((1, 1)),
+        "-mm" => Some((2, 2)),
+        "-o" | "-s" | "-t" => Some((1, 3)),
+        _ => None,
+    }
+}
+
+/// File name of a texture statement, the rest of the line after the options
+fn texture_name<'a>(values: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut values = values.peekable();
+    while let Some(option) = values.next_if(|value| texture_option_arguments(value).is_some()) {
+        let (least, most) = texture_option_arguments(option).unwrap_or_default();
+        for argument in 0..most {
+            let number = values
+                .peek()
+                .is_some_and(|value| value.parse::<f32>().is_ok());
+            if argument >= least && !number {
+                break;
+            }
+            values.next();
+        }
+    }
+    let name = values.collect::<Vec<_>>().join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
+/// Parse a MTL material library
+pub fn parse_mtl<R: BufRead>(reader: R) -> Result<Vec<ObjMaterial>, ObjError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, text) in reader.lines().enumerate() {
+        let line = number + 1;
+        let text = text?;
+        let text = text.split('#').next().unwrap_or("");
+        let mut values = text.split_whitespace();
+        let keyword = match values.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(values.collect::<Vec<_>>().join(" ")));
+            continue;
+        }
+        let material = materials
+            .last_mut()
+            .ok_or_else(|| parse_error(line, format!("\"{keyword}\" before newmtl")))?;
+
+        match keyword {
+            "Ka" => material.ambient = parse_color(line, &mut values)?,
+            "Kd" => material.diffuse = parse_color(line, &mut values)?,
+            "Ks" => material.specular = parse_color(line, &mut values)?,
+            "Ke" => material.emissive = parse_color(line, &mut values)?,
+            "Ns" => material.shininess = parse_floats(line, &mut values, [0.0])?[0],
+            "Ni" => material.optical_density = parse_floats(line, &mut values, [0.0])?[0],
+            "d" => material.dissolve = parse_floats(line, &mut values, [0.0])?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(line, &mut values, [0.0])?[0],
+            "illum" => material.illumination = parse_floats(line, &mut values, [0.0])?[0] as u32,
+            "map_Ka" => material.ambient_texture = texture_name(values),
+            "map_Kd" => material.diffuse_texture = texture_name(values),
+            "map_Ks" => material.specular_texture = texture_name(values),
+            "map_Ke" => material.emissive_texture = texture_name(values),
+            "map_Bump" | "map_bump" | "bump" | "norm" => {
+                material.normal_texture = texture_name(values)
+            }
+            "map_d" => material.alpha_texture = texture_name(values),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_names_keep_their_spaces() {
+        let mtl = "newmtl brick wall
+map_Kd -o 0.5 0.5 -s 2 -clamp on textures/brick wall.png
+map_Bump -bm 0.25 brick normal.png
+map_d -mm 0 1 -t 0.1 mask.png
+map_Ks
+";
+        let materials = parse_mtl(mtl.as_bytes()).unwrap();
+        let material = &materials[0];
+        assert_eq!(material.name, "brick wall");
+        assert_eq!(
+            material.diffuse_texture.as_deref(),
+            Some("textures/brick wall.png")
+        );
+        assert_eq!(material.normal_texture.as_deref(), Some("brick normal.png"));
+        assert_eq!(material.alpha_texture.as_deref(), Some("mask.png"));
+        assert_eq!(material.specular_texture, None);
+    }
+}
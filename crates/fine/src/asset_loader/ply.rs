@@ -0,0 +1,503 @@
+use render::prelude::{GeometryData, IndiceValues, VertexValues};
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(std::io::Error),
+    /// A header or ASCII body line could not be parsed
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A binary body ended before every element was read
+    UnexpectedEof {
+        element: String,
+        index: usize,
+    },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(error) => write!(f, "{error}"),
+            PlyError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            PlyError::UnexpectedEof { element, index } => {
+                write!(f, "unexpected end of file reading {element} {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+impl From<std::io::Error> for PlyError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> PlyError {
+    PlyError::Parse {
+        line,
+        message: message.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Scale to bring integer colors in `0..=1`
+    fn normalize(self, value: f64) -> f64 {
+        match self {
+            Self::U8 => value / u8::MAX as f64,
+            Self::U16 => value / u16::MAX as f64,
+            _ => value,
+        }
+    }
+
+    fn read(self, bytes: &[u8], format: PlyFormat) -> f64 {
+        macro_rules! read {
+            ($ty:ty) => {{
+                let bytes = bytes.try_into().unwrap();
+                (if format == PlyFormat::BinaryBigEndian {
+                    <$ty>::from_be_bytes(bytes)
+                } else {
+                    <$ty>::from_le_bytes(bytes)
+                }) as f64
+            }};
+        }
+        match self {
+            Self::I8 => read!(i8),
+            Self::U8 => read!(u8),
+            Self::I16 => read!(i16),
+            Self::U16 => read!(u16),
+            Self::I32 => read!(i32),
+            Self::U32 => read!(u32),
+            Self::F32 => read!(f32),
+            Self::F64 => read!(f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: PlyFormat,
+    elements: Vec<Element>,
+    /// Number of lines of the header
+    lines: usize,
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<Header, PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = 0;
+    let mut text = String::new();
+
+    loop {
+        text.clear();
+        line += 1;
+        if reader.read_line(&mut text)? == 0 {
+            return Err(parse_error(line, "missing end_header"));
+        }
+        let mut values = text.split_whitespace();
+        let keyword = values.next().unwrap_or("");
+
+        if line == 1 {
+            if keyword != "ply" {
+                return Err(parse_error(line, "not a PLY file"));
+            }
+            continue;
+        }
+
+        match keyword {
+            "format" => {
+                format = Some(match values.next() {
+                    Some("ascii") => PlyFormat::Ascii,
+                    Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    Some("binary_big_endian") => PlyFormat::BinaryBigEndian,
+                    _ => return Err(parse_error(line, "unknown format")),
+                })
+            }
+            "element" => {
+                let name = values.next().unwrap_or("").to_string();
+                let count = values
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or_else(|| parse_error(line, "invalid element count"))?;
+                elements.push(Element {
+                    name,
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            "property" => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| parse_error(line, "property before element"))?;
+                let scalar = |name: Option<&str>| {
+                    name.and_then(ScalarType::parse)
+                        .ok_or_else(|| parse_error(line, "unknown property type"))
+                };
+                let ty = match values.next() {
+                    Some("list") => PropertyType::List {
+                        count: scalar(values.next())?,
+                        item: scalar(values.next())?,
+                    },
+                    ty => PropertyType::Scalar(scalar(ty)?),
+                };
+                let name = values
+                    .next()
+                    .ok_or_else(|| parse_error(line, "missing property name"))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty,
+                });
+            }
+            "end_header" => break,
+            "comment" | "obj_info" | "" => {}
+            keyword => return Err(parse_error(line, format!("unknown keyword \"{keyword}\""))),
+        }
+    }
+
+    Ok(Header {
+        format: format.ok_or_else(|| parse_error(line, "missing format"))?,
+        elements,
+        lines: line,
+    })
+}
+
+/// Reads the values of one element at a time, ASCII or binary
+struct BodyReader<R> {
+    reader: R,
+    format: PlyFormat,
+    line: usize,
+    text: String,
+    buffer: [u8; 8],
+}
+
+impl<R: BufRead> BodyReader<R> {
+    /// Read every property of an element. Lists are flattened after their count.
+    fn read_element(
+        &mut self,
+        element: &Element,
+        index: usize,
+        values: &mut Vec<f64>,
+    ) -> Result<(), PlyError> {
+        values.clear();
+        if self.format == PlyFormat::Ascii {
+            return self.read_ascii_element(element, values);
+        }
+
+        for property in element.properties.iter() {
+            match property.ty {
+                PropertyType::Scalar(ty) => values.push(self.read_binary(ty, element, index)?),
+                PropertyType::List { count, item } => {
+                    let count = self.read_binary(count, element, index)?;
+                    values.push(count);
+                    for _ in 0..count as usize {
+                        values.push(self.read_binary(item, element, index)?);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_binary(
+        &mut self,
+        ty: ScalarType,
+        element: &Element,
+        index: usize,
+    ) -> Result<f64, PlyError> {
+        let bytes = &mut self.buffer[..ty.size()];
+        self.reader.read_exact(bytes).map_err(|error| {
+            if error.kind() == std::io::ErrorKind::UnexpectedEof {
+                PlyError::UnexpectedEof {
+                    element: element.name.clone(),
+                    index,
+                }
+            } else {
+                PlyError::Io(error)
+            }
+        })?;
+        Ok(ty.read(bytes, self.format))
+    }
+
+    fn read_ascii_element(
+        &mut self,
+        element: &Element,
+        values: &mut Vec<f64>,
+    ) -> Result<(), PlyError> {
+        self.text.clear();
+        self.line += 1;
+        if self.reader.read_line(&mut self.text)? == 0 {
+            return Err(parse_error(
+                self.line,
+                format!("missing {} values", element.name),
+            ));
+        }
+        let line = self.line;
+        for value in self.text.split_whitespace() {
+            values.push(
+                value
+                    .parse()
+                    .map_err(|_| parse_error(line, format!("invalid number \"{value}\"")))?,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Position in the flattened values of each vertex property used for geometry
+#[derive(Default)]
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: [Option<usize>; 3],
+    tex_coord: [Option<usize>; 2],
+    /// Offsets and type to normalize integer colors
+    color: [Option<(usize, ScalarType)>; 4],
+}
+
+impl VertexLayout {
+    fn new(element: &Element) -> Self {
+        let mut layout = Self::default();
+        for (offset, property) in element.properties.iter().enumerate() {
+            let ty = match property.ty {
+                PropertyType::Scalar(ty) => ty,
+                // Lists in vertices are not supported and would shift every offset
+                PropertyType::List { .. } => return Self::default(),
+            };
+            match property.name.as_str() {
+                "x" => layout.position[0] = Some(offset),
+                "y" => layout.position[1] = Some(offset),
+                "z" => layout.position[2] = Some(offset),
+                "nx" => layout.normal[0] = Some(offset),
+                "ny" => layout.normal[1] = Some(offset),
+                "nz" => layout.normal[2] = Some(offset),
+                "u" | "s" | "texture_u" | "texture_s" => layout.tex_coord[0] = Some(offset),
+                "v" | "t" | "texture_v" | "texture_t" => layout.tex_coord[1] = Some(offset),
+                "red" | "r" => layout.color[0] = Some((offset, ty)),
+                "green" | "g" => layout.color[1] = Some((offset, ty)),
+                "blue" | "b" => layout.color[2] = Some((offset, ty)),
+                "alpha" | "a" => layout.color[3] = Some((offset, ty)),
+                _ => {}
+            }
+        }
+        layout
+    }
+}
+
+/// Load an ASCII or binary PLY file
+pub fn load_ply<P>(path: P) -> Result<GeometryData, PlyError>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::open(path)?;
+    parse_ply(std::io::BufReader::new(file))
+}
+
+/// Parse a PLY file element by element. Vertices are read from `x`, `y`, `z`, normals,
+/// texture coordinates and colors properties, faces from their `vertex_indices` list,
+/// triangulated as fans. Other elements are skipped.
+pub fn parse_ply<R: BufRead>(mut reader: R) -> Result<GeometryData, PlyError> {
+    let header = read_header(&mut reader)?;
+    let mut body = BodyReader {
+        reader,
+        format: header.format,
+        line: header.lines,
+        text: String::new(),
+        buffer: [0; 8],
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    let mut has_faces = false;
+    let mut layout = VertexLayout::default();
+    let mut values = Vec::new();
+
+    for element in header.elements.iter() {
+        match element.name.as_str() {
+            "vertex" => {
+                layout = VertexLayout::new(element);
+                if layout.position.iter().any(Option::is_none) {
+                    return Err(parse_error(header.lines, "vertices have no position"));
+                }
+            }
+            "face" => has_faces = true,
+            _ => {}
+        }
+        let face_list = element.properties.iter().position(|property| {
+            property.name == "vertex_indices" || property.name == "vertex_index"
+        });
+
+        for index in 0..element.count {
+            body.read_element(element, index, &mut values)?;
+            let get = |offset: Option<usize>| offset.and_then(|offset| values.get(offset)).copied();
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let missing = || parse_error(body.line, "missing vertex value");
+                    let position = layout.position.map(get);
+                    positions.push([
+                        position[0].ok_or_else(missing)? as f32,
+                        position[1].ok_or_else(missing)? as f32,
+                        position[2].ok_or_else(missing)? as f32,
+                    ]);
+                    if layout.normal.iter().all(Option::is_some) {
+                        normals.push(
+                            layout
+                                .normal
+                                .map(|offset| get(offset).unwrap_or(0.0) as f32),
+                        );
+                    }
+                    if layout.tex_coord.iter().all(Option::is_some) {
+                        let [u, v] = layout
+                            .tex_coord
+                            .map(|offset| get(offset).unwrap_or(0.0) as f32);
+                        tex_coords.push([u, 1.0 - v]);
+                    }
+                    if layout.color[..3].iter().all(Option::is_some) {
+                        colors.push(layout.color.map(|color| match color {
+                            Some((offset, ty)) => {
+                                ty.normalize(get(Some(offset)).unwrap_or(0.0)) as f32
+                            }
+                            None => 1.0,
+                        }));
+                    }
+                }
+                "face" => {
+                    let list = match face_list {
+                        Some(list) => list,
+                        None => continue,
+                    };
+                    // Offset of the list among flattened values, after preceding lists
+                    let mut offset = 0;
+                    for property in element.properties[..list].iter() {
+                        offset += match property.ty {
+                            PropertyType::Scalar(_) => 1,
+                            PropertyType::List { .. } => {
+                                let count = values.get(offset).ok_or_else(|| {
+                                    parse_error(body.line, "face line is shorter than its lists")
+                                })?;
+                                *count as usize + 1
+                            }
+                        };
+                    }
+                    let count = values.get(offset).copied().unwrap_or(0.0) as usize;
+                    let corners = values.get(offset + 1..offset + 1 + count).ok_or_else(|| {
+                        parse_error(body.line, "face list is shorter than its count")
+                    })?;
+                    if count < 3 {
+                        return Err(parse_error(body.line, "face with less than 3 vertices"));
+                    }
+                    for corner in corners.iter() {
+                        if *corner < 0.0 || *corner as usize >= positions.len() {
+                            return Err(parse_error(
+                                body.line,
+                                format!("vertex index {corner} is out of bounds"),
+                            ));
+                        }
+                    }
+                    for window in corners.windows(2).skip(1) {
+                        indices.extend([corners[0] as u32, window[0] as u32, window[1] as u32]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut geometry = GeometryData::new();
+    geometry.set_attribute(
+        GeometryData::ATTRIBUTE_POSITION,
+        VertexValues::Float32x3(positions),
+    );
+    if !normals.is_empty() {
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_NORMAL,
+            VertexValues::Float32x3(normals),
+        );
+    }
+    if !tex_coords.is_empty() {
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_TEX_COORD,
+            VertexValues::Float32x2(tex_coords),
+        );
+    }
+    if !colors.is_empty() {
+        geometry.set_attribute(
+            GeometryData::ATTRIBUTE_COLOR,
+            VertexValues::Float32x4(colors),
+        );
+    }
+    if has_faces {
+        geometry.set_indices(Some(IndiceValues::auto(indices)));
+    } else {
+        // Point clouds
+        geometry.set_topology(render::prelude::wgpu::PrimitiveTopology::PointList);
+    }
+    Ok(geometry)
+}
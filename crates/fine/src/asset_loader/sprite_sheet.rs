@@ -0,0 +1,719 @@
+use render::prelude::{Context, FrameRotation, RawTexture, TextureAtlas, TextureRect};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SpriteSheetError {
+    Io(std::io::Error),
+    /// A JSON sheet could not be parsed
+    Json(serde_json::Error),
+    /// A line of a LibGDX atlas could not be parsed
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A JSON sheet has neither `frames` nor `textures`
+    MissingFrames,
+}
+
+impl fmt::Display for SpriteSheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteSheetError::Io(error) => write!(f, "{error}"),
+            SpriteSheetError::Json(error) => write!(f, "invalid sprite sheet: {error}"),
+            SpriteSheetError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            SpriteSheetError::MissingFrames => write!(f, "sprite sheet has no frames"),
+        }
+    }
+}
+
+impl std::error::Error for SpriteSheetError {}
+
+impl From<std::io::Error> for SpriteSheetError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SpriteSheetError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Image holding frames, stored in the atlas layer of the same index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpritePage {
+    /// Image file, relative to the sheet
+    pub file: String,
+    /// Size declared by the sheet, `0` when unknown
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteFrame {
+    pub name: String,
+    /// LibGDX index, the frames of an animation share their name
+    pub index: Option<u32>,
+    pub page: u32,
+    /// Rectangle in the page. `width` and `height` are the page ones when rotated.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rotation: FrameRotation,
+    /// Size of the image before trimming
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Position of the trimmed image in the source, from the top left
+    pub offset_x: u32,
+    pub offset_y: u32,
+    /// Aseprite duration, in milliseconds
+    pub duration: Option<u32>,
+}
+
+impl SpriteFrame {
+    /// Name in the atlas, `name_index` for indexed frames
+    pub fn key(&self) -> String {
+        match self.index {
+            Some(index) => format!("{}_{index}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Size of the trimmed image, rotation undone
+    pub fn size(&self) -> (u32, u32) {
+        self.texture_rect().size()
+    }
+
+    /// Whether transparent borders were cut from the source
+    pub fn trimmed(&self) -> bool {
+        self.texture_rect().trimmed()
+    }
+
+    /// Rectangle in the page, with the rotation and the trim of the frame
+    pub fn texture_rect(&self) -> TextureRect {
+        TextureRect::new(self.x, self.y, self.width, self.height)
+            .with_rotation(self.rotation)
+            .with_trim(
+                (self.offset_x, self.offset_y),
+                self.source_width,
+                self.source_height,
+            )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+/// Aseprite tag, a named range of frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTag {
+    pub name: String,
+    /// First and last frame, inclusive
+    pub from: usize,
+    pub to: usize,
+    pub direction: AnimationDirection,
+}
+
+/// Frames of a TexturePacker, Aseprite or LibGDX atlas
+#[derive(Debug, Clone, Default)]
+pub struct SpriteSheet {
+    pub pages: Vec<SpritePage>,
+    /// Frames in file order
+    pub frames: Vec<SpriteFrame>,
+    pub tags: Vec<FrameTag>,
+}
+
+impl SpriteSheet {
+    /// Frame by [`SpriteFrame::key`]
+    pub fn frame(&self, key: &str) -> Option<&SpriteFrame> {
+        self.frames.iter().find(|frame| frame.key() == key)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&FrameTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Frames of a tag, in file order
+    pub fn tag_frames(&self, tag: &FrameTag) -> &[SpriteFrame] {
+        let end = (tag.to + 1).min(self.frames.len());
+        &self.frames[tag.from.min(end)..end]
+    }
+
+    /// Copy the frames in a new atlas, page `i` in layer `i` at the same position,
+    /// trim offsets and source sizes kept on the atlas frames. Pages must be usable
+    /// as copy source, frames of missing pages are skipped.
+    /// Returns `None` when a frame cannot be added.
+    pub fn create_atlas(&self, gpu: &mut Context, pages: &[RawTexture]) -> Option<TextureAtlas> {
+        let width = pages.iter().map(|page| page.width()).max().unwrap_or(1);
+        let height = pages.iter().map(|page| page.height()).max().unwrap_or(1);
+        let mut atlas = TextureAtlas::new(gpu, width, height, pages.len().max(1) as u32);
+        for frame in self.frames.iter() {
+            let page = match pages.get(frame.page as usize) {
+                Some(page) => page,
+                None => continue,
+            };
+            atlas.append_raw_texture(
+                frame.key(),
+                frame.page,
+                gpu,
+                page,
+                frame.texture_rect(),
+                Some((frame.x, frame.y)),
+            )?;
+        }
+        Some(atlas)
+    }
+}
+
+/// Load a sheet, `.json` files are read by [`parse_sprite_sheet_json`] and
+/// others by [`parse_libgdx_atlas`]
+pub fn load_sprite_sheet<P>(path: P) -> Result<SpriteSheet, SpriteSheetError>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let json = path
+        .extension()
+        .map(|extension| extension.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    if json {
+        parse_sprite_sheet_json(&text)
+    } else {
+        parse_libgdx_atlas(&text)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFrame {
+    filename: Option<String>,
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: Option<JsonRect>,
+    source_size: Option<JsonSize>,
+    duration: Option<u32>,
+}
+
+/// Frames of the array format, or of the hash format in file order
+struct JsonFrames(Vec<(Option<String>, JsonFrame)>);
+
+impl<'de> Deserialize<'de> for JsonFrames {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = JsonFrames;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an array or a map of frames")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element::<JsonFrame>()? {
+                    frames.push((None, frame));
+                }
+                Ok(JsonFrames(frames))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut frames = Vec::new();
+                while let Some((name, frame)) = map.next_entry::<String, JsonFrame>()? {
+                    frames.push((Some(name), frame));
+                }
+                Ok(JsonFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonMeta {
+    image: Option<String>,
+    size: Option<JsonSize>,
+    #[serde(default)]
+    frame_tags: Vec<JsonTag>,
+}
+
+/// Page of a multi-texture sheet
+#[derive(Deserialize)]
+struct JsonTexture {
+    image: Option<String>,
+    size: Option<JsonSize>,
+    frames: JsonFrames,
+}
+
+#[derive(Deserialize)]
+struct JsonSheet {
+    frames: Option<JsonFrames>,
+    #[serde(default)]
+    textures: Vec<JsonTexture>,
+    meta: Option<JsonMeta>,
+}
+
+fn json_page(image: Option<String>, size: Option<JsonSize>) -> SpritePage {
+    let (width, height) = size.map(|size| (size.w, size.h)).unwrap_or((0, 0));
+    SpritePage {
+        file: image.unwrap_or_default(),
+        width,
+        height,
+    }
+}
+
+fn json_frame(page: u32, name: Option<String>, frame: JsonFrame) -> SpriteFrame {
+    let JsonRect { x, y, w, h } = frame.frame;
+    // Sizes are the unrotated ones, TexturePacker turns frames clockwise
+    let (rotation, width, height) = if frame.rotated {
+        (FrameRotation::Clockwise, h, w)
+    } else {
+        (FrameRotation::None, w, h)
+    };
+    let (offset_x, offset_y) = frame
+        .sprite_source_size
+        .map(|rect| (rect.x, rect.y))
+        .unwrap_or((0, 0));
+    let (source_width, source_height) = frame
+        .source_size
+        .map(|size| (size.w, size.h))
+        .unwrap_or((w, h));
+    SpriteFrame {
+        name: name.or(frame.filename).unwrap_or_default(),
+        index: None,
+        page,
+        x,
+        y,
+        width,
+        height,
+        rotation,
+        source_width,
+        source_height,
+        offset_x,
+        offset_y,
+        duration: frame.duration,
+    }
+}
+
+/// Parse a TexturePacker JSON sheet, in the hash, array or multi-texture
+/// format, or an Aseprite JSON export with its frame tags and durations
+pub fn parse_sprite_sheet_json(text: &str) -> Result<SpriteSheet, SpriteSheetError> {
+    let sheet = serde_json::from_str::<JsonSheet>(text)?;
+    let meta = sheet.meta.unwrap_or(JsonMeta {
+        image: None,
+        size: None,
+        frame_tags: Vec::new(),
+    });
+
+    let mut result = SpriteSheet::default();
+    let textures = match sheet.frames {
+        Some(frames) => vec![(json_page(meta.image, meta.size), frames)],
+        None if !sheet.textures.is_empty() => sheet
+            .textures
+            .into_iter()
+            .map(|texture| (json_page(texture.image, texture.size), texture.frames))
+            .collect(),
+        None => return Err(SpriteSheetError::MissingFrames),
+    };
+    for (page, (sprite_page, frames)) in textures.into_iter().enumerate() {
+        result.pages.push(sprite_page);
+        result.frames.extend(
+            frames
+                .0
+                .into_iter()
+                .map(|(name, frame)| json_frame(page as u32, name, frame)),
+        );
+    }
+
+    result.tags = meta
+        .frame_tags
+        .into_iter()
+        .map(|tag| FrameTag {
+            name: tag.name,
+            from: tag.from,
+            to: tag.to,
+            direction: match tag.direction.as_str() {
+                "reverse" => AnimationDirection::Reverse,
+                "pingpong" => AnimationDirection::PingPong,
+                "pingpong_reverse" => AnimationDirection::PingPongReverse,
+                _ => AnimationDirection::Forward,
+            },
+        })
+        .collect();
+
+    Ok(result)
+}
+
+fn integers<const N: usize>(line: usize, value: &str) -> Result<[i64; N], SpriteSheetError> {
+    let mut result = [0; N];
+    let mut parts = value.split(',');
+    for number in result.iter_mut() {
+        let part = parts.next().ok_or_else(|| SpriteSheetError::Parse {
+            line,
+            message: format!("expected {N} values in {value:?}"),
+        })?;
+        *number = part.trim().parse().map_err(|_| SpriteSheetError::Parse {
+            line,
+            message: format!("invalid number {part:?}"),
+        })?;
+    }
+    Ok(result)
+}
+
+/// LibGDX region, sizes unrotated and offsets from the bottom left
+#[derive(Default)]
+struct Region {
+    name: String,
+    index: Option<u32>,
+    bounds: [i64; 4],
+    original: Option<[i64; 2]>,
+    offset: [i64; 2],
+    degrees: i64,
+}
+
+impl Region {
+    fn into_frame(self, line: usize, page: u32) -> Result<SpriteFrame, SpriteSheetError> {
+        let [x, y, width, height] = self.bounds.map(|value| value.max(0) as u32);
+        let [source_width, source_height] = self
+            .original
+            .map(|original| original.map(|value| value.max(0) as u32))
+            .unwrap_or([width, height]);
+        let [offset_x, offset_bottom] = self.offset.map(|value| value.max(0) as u32);
+        // LibGDX turns frames counter clockwise
+        let (rotation, stored_width, stored_height) = match self.degrees.rem_euclid(360) {
+            0 => (FrameRotation::None, width, height),
+            90 => (FrameRotation::CounterClockwise, height, width),
+            270 => (FrameRotation::Clockwise, height, width),
+            degrees => {
+                return Err(SpriteSheetError::Parse {
+                    line,
+                    message: format!("unsupported rotation of {degrees} degrees"),
+                })
+            }
+        };
+        Ok(SpriteFrame {
+            name: self.name,
+            index: self.index,
+            page,
+            x,
+            y,
+            width: stored_width,
+            height: stored_height,
+            rotation,
+            source_width,
+            source_height,
+            offset_x,
+            offset_y: source_height.saturating_sub(height + offset_bottom),
+            duration: None,
+        })
+    }
+}
+
+/// Parse a LibGDX `.atlas`, in the legacy or the 1.10 format
+pub fn parse_libgdx_atlas(text: &str) -> Result<SpriteSheet, SpriteSheetError> {
+    let mut sheet = SpriteSheet::default();
+    // Region being read and the line of its name
+    let mut region: Option<(usize, Region)> = None;
+    let mut in_page = false;
+
+    for (number, text) in text.lines().enumerate() {
+        let line = number + 1;
+        let text = text.trim();
+        let page = sheet.pages.len() as u32;
+
+        if text.is_empty() || !in_page || !text.contains(':') {
+            if let Some((line, region)) = region.take() {
+                sheet.frames.push(region.into_frame(line, page - 1)?);
+            }
+            if text.is_empty() {
+                in_page = false;
+            } else if !in_page {
+                sheet.pages.push(SpritePage {
+                    file: text.to_string(),
+                    ..Default::default()
+                });
+                in_page = true;
+            } else {
+                region = Some((
+                    line,
+                    Region {
+                        name: text.to_string(),
+                        ..Default::default()
+                    },
+                ));
+            }
+            continue;
+        }
+
+        let (key, value) = text.split_once(':').unwrap_or((text, ""));
+        let (key, value) = (key.trim(), value.trim());
+        match region.as_mut() {
+            None => {
+                if key == "size" {
+                    let [width, height] = integers(line, value)?;
+                    let sprite_page = sheet.pages.last_mut().unwrap();
+                    sprite_page.width = width.max(0) as u32;
+                    sprite_page.height = height.max(0) as u32;
+                }
+            }
+            Some((_, region)) => match key {
+                "rotate" => {
+                    region.degrees = match value {
+                        "true" => 90,
+                        "false" => 0,
+                        _ => integers::<1>(line, value)?[0],
+                    }
+                }
+                "xy" => {
+                    let [x, y] = integers(line, value)?;
+                    region.bounds[0] = x;
+                    region.bounds[1] = y;
+                }
+                "size" => {
+                    let [width, height] = integers(line, value)?;
+                    region.bounds[2] = width;
+                    region.bounds[3] = height;
+                }
+                "bounds" => region.bounds = integers(line, value)?,
+                "orig" => region.original = Some(integers(line, value)?),
+                "offset" => region.offset = integers(line, value)?,
+                "offsets" => {
+                    let [x, y, width, height] = integers(line, value)?;
+                    region.offset = [x, y];
+                    region.original = Some([width, height]);
+                }
+                "index" => {
+                    let [index] = integers(line, value)?;
+                    region.index = (index >= 0).then_some(index as u32);
+                }
+                _ => {}
+            },
+        }
+    }
+
+    if let Some((line, region)) = region {
+        let page = sheet.pages.len() as u32 - 1;
+        sheet.frames.push(region.into_frame(line, page)?);
+    }
+
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXTURE_PACKER: &str = r#"{
+        "frames": {
+            "hero.png": {
+                "frame": { "x": 2, "y": 2, "w": 20, "h": 10 },
+                "rotated": true,
+                "trimmed": true,
+                "spriteSourceSize": { "x": 3, "y": 4, "w": 20, "h": 10 },
+                "sourceSize": { "w": 32, "h": 16 }
+            },
+            "coin.png": {
+                "frame": { "x": 14, "y": 2, "w": 8, "h": 8 },
+                "rotated": false,
+                "trimmed": false,
+                "spriteSourceSize": { "x": 0, "y": 0, "w": 8, "h": 8 },
+                "sourceSize": { "w": 8, "h": 8 }
+            }
+        },
+        "meta": { "image": "sheet.png", "size": { "w": 64, "h": 32 } }
+    }"#;
+
+    const ASEPRITE: &str = r#"{
+        "frames": [
+            {
+                "filename": "walk 0.aseprite",
+                "frame": { "x": 0, "y": 0, "w": 16, "h": 16 },
+                "rotated": false,
+                "trimmed": false,
+                "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+                "sourceSize": { "w": 16, "h": 16 },
+                "duration": 100
+            },
+            {
+                "filename": "walk 1.aseprite",
+                "frame": { "x": 16, "y": 0, "w": 12, "h": 14 },
+                "rotated": false,
+                "trimmed": true,
+                "spriteSourceSize": { "x": 2, "y": 1, "w": 12, "h": 14 },
+                "sourceSize": { "w": 16, "h": 16 },
+                "duration": 150
+            }
+        ],
+        "meta": {
+            "image": "walk.png",
+            "size": { "w": 32, "h": 16 },
+            "frameTags": [{ "name": "walk", "from": 0, "to": 1, "direction": "pingpong" }]
+        }
+    }"#;
+
+    /// Legacy page followed by a 1.10 page
+    const LIBGDX: &str = "
+sheet.png
+size: 64, 32
+format: RGBA8888
+filter: Nearest,Nearest
+repeat: none
+hero
+  rotate: true
+  xy: 2, 2
+  size: 20, 10
+  orig: 32, 16
+  offset: 3, 2
+  index: -1
+run
+  rotate: false
+  xy: 30, 2
+  size: 8, 8
+  orig: 8, 8
+  offset: 0, 0
+  index: 1
+
+shield.png
+size:32,32
+repeat:none
+shield
+bounds:0,0,10,12
+offsets:1,1,12,14
+rotate:270
+";
+
+    #[test]
+    fn texture_packer_hash() {
+        let sheet = parse_sprite_sheet_json(TEXTURE_PACKER).unwrap();
+        assert_eq!(
+            sheet.pages,
+            [SpritePage {
+                file: "sheet.png".to_string(),
+                width: 64,
+                height: 32,
+            }]
+        );
+        assert_eq!(sheet.frames.len(), 2);
+
+        let hero = sheet.frame("hero.png").unwrap();
+        assert_eq!((hero.x, hero.y, hero.width, hero.height), (2, 2, 10, 20));
+        assert_eq!(hero.rotation, FrameRotation::Clockwise);
+        assert_eq!(hero.size(), (20, 10));
+        assert!(hero.trimmed());
+        let rect = hero.texture_rect();
+        assert_eq!(rect.offset(), (3, 4));
+        assert_eq!(rect.source_size(), (32, 16));
+
+        let coin = sheet.frame("coin.png").unwrap();
+        assert_eq!(coin.rotation, FrameRotation::None);
+        assert!(!coin.trimmed());
+        assert_eq!(coin.texture_rect().source_size(), (8, 8));
+    }
+
+    #[test]
+    fn aseprite_array() {
+        let sheet = parse_sprite_sheet_json(ASEPRITE).unwrap();
+        let names = sheet
+            .frames
+            .iter()
+            .map(SpriteFrame::key)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["walk 0.aseprite", "walk 1.aseprite"]);
+        assert_eq!(sheet.frames[0].duration, Some(100));
+        assert!(!sheet.frames[0].trimmed());
+
+        let trimmed = &sheet.frames[1];
+        assert_eq!(trimmed.duration, Some(150));
+        assert!(trimmed.trimmed());
+        assert_eq!((trimmed.offset_x, trimmed.offset_y), (2, 1));
+        assert_eq!(trimmed.texture_rect().source_size(), (16, 16));
+
+        let tag = sheet.tag("walk").unwrap();
+        assert_eq!(tag.direction, AnimationDirection::PingPong);
+        assert_eq!(sheet.tag_frames(tag).len(), 2);
+    }
+
+    #[test]
+    fn libgdx_pages() {
+        let sheet = parse_libgdx_atlas(LIBGDX).unwrap();
+        assert_eq!(sheet.pages.len(), 2);
+        assert_eq!((sheet.pages[0].width, sheet.pages[0].height), (64, 32));
+        assert_eq!(sheet.pages[1].file, "shield.png");
+
+        let hero = sheet.frame("hero").unwrap();
+        assert_eq!(hero.index, None);
+        assert_eq!(hero.rotation, FrameRotation::CounterClockwise);
+        assert_eq!((hero.x, hero.y, hero.width, hero.height), (2, 2, 10, 20));
+        // Offsets are turned to the top left
+        assert_eq!(hero.texture_rect().offset(), (3, 4));
+        assert_eq!(hero.texture_rect().source_size(), (32, 16));
+
+        let run = sheet.frame("run_1").unwrap();
+        assert_eq!((run.page, run.x, run.y), (0, 30, 2));
+        assert!(!run.trimmed());
+
+        let shield = sheet.frame("shield").unwrap();
+        assert_eq!(shield.page, 1);
+        assert_eq!(shield.rotation, FrameRotation::Clockwise);
+        assert_eq!(shield.size(), (10, 12));
+        assert_eq!((shield.offset_x, shield.offset_y), (1, 1));
+        assert_eq!((shield.source_width, shield.source_height), (12, 14));
+    }
+
+    #[test]
+    fn libgdx_errors_name_the_line() {
+        let error = parse_libgdx_atlas("sheet.png\nhero\n  rotate: 45\n").unwrap_err();
+        assert!(matches!(error, SpriteSheetError::Parse { line: 2, .. }));
+        let error = parse_libgdx_atlas("sheet.png\nhero\n  xy: 2\n").unwrap_err();
+        assert!(matches!(error, SpriteSheetError::Parse { line: 3, .. }));
+        assert!(matches!(
+            parse_sprite_sheet_json("{}"),
+            Err(SpriteSheetError::MissingFrames)
+        ));
+    }
+}
@@ -45,6 +45,7 @@ impl Default for PerspectiveOptions {
     }
 }
 
+#[derive(Debug)]
 pub enum CameraProjection {
     Perspective(PerspectiveOptions),
     Orthographic(OrthographicOptions),
@@ -56,6 +57,7 @@ impl Default for CameraProjection {
     }
 }
 
+#[derive(Debug)]
 pub struct Camera {
     pub node: Node,
     view_matrix: Mat4,
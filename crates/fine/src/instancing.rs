@@ -0,0 +1,28 @@
+use render::context::Context;
+use render::geometry::InstanceBuffer;
+use render::util::VertexAttribute;
+use transform::Node;
+
+/// Fill an instance buffer from the world matrices of nodes
+pub trait NodeInstances {
+    /// Replace every instance with one per node, in iteration order. World matrices
+    /// must be up to date.
+    fn write_nodes<'n, I>(&mut self, context: &Context, nodes: I)
+    where
+        I: IntoIterator<Item = &'n Node>;
+}
+
+impl<T> NodeInstances for InstanceBuffer<T>
+where
+    T: bytemuck::Pod + VertexAttribute + From<[f32; 16]>,
+{
+    fn write_nodes<'n, I>(&mut self, context: &Context, nodes: I)
+    where
+        I: IntoIterator<Item = &'n Node>,
+    {
+        self.write_matrices(
+            context,
+            nodes.into_iter().map(|node| node.get_raw_world_matrix()),
+        );
+    }
+}
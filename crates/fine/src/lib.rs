@@ -1,11 +1,15 @@
 pub use render;
 pub use transform;
 pub use math;
+pub mod asset_cache;
 pub mod asset_loader;
 pub mod camera;
 pub mod frame;
 pub mod init;
+pub mod instancing;
+pub mod raycast;
 pub mod scene;
+pub mod text;
 pub use camera::*;
 pub use frame::*;
 
@@ -0,0 +1,306 @@
+use super::ray::{intersect_aabb, intersect_triangle, Ray};
+use math::{Mat3, Mat4, Vec2, Vec3};
+use render::prelude::{wgpu, GeometryData, VertexValues};
+
+/// Triangles per leaf
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// World space distance from the ray origin
+    pub distance: f32,
+    /// World space position
+    pub point: Vec3,
+    /// Index in [`GeometryData::triangle_list_indices`], divided by 3
+    pub triangle: usize,
+    /// Weights of the three triangle vertices
+    pub barycentrics: Vec3,
+    /// Interpolated `tex_coord`, when the geometry has one
+    pub uv: Option<Vec2>,
+    /// World space normal, interpolated when the geometry has normals
+    pub normal: Vec3,
+}
+
+/// Positions and triangles of a geometry
+type Triangles<'a> = (&'a [[f32; 3]], Vec<[u32; 3]>);
+
+fn triangles(data: &GeometryData) -> Option<Triangles<'_>> {
+    match data.topology() {
+        wgpu::PrimitiveTopology::TriangleList | wgpu::PrimitiveTopology::TriangleStrip => {}
+        _ => return None,
+    }
+    let positions = data.positions()?;
+    let triangles = data
+        .triangle_list_indices()
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|triangle| {
+            triangle
+                .iter()
+                .all(|index| (*index as usize) < positions.len())
+        })
+        .collect();
+    Some((positions, triangles))
+}
+
+fn corners(positions: &[[f32; 3]], triangle: [u32; 3]) -> [Vec3; 3] {
+    triangle.map(|index| Vec3::from(positions[index as usize]))
+}
+
+/// Build the hit of `triangle` at barycentrics `u`, `v`
+fn resolve(
+    data: &GeometryData,
+    ray: &Ray,
+    world: &Mat4,
+    (id, triangle): (usize, [u32; 3]),
+    (distance, u, v): (f32, f32, f32),
+    corners: [Vec3; 3],
+) -> RayHit {
+    let barycentrics = Vec3::new(1.0 - u - v, u, v);
+    let interpolate = |values: &VertexValues| {
+        triangle
+            .iter()
+            .zip(barycentrics.to_array())
+            .fold(Vec3::ZERO, |sum, (index, weight)| {
+                let value = values.get(*index as usize);
+                let value = Vec3::new(
+                    value[0],
+                    value.get(1).copied().unwrap_or(0.0),
+                    value.get(2).copied().unwrap_or(0.0),
+                );
+                sum + value * weight
+            })
+    };
+
+    let uv = data
+        .attribute(GeometryData::ATTRIBUTE_TEX_COORD)
+        .filter(|values| values.components() >= 2)
+        .map(|values| interpolate(values).truncate());
+
+    let normal = data
+        .attribute(GeometryData::ATTRIBUTE_NORMAL)
+        .filter(|values| values.components() == 3)
+        .map(interpolate)
+        .filter(|normal| normal.length_squared() > 0.0)
+        .unwrap_or_else(|| (corners[1] - corners[0]).cross(corners[2] - corners[0]));
+    let normal_matrix = Mat3::from_mat4(*world).inverse().transpose();
+    let normal = (normal_matrix * normal).normalize_or_zero();
+
+    RayHit {
+        distance,
+        point: ray.at(distance),
+        triangle: id,
+        barycentrics,
+        uv,
+        normal,
+    }
+}
+
+/// Closest hit of `ray` against a geometry placed by `world`, testing every triangle.
+/// Only triangle topologies can be hit.
+pub fn raycast_geometry(ray: &Ray, data: &GeometryData, world: &Mat4) -> Option<RayHit> {
+    let (positions, triangles) = triangles(data)?;
+    let (origin, direction) = ray.transform(&world.inverse());
+
+    let mut closest: Option<(usize, (f32, f32, f32))> = None;
+    for (id, triangle) in triangles.iter().enumerate() {
+        if let Some(hit) = intersect_triangle(origin, direction, corners(positions, *triangle)) {
+            if closest
+                .map(|(_, closest)| hit.0 < closest.0)
+                .unwrap_or(true)
+            {
+                closest = Some((id, hit));
+            }
+        }
+    }
+
+    closest.map(|(id, hit)| {
+        let triangle = triangles[id];
+        resolve(
+            data,
+            ray,
+            world,
+            (id, triangle),
+            hit,
+            corners(positions, triangle),
+        )
+    })
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// First child for inner nodes, first triangle for leaves
+    start: u32,
+    /// Triangle count, `0` for inner nodes
+    count: u32,
+}
+
+/// Bounding volume hierarchy over the triangles of a geometry, built once and
+/// reused for every ray. Rebuild it when the geometry changes.
+#[derive(Debug, Clone)]
+pub struct GeometryBvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<(usize, [u32; 3])>,
+}
+
+impl GeometryBvh {
+    pub fn new(data: &GeometryData) -> Self {
+        let (positions, triangles) = match triangles(data) {
+            Some(triangles) => triangles,
+            None => {
+                return Self {
+                    nodes: Vec::new(),
+                    triangles: Vec::new(),
+                }
+            }
+        };
+
+        let mut triangles = triangles.into_iter().enumerate().collect::<Vec<_>>();
+        let mut nodes = Vec::with_capacity(triangles.len().div_ceil(LEAF_SIZE) * 2);
+        if !triangles.is_empty() {
+            nodes.push(BvhNode {
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+                start: 0,
+                count: 0,
+            });
+            Self::build(&mut nodes, 0, &mut triangles, 0, positions);
+        }
+
+        Self { nodes, triangles }
+    }
+
+    fn build(
+        nodes: &mut Vec<BvhNode>,
+        node: usize,
+        triangles: &mut [(usize, [u32; 3])],
+        offset: usize,
+        positions: &[[f32; 3]],
+    ) {
+        let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+        let (mut centroid_min, mut centroid_max) = (min, max);
+        for (_, triangle) in triangles.iter() {
+            let corners = corners(positions, *triangle);
+            for corner in corners {
+                min = min.min(corner);
+                max = max.max(corner);
+            }
+            let centroid = (corners[0] + corners[1] + corners[2]) / 3.0;
+            centroid_min = centroid_min.min(centroid);
+            centroid_max = centroid_max.max(centroid);
+        }
+        nodes[node].min = min;
+        nodes[node].max = max;
+
+        if triangles.len() <= LEAF_SIZE {
+            nodes[node].start = offset as u32;
+            nodes[node].count = triangles.len() as u32;
+            return;
+        }
+
+        // Median split along the longest axis of the centroids
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let centroid = |triangle: &[u32; 3]| {
+            triangle
+                .iter()
+                .map(|index| positions[*index as usize][axis])
+                .sum::<f32>()
+        };
+        let middle = triangles.len() / 2;
+        triangles
+            .select_nth_unstable_by(middle, |(_, a), (_, b)| centroid(a).total_cmp(&centroid(b)));
+
+        let left = nodes.len();
+        nodes[node].start = left as u32;
+        for _ in 0..2 {
+            nodes.push(BvhNode {
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+                start: 0,
+                count: 0,
+            });
+        }
+        let (first, second) = triangles.split_at_mut(middle);
+        Self::build(nodes, left, first, offset, positions);
+        Self::build(nodes, left + 1, second, offset + middle, positions);
+    }
+
+    /// Number of triangles indexed
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    /// Local space bounds, `None` when nothing can be hit
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.nodes.first().map(|node| (node.min, node.max))
+    }
+
+    /// Closest hit of `ray` against `data`, which must be the geometry the
+    /// hierarchy was built from, placed by `world`
+    pub fn raycast(&self, ray: &Ray, data: &GeometryData, world: &Mat4) -> Option<RayHit> {
+        let positions = data.positions()?;
+        let (origin, direction) = ray.transform(&world.inverse());
+        let inverse_direction = direction.recip();
+
+        let mut closest: Option<(usize, (f32, f32, f32))> = None;
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let entry = match intersect_aabb(origin, inverse_direction, node.min, node.max) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if closest.map(|(_, hit)| entry > hit.0).unwrap_or(false) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.start as usize);
+                stack.push(node.start as usize + 1);
+                continue;
+            }
+
+            let start = node.start as usize;
+            for slot in start..start + node.count as usize {
+                let (_, triangle) = self.triangles[slot];
+                let corners = corners(positions, triangle);
+                if let Some(hit) = intersect_triangle(origin, direction, corners) {
+                    if closest
+                        .map(|(_, closest)| hit.0 < closest.0)
+                        .unwrap_or(true)
+                    {
+                        closest = Some((slot, hit));
+                    }
+                }
+            }
+        }
+
+        closest.map(|(slot, hit)| {
+            let triangle = self.triangles[slot];
+            resolve(
+                data,
+                ray,
+                world,
+                triangle,
+                hit,
+                corners(positions, triangle.1),
+            )
+        })
+    }
+}
@@ -0,0 +1,6 @@
+mod bvh;
+mod ray;
+mod scene;
+pub use bvh::*;
+pub use ray::Ray;
+pub use scene::*;
@@ -0,0 +1,88 @@
+use crate::camera::Camera;
+use math::{Mat4, Vec2, Vec3, Vec4Swizzles};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Normalized direction
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Point at `distance` along the ray
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Ray through a point in normalized device coordinates, `[-1, 1]` with y up.
+    /// The camera matrices must be up to date.
+    pub fn from_camera(camera: &Camera, ndc: Vec2) -> Self {
+        let inverse = camera.get_projection_view_matrix().inverse();
+        let unproject = |z: f32| {
+            let point = inverse * ndc.extend(z).extend(1.0);
+            point.xyz() / point.w
+        };
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        Self::new(near, far - near)
+    }
+
+    /// Transform by `matrix` without normalizing, distances stay comparable
+    /// between both spaces
+    pub(super) fn transform(&self, matrix: &Mat4) -> (Vec3, Vec3) {
+        (
+            matrix.transform_point3(self.origin),
+            matrix.transform_vector3(self.direction),
+        )
+    }
+}
+
+/// Ray to triangle intersection, double sided. Returns the distance and the
+/// barycentrics of the second and third vertices.
+pub(super) fn intersect_triangle(
+    origin: Vec3,
+    direction: Vec3,
+    [a, b, c]: [Vec3; 3],
+) -> Option<(f32, f32, f32)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let t = origin - a;
+    let u = t.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = t.cross(ab);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = ac.dot(q) * inverse;
+    (distance >= 0.0).then_some((distance, u, v))
+}
+
+/// Slab test, returns the entry distance
+pub(super) fn intersect_aabb(
+    origin: Vec3,
+    inverse_direction: Vec3,
+    min: Vec3,
+    max: Vec3,
+) -> Option<f32> {
+    let a = (min - origin) * inverse_direction;
+    let b = (max - origin) * inverse_direction;
+    let near = a.min(b).max_element();
+    let far = a.max(b).min_element();
+    (far >= near.max(0.0)).then_some(near.max(0.0))
+}
@@ -0,0 +1,111 @@
+use super::{raycast_geometry, GeometryBvh, Ray, RayHit};
+use crate::asset_loader::GltfScene;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneHit {
+    /// Index in [`GltfScene::nodes`]
+    pub node: usize,
+    /// Index in [`GltfScene::meshes`]
+    pub mesh: usize,
+    /// Primitive of the mesh
+    pub primitive: usize,
+    pub hit: RayHit,
+}
+
+/// Keeps the closest of the hits
+fn closest(current: Option<SceneHit>, hit: SceneHit) -> Option<SceneHit> {
+    match current {
+        Some(current) if current.hit.distance <= hit.hit.distance => Some(current),
+        _ => Some(hit),
+    }
+}
+
+/// Picks scene meshes with one hierarchy per primitive.
+/// Rebuild it when the meshes of the scene change.
+#[derive(Debug, Clone)]
+pub struct ScenePicker {
+    meshes: Vec<Vec<GeometryBvh>>,
+}
+
+impl ScenePicker {
+    pub fn new(scene: &GltfScene) -> Self {
+        Self {
+            meshes: scene
+                .meshes
+                .iter()
+                .map(|mesh| {
+                    mesh.primitives
+                        .iter()
+                        .map(|primitive| GeometryBvh::new(&primitive.geometry))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Closest mesh hit by `ray`. World matrices must be up to date, see
+    /// [`GltfScene::update_world_matrices`].
+    pub fn pick(&self, scene: &GltfScene, ray: &Ray) -> Option<SceneHit> {
+        let mut result = None;
+        for (node_index, node) in scene.nodes.iter().enumerate() {
+            let mesh_index = match node.mesh {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+            let (mesh, bvhs) = match (scene.meshes.get(mesh_index), self.meshes.get(mesh_index)) {
+                (Some(mesh), Some(bvhs)) => (mesh, bvhs),
+                _ => continue,
+            };
+            let world = node.node.get_world_matrix();
+            for (primitive, (data, bvh)) in mesh
+                .primitives
+                .iter()
+                .map(|primitive| &primitive.geometry)
+                .zip(bvhs)
+                .enumerate()
+            {
+                if let Some(hit) = bvh.raycast(ray, data, world) {
+                    result = closest(
+                        result,
+                        SceneHit {
+                            node: node_index,
+                            mesh: mesh_index,
+                            primitive,
+                            hit,
+                        },
+                    );
+                }
+            }
+        }
+        result
+    }
+}
+
+impl GltfScene {
+    /// Closest mesh hit by `ray`, testing every triangle. Prefer a [`ScenePicker`]
+    /// for repeated picking. World matrices must be up to date.
+    pub fn raycast(&self, ray: &Ray) -> Option<SceneHit> {
+        let mut result = None;
+        for (node_index, node) in self.nodes.iter().enumerate() {
+            let mesh = match node.mesh.and_then(|mesh| self.meshes.get(mesh)) {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+            let world = node.node.get_world_matrix();
+            for (primitive, data) in mesh.primitives.iter().enumerate() {
+                if let Some(hit) = raycast_geometry(ray, &data.geometry, world) {
+                    result = closest(
+                        result,
+                        SceneHit {
+                            node: node_index,
+                            mesh: node.mesh.unwrap_or_default(),
+                            primitive,
+                            hit,
+                        },
+                    );
+                }
+            }
+        }
+        result
+    }
+}
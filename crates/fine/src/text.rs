@@ -0,0 +1,310 @@
+use crate::asset_loader::{BitmapFont, BitmapGlyph};
+use render::prelude::{Context, GeometryData, IndiceValues, RawTexture, TextureAtlas, TextureRect};
+
+/// Attribute holding the atlas layer of each glyph vertex
+pub const ATTRIBUTE_PAGE: &str = "page";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextLayoutOptions {
+    /// Wrap lines longer than this width, after scaling
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+    pub scale: f32,
+    /// Multiplies the font line height
+    pub line_spacing: f32,
+}
+
+impl Default for TextLayoutOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            align: TextAlign::Left,
+            scale: 1.0,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// Glyph placed by [`layout_text`]. Positions go right and down from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub character: char,
+    pub line: usize,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Normalized `[left, top, right, bottom]` in the page
+    pub uv: [f32; 4],
+    pub page: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// Width of each line
+    pub line_widths: Vec<f32>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TextLayout {
+    /// One quad per glyph with `position`, `tex_coord` and [`ATTRIBUTE_PAGE`].
+    /// Positions are flipped to go up, the first line's top is at `y = 0`.
+    pub fn to_geometry(&self) -> GeometryData {
+        let count = self.glyphs.len();
+        let mut positions = Vec::with_capacity(count * 4);
+        let mut tex_coords = Vec::with_capacity(count * 4);
+        let mut pages = Vec::with_capacity(count * 4);
+        let mut indices = Vec::with_capacity(count * 6);
+
+        for glyph in self.glyphs.iter() {
+            let first = positions.len() as u32;
+            let [left, top, right, bottom] = glyph.uv;
+            let (x0, x1) = (glyph.x, glyph.x + glyph.width);
+            let (y0, y1) = (-glyph.y, -glyph.y - glyph.height);
+            positions.extend([[x0, y1, 0.0], [x1, y1, 0.0], [x1, y0, 0.0], [x0, y0, 0.0]]);
+            tex_coords.extend([[left, bottom], [right, bottom], [right, top], [left, top]]);
+            pages.extend([glyph.page as f32; 4]);
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        let mut data = GeometryData::new();
+        data.set_attribute(GeometryData::ATTRIBUTE_POSITION, positions.into());
+        data.set_attribute(GeometryData::ATTRIBUTE_TEX_COORD, tex_coords.into());
+        data.set_attribute(ATTRIBUTE_PAGE, pages.into());
+        data.set_indices(Some(IndiceValues::auto(indices)));
+        data
+    }
+}
+
+/// Pen advance of `character` after `previous`, unscaled
+fn advance(
+    font: &BitmapFont,
+    previous: Option<char>,
+    character: char,
+) -> (i32, Option<&BitmapGlyph>) {
+    let kerning = previous
+        .map(|previous| font.kerning(previous, character))
+        .unwrap_or(0);
+    (kerning, font.glyph(character))
+}
+
+/// Index of the last character fitting on the line starting at `start`
+fn line_end(
+    font: &BitmapFont,
+    characters: &[char],
+    start: usize,
+    max_width: Option<f32>,
+    scale: f32,
+) -> usize {
+    let max_width = match max_width {
+        Some(max_width) => max_width / scale,
+        None => return characters.len(),
+    };
+
+    let mut pen = 0i32;
+    let mut previous = None;
+    let mut last_space = None;
+    // Leading spaces are kept on the line, a line breaks after a word only
+    let mut word = false;
+    for (index, character) in characters.iter().enumerate().skip(start) {
+        let (kerning, glyph) = advance(font, previous, *character);
+        if let Some(glyph) = glyph {
+            let right = pen + kerning + glyph.x_offset + glyph.width as i32;
+            if *character != ' ' && word && right as f32 > max_width {
+                return last_space.unwrap_or(index);
+            }
+            pen += kerning + glyph.x_advance;
+        }
+        if *character == ' ' {
+            if word {
+                last_space = Some(index);
+            }
+        } else {
+            word = true;
+        }
+        previous = Some(*character);
+    }
+    characters.len()
+}
+
+/// Place the glyphs of `text`. Lines break on `\n` and wrap on spaces at
+/// `max_width`, words longer than a line are split. Characters missing from
+/// the font are skipped.
+pub fn layout_text(font: &BitmapFont, text: &str, options: &TextLayoutOptions) -> TextLayout {
+    let scale = options.scale;
+    let line_height = font.line_height as f32 * options.line_spacing * scale;
+    let (page_width, page_height) = (font.scale_w.max(1) as f32, font.scale_h.max(1) as f32);
+
+    let mut layout = TextLayout::default();
+    let mut line = 0;
+    for paragraph in text.split('\n') {
+        let characters = paragraph.trim_end_matches('\r').chars().collect::<Vec<_>>();
+        let mut start = 0;
+        loop {
+            let end = line_end(font, &characters, start, options.max_width, scale);
+
+            let mut pen = 0i32;
+            let mut width = 0i32;
+            let mut previous = None;
+            for character in characters[start..end].iter() {
+                let (kerning, glyph) = advance(font, previous, *character);
+                previous = Some(*character);
+                let glyph = match glyph {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                pen += kerning;
+                if glyph.width > 0 && glyph.height > 0 {
+                    layout.glyphs.push(PositionedGlyph {
+                        character: *character,
+                        line,
+                        x: (pen + glyph.x_offset) as f32 * scale,
+                        y: line as f32 * line_height + glyph.y_offset as f32 * scale,
+                        width: glyph.width as f32 * scale,
+                        height: glyph.height as f32 * scale,
+                        uv: [
+                            glyph.x as f32 / page_width,
+                            glyph.y as f32 / page_height,
+                            (glyph.x + glyph.width) as f32 / page_width,
+                            (glyph.y + glyph.height) as f32 / page_height,
+                        ],
+                        page: glyph.page,
+                    });
+                }
+                pen += glyph.x_advance;
+                // Trailing spaces do not count
+                if *character != ' ' {
+                    width = pen.max(pen - glyph.x_advance + glyph.x_offset + glyph.width as i32);
+                }
+            }
+            layout.line_widths.push(width as f32 * scale);
+            line += 1;
+
+            // Spaces at a wrap are dropped
+            start = end;
+            while start < characters.len() && characters[start] == ' ' {
+                start += 1;
+            }
+            if start >= characters.len() {
+                break;
+            }
+        }
+    }
+
+    layout.width = layout.line_widths.iter().copied().fold(0.0, f32::max);
+    layout.height = line as f32 * line_height;
+
+    let box_width = options.max_width.unwrap_or(layout.width);
+    if options.align != TextAlign::Left {
+        for glyph in layout.glyphs.iter_mut() {
+            let free = box_width - layout.line_widths[glyph.line];
+            glyph.x += match options.align {
+                TextAlign::Center => free / 2.0,
+                _ => free,
+            };
+        }
+    }
+
+    layout
+}
+
+impl BitmapFont {
+    /// Copy the pages in the layers of a new atlas, page `i` in layer `i`.
+    /// Pages must be `scale_w` by `scale_h` and usable as copy source.
+    /// `None` when a page cannot be added.
+    pub fn create_atlas(&self, gpu: &mut Context, pages: &[RawTexture]) -> Option<TextureAtlas> {
+        let mut atlas = TextureAtlas::new(gpu, self.scale_w, self.scale_h, pages.len() as u32);
+        for (layer, page) in pages.iter().enumerate() {
+            let name = self.pages.get(layer).cloned().unwrap_or_default();
+            atlas.append_raw_texture(
+                name,
+                layer as u32,
+                gpu,
+                page,
+                TextureRect::new(0, 0, page.width(), page.height()),
+                Some((0, 0)),
+            )?;
+        }
+        Some(atlas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 5 pixels wide `a` and space
+    fn font() -> BitmapFont {
+        let mut font = BitmapFont {
+            line_height: 10,
+            scale_w: 64,
+            scale_h: 64,
+            ..BitmapFont::default()
+        };
+        for (character, width) in [('a', 5), (' ', 0)] {
+            let glyph = BitmapGlyph {
+                id: character as u32,
+                width,
+                height: 8,
+                x_advance: 5,
+                ..BitmapGlyph::default()
+            };
+            font.glyphs.insert(glyph.id, glyph);
+        }
+        font
+    }
+
+    fn wrapped(text: &str, max_width: f32) -> TextLayout {
+        let options = TextLayoutOptions {
+            max_width: Some(max_width),
+            ..TextLayoutOptions::default()
+        };
+        layout_text(&font(), text, &options)
+    }
+
+    #[test]
+    fn lines_wrap_on_spaces() {
+        let layout = wrapped("aa aa aa", 25.0);
+        assert_eq!(layout.line_widths, [25.0, 10.0]);
+        assert_eq!(layout.glyphs.len(), 6);
+        let last = layout.glyphs.last().unwrap();
+        assert_eq!((last.line, last.x, last.y), (1, 5.0, 10.0));
+        assert_eq!((layout.width, layout.height), (25.0, 20.0));
+    }
+
+    #[test]
+    fn long_words_are_split() {
+        let layout = wrapped("aaaaa", 10.0);
+        assert_eq!(layout.line_widths, [10.0, 10.0, 5.0]);
+    }
+
+    #[test]
+    fn leading_spaces_stay_on_the_first_line() {
+        let layout = wrapped("  aaaaa", 10.0);
+        assert_eq!(layout.line_widths, [15.0, 10.0, 10.0]);
+        assert_eq!(layout.glyphs[0].x, 10.0);
+    }
+
+    #[test]
+    fn paragraphs_and_alignment() {
+        let options = TextLayoutOptions {
+            align: TextAlign::Right,
+            scale: 2.0,
+            ..TextLayoutOptions::default()
+        };
+        let layout = layout_text(&font(), "aa\r\n\na", &options);
+        assert_eq!(layout.line_widths, [20.0, 0.0, 10.0]);
+        assert_eq!(layout.height, 60.0);
+        let last = layout.glyphs.last().unwrap();
+        assert_eq!((last.line, last.x, last.y), (2, 10.0, 40.0));
+    }
+}
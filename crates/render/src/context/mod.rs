@@ -98,10 +98,19 @@ impl Context {
             })
     }
 
+    /// Features enabled on the device
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
     pub fn create_texture(&self, desc: &wgpu::TextureDescriptor) -> wgpu::Texture {
         self.device.create_texture(desc)
     }
 
+    pub fn create_sampler(&self, desc: &wgpu::SamplerDescriptor) -> wgpu::Sampler {
+        self.device.create_sampler(desc)
+    }
+
     pub fn create_pipeline(&self) -> PipelineBuilder {
         PipelineBuilder::new(self.device.clone())
     }
@@ -136,7 +145,7 @@ impl Context {
         offset: wgpu::BufferAddress,
         data: &[u8],
     ) {
-        self.queue.write_buffer(&buffer, offset, data);
+        self.queue.write_buffer(buffer, offset, data);
     }
 
     pub fn enqueue_write_texture(
@@ -156,7 +165,7 @@ impl Context {
         self.encoder.begin_render_pass(desc)
     }
 
-    pub fn begin_compute_pass(&mut self, desc: &wgpu::ComputePassDescriptor) -> wgpu::ComputePass {
+    pub fn begin_compute_pass(&mut self, desc: &wgpu::ComputePassDescriptor) -> wgpu::ComputePass<'_> {
         self.encoder.begin_compute_pass(desc)
     }
 
@@ -167,7 +176,7 @@ impl Context {
         let current = std::mem::replace(&mut self.encoder, encoder);
         self.queue.submit(Some(current.finish()));
 
-        let output = std::mem::replace(&mut surface.output, None);
+        let output = surface.output.take();
         if let Some(output) = output {
             output.present();
         }
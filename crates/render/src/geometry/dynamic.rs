@@ -0,0 +1,419 @@
+use super::{GeometryData, GeometryError, IndiceValues};
+use crate::context::Context;
+use std::ops::Range;
+
+/// GPU buffer mirrored on the CPU so partial writes can be aligned and the buffer
+/// can be reallocated without reading it back.
+struct DynamicBuffer {
+    buffer: wgpu::Buffer,
+    usage: wgpu::BufferUsages,
+    capacity: u64,
+    data: Vec<u8>,
+}
+
+impl DynamicBuffer {
+    fn new(context: &Context, usage: wgpu::BufferUsages, capacity: u64) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let capacity = Self::align(capacity.max(1));
+        Self {
+            buffer: context.create_buffer(false, usage, capacity),
+            usage,
+            capacity,
+            data: Vec::new(),
+        }
+    }
+
+    fn align(size: u64) -> u64 {
+        let alignment = wgpu::COPY_BUFFER_ALIGNMENT;
+        size.div_ceil(alignment) * alignment
+    }
+
+    fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    fn reserve(&mut self, context: &Context, size: u64) {
+        if size <= self.capacity() {
+            return;
+        }
+        self.capacity = Self::align(size.next_power_of_two());
+        self.buffer = context.create_buffer(false, self.usage, self.capacity);
+        self.flush(context, 0..self.data.len() as u64);
+    }
+
+    fn write(&mut self, context: &Context, offset: u64, bytes: &[u8]) {
+        let end = offset + bytes.len() as u64;
+        if end as usize > self.data.len() {
+            self.data.resize(end as usize, 0);
+        }
+        self.data[offset as usize..end as usize].copy_from_slice(bytes);
+
+        if end > self.capacity() {
+            // Reallocating uploads the whole mirror
+            self.reserve(context, end);
+        } else {
+            self.flush(context, offset..end);
+        }
+    }
+
+    fn truncate(&mut self, len: u64) {
+        self.data.truncate(len as usize);
+    }
+
+    /// Upload a range of the mirror, widened to the copy alignment
+    fn flush(&mut self, context: &Context, range: Range<u64>) {
+        let alignment = wgpu::COPY_BUFFER_ALIGNMENT;
+        let start = range.start / alignment * alignment;
+        let end = Self::align(range.end);
+        if end as usize > self.data.len() {
+            self.data.resize(end as usize, 0);
+        }
+        if start < end {
+            context.enqueue_write_buffer(
+                &self.buffer,
+                start,
+                &self.data[start as usize..end as usize],
+            );
+        }
+    }
+}
+
+/// Geometry whose vertices and indices can be rewritten after creation.
+/// Buffers grow to the next power of two when an update does not fit.
+pub struct DynamicGeometry {
+    vertices: DynamicBuffer,
+    vertex_count: usize,
+    vertex_size: u64,
+    indices: Option<DynamicBuffer>,
+    index_count: usize,
+    index_format: wgpu::IndexFormat,
+}
+
+impl DynamicGeometry {
+    /// Upload a geometry after checking it with [`GeometryData::validate`]
+    pub fn new(context: &Context, geometry: &GeometryData) -> Result<Self, GeometryError> {
+        geometry.validate()?;
+        let vertex_count = geometry.compute_vertex_count();
+        let mut dynamic = Self::with_capacity(
+            context,
+            geometry.vertex_size(),
+            vertex_count,
+            geometry
+                .indices()
+                .map(|indices| (indices.index_format(), indices.len())),
+        );
+        dynamic.set_data(context, geometry)?;
+        Ok(dynamic)
+    }
+
+    /// Create empty buffers for `vertex_capacity` vertices and, when given, an index
+    /// buffer of the format and capacity
+    pub fn with_capacity(
+        context: &Context,
+        vertex_size: u64,
+        vertex_capacity: usize,
+        indices: Option<(wgpu::IndexFormat, usize)>,
+    ) -> Self {
+        let index_format = indices
+            .map(|(format, _)| format)
+            .unwrap_or(wgpu::IndexFormat::Uint32);
+
+        Self {
+            vertices: DynamicBuffer::new(
+                context,
+                wgpu::BufferUsages::VERTEX,
+                vertex_capacity as u64 * vertex_size,
+            ),
+            vertex_count: 0,
+            vertex_size,
+            indices: indices.map(|(format, capacity)| {
+                DynamicBuffer::new(
+                    context,
+                    wgpu::BufferUsages::INDEX,
+                    capacity as u64 * index_size(format),
+                )
+            }),
+            index_count: 0,
+            index_format,
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn vertex_capacity(&self) -> usize {
+        (self.vertices.capacity() / self.vertex_size.max(1)) as usize
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    pub fn index_capacity(&self) -> usize {
+        self.indices
+            .as_ref()
+            .map(|indices| (indices.capacity() / index_size(self.index_format)) as usize)
+            .unwrap_or(0)
+    }
+
+    /// Replace every vertex and index, after checking the geometry with
+    /// [`GeometryData::validate`]
+    pub fn set_data(
+        &mut self,
+        context: &Context,
+        geometry: &GeometryData,
+    ) -> Result<(), GeometryError> {
+        geometry.validate()?;
+        assert_eq!(
+            geometry.vertex_size(),
+            self.vertex_size,
+            "[fine::graphic::DynamicGeometry] Vertex size does not match."
+        );
+
+        self.vertices.truncate(0);
+        self.vertex_count = 0;
+        self.update_vertices(context, 0, &geometry.interleave());
+
+        self.index_count = 0;
+        match geometry.indices() {
+            Some(indices) => {
+                if let Some(buffer) = self.indices.as_mut() {
+                    buffer.truncate(0);
+                }
+                self.update_indices(context, 0, indices);
+            }
+            // Draw the vertices in order
+            None => self.indices = None,
+        }
+        Ok(())
+    }
+
+    /// Write interleaved vertices starting at `first_vertex`
+    pub fn update_vertices(&mut self, context: &Context, first_vertex: usize, bytes: &[u8]) {
+        let offset = first_vertex as u64 * self.vertex_size;
+        self.vertices.write(context, offset, bytes);
+
+        let vertex_count = first_vertex + (bytes.len() as u64 / self.vertex_size.max(1)) as usize;
+        self.vertex_count = self.vertex_count.max(vertex_count);
+    }
+
+    /// Write indices starting at `first_index`, converted to the geometry index format.
+    /// Geometry created without indices gets an index buffer. A `Uint16` buffer
+    /// is widened to `Uint32` when a value does not fit.
+    pub fn update_indices(&mut self, context: &Context, first_index: usize, values: &IndiceValues) {
+        let values =
+            IndiceValues::with_format(values.iter_with_restart().collect(), self.index_format);
+        if values.index_format() != self.index_format {
+            self.widen_indices(context);
+        }
+        let format = self.index_format;
+        let offset = first_index as u64 * index_size(format);
+        self.indices
+            .get_or_insert_with(|| {
+                DynamicBuffer::new(
+                    context,
+                    wgpu::BufferUsages::INDEX,
+                    (first_index + values.len()) as u64 * index_size(format),
+                )
+            })
+            .write(context, offset, values.get_bytes());
+        self.index_count = self.index_count.max(first_index + values.len());
+    }
+
+    /// Switch the index buffer to `Uint32`, keeping the written indices
+    fn widen_indices(&mut self, context: &Context) {
+        self.index_format = wgpu::IndexFormat::Uint32;
+        let buffer = match self.indices.take() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let values = buffer
+            .data
+            .chunks_exact(2)
+            .map(|bytes| match u16::from_le_bytes([bytes[0], bytes[1]]) {
+                u16::MAX => u32::MAX,
+                value => value as u32,
+            })
+            .collect::<Vec<_>>();
+        let mut widened =
+            DynamicBuffer::new(context, wgpu::BufferUsages::INDEX, buffer.capacity() * 2);
+        widened.write(context, 0, bytemuck::cast_slice(&values));
+        self.indices = Some(widened);
+    }
+
+    /// Shrink or extend the number of vertices drawn
+    pub fn set_vertex_count(&mut self, vertex_count: usize) {
+        self.vertex_count = vertex_count;
+    }
+
+    /// Shrink or extend the number of indices drawn
+    pub fn set_index_count(&mut self, index_count: usize) {
+        self.index_count = index_count;
+    }
+
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, slot: u32) {
+        pass.set_vertex_buffer(slot, self.vertices.buffer.slice(..));
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        match self.indices.as_ref() {
+            Some(indices) => {
+                pass.set_index_buffer(indices.buffer.slice(..), self.index_format);
+                pass.draw_indexed(0..self.index_count as u32, 0, 0..1);
+            }
+            None => {
+                pass.draw(0..self.vertex_count as u32, 0..1);
+            }
+        }
+    }
+}
+
+fn index_size(format: wgpu::IndexFormat) -> u64 {
+    match format {
+        wgpu::IndexFormat::Uint16 => 2,
+        wgpu::IndexFormat::Uint32 => 4,
+    }
+}
+
+/// Range of a geometry pushed this frame into a [`StreamingGeometry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamingRange {
+    pub base_vertex: i32,
+    pub first_index: u32,
+    pub count: u32,
+}
+
+/// Ring buffer for geometry rebuilt every frame, like trails, debug lines or UI.
+/// Each frame in flight writes to its own region so the GPU never reads a region
+/// being rewritten.
+pub struct StreamingGeometry {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    vertex_size: u64,
+    frame_count: u32,
+    vertices_per_frame: u32,
+    indices_per_frame: u32,
+    frame: u32,
+    vertex_cursor: u32,
+    index_cursor: u32,
+}
+
+impl StreamingGeometry {
+    pub fn new(
+        context: &Context,
+        vertex_size: u64,
+        vertices_per_frame: u32,
+        indices_per_frame: u32,
+        frame_count: u32,
+    ) -> Self {
+        // Keep every frame region aligned for queue writes
+        let indices_per_frame = (indices_per_frame + 1) & !1;
+
+        Self {
+            vertex_buffer: context.create_buffer(
+                false,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                (vertices_per_frame * frame_count) as u64 * vertex_size,
+            ),
+            index_buffer: context.create_buffer(
+                false,
+                wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                (indices_per_frame * frame_count) as u64 * 4,
+            ),
+            vertex_size,
+            frame_count,
+            vertices_per_frame,
+            indices_per_frame,
+            frame: 0,
+            vertex_cursor: 0,
+            index_cursor: 0,
+        }
+    }
+
+    /// Move to the next region of the ring, discarding what was pushed there before
+    pub fn begin_frame(&mut self) {
+        self.frame = (self.frame + 1) % self.frame_count;
+        self.vertex_cursor = 0;
+        self.index_cursor = 0;
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Remaining vertex and index room in the current frame
+    pub fn remaining(&self) -> (u32, u32) {
+        (
+            self.vertices_per_frame - self.vertex_cursor,
+            self.indices_per_frame - self.index_cursor,
+        )
+    }
+
+    /// Append a geometry to the current frame, after checking it with
+    /// [`GeometryData::validate`]. Returns `None` when it does not fit.
+    pub fn push(
+        &mut self,
+        context: &Context,
+        geometry: &GeometryData,
+    ) -> Result<Option<StreamingRange>, GeometryError> {
+        geometry.validate()?;
+        let indices = geometry.vertex_indices();
+        Ok(self.push_raw(context, &geometry.interleave(), &indices))
+    }
+
+    /// Append interleaved vertices and indices relative to the first of these vertices
+    pub fn push_raw(
+        &mut self,
+        context: &Context,
+        vertices: &[u8],
+        indices: &[u32],
+    ) -> Option<StreamingRange> {
+        let vertex_count = (vertices.len() as u64 / self.vertex_size.max(1)) as u32;
+        let index_count = indices.len() as u32;
+        let (vertex_room, index_room) = self.remaining();
+        if vertex_count > vertex_room || index_count > index_room {
+            return None;
+        }
+
+        let base_vertex = self.frame * self.vertices_per_frame + self.vertex_cursor;
+        let first_index = self.frame * self.indices_per_frame + self.index_cursor;
+
+        if !vertices.is_empty() {
+            context.enqueue_write_buffer(
+                &self.vertex_buffer,
+                base_vertex as u64 * self.vertex_size,
+                vertices,
+            );
+        }
+        if !indices.is_empty() {
+            context.enqueue_write_buffer(
+                &self.index_buffer,
+                first_index as u64 * 4,
+                bytemuck::cast_slice(indices),
+            );
+        }
+
+        self.vertex_cursor += vertex_count;
+        self.index_cursor += index_count;
+
+        Some(StreamingRange {
+            base_vertex: base_vertex as i32,
+            first_index,
+            count: index_count,
+        })
+    }
+
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, slot: u32) {
+        pass.set_vertex_buffer(slot, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, range: &StreamingRange) {
+        pass.draw_indexed(
+            range.first_index..range.first_index + range.count,
+            range.base_vertex,
+            0..1,
+        );
+    }
+}
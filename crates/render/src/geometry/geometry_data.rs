@@ -3,38 +3,121 @@ use crate::prelude::Context;
 pub use super::IndiceValues;
 pub use super::VertexValues;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GeometryData {
+    vertex_names: Vec<String>,
     vertex_values: Vec<VertexValues>,
     index_values: Option<IndiceValues>,
     vertex_size: u64,
+    topology: wgpu::PrimitiveTopology,
+    pub(super) morph_targets: Vec<super::MorphTarget>,
+}
+
+impl Default for GeometryData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GeometryData {
+    pub const ATTRIBUTE_POSITION: &'static str = "position";
+    pub const ATTRIBUTE_NORMAL: &'static str = "normal";
+    pub const ATTRIBUTE_TEX_COORD: &'static str = "tex_coord";
+    pub const ATTRIBUTE_TANGENT: &'static str = "tangent";
+    pub const ATTRIBUTE_COLOR: &'static str = "color";
+    pub const ATTRIBUTE_JOINTS: &'static str = "joints";
+    pub const ATTRIBUTE_WEIGHTS: &'static str = "weights";
+
     pub fn new() -> Self {
         Self {
+            vertex_names: Vec::new(),
             vertex_values: Vec::new(),
             index_values: None,
             vertex_size: 0,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            morph_targets: Vec::new(),
         }
     }
 
-    pub fn set_attribute(&mut self, values: VertexValues) {
+    /// Set the values of a named attribute. Replacing an existing attribute keeps its location.
+    pub fn set_attribute(&mut self, name: impl Into<String>, values: VertexValues) {
+        let name = name.into();
         self.vertex_size += values.attribute_size();
-        self.vertex_values.push(values);
+        match self.vertex_names.iter().position(|n| *n == name) {
+            Some(index) => {
+                self.vertex_size -= self.vertex_values[index].attribute_size();
+                self.vertex_values[index] = values;
+            }
+            None => {
+                self.vertex_names.push(name);
+                self.vertex_values.push(values);
+            }
+        }
     }
 
     pub fn set_indices(&mut self, values: Option<IndiceValues>) {
         self.index_values = values;
     }
 
+    /// Strip topologies restart on [`IndiceValues::restart_value`]
+    pub fn set_topology(&mut self, topology: wgpu::PrimitiveTopology) {
+        self.topology = topology;
+    }
+
+    pub fn topology(&self) -> wgpu::PrimitiveTopology {
+        self.topology
+    }
+
+    /// Whether indices may contain restart values
+    pub fn has_restart(&self) -> bool {
+        self.topology.is_strip() && self.index_values.is_some()
+    }
+
+    /// Primitive state to create a pipeline drawing this geometry
+    pub fn primitive_state(&self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: self.topology,
+            strip_index_format: if self.topology.is_strip() {
+                self.index_values.as_ref().map(|indices| indices.index_format())
+            } else {
+                None
+            },
+            ..Default::default()
+        }
+    }
+
     #[inline]
     pub fn vertex_size(&self) -> u64 {
         self.vertex_size
     }
 
     pub fn values(&self) -> Vec<&VertexValues> {
-        self.vertex_values.iter().map(|values| values).collect()
+        self.vertex_values.iter().collect()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&VertexValues> {
+        self.vertex_names
+            .iter()
+            .position(|n| n == name)
+            .map(|index| &self.vertex_values[index])
+    }
+
+    pub fn attribute_names(&self) -> impl Iterator<Item = &str> {
+        self.vertex_names.iter().map(|name| name.as_str())
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &VertexValues)> {
+        self.vertex_names
+            .iter()
+            .map(|name| name.as_str())
+            .zip(self.vertex_values.iter())
+    }
+
+    pub fn positions(&self) -> Option<&[[f32; 3]]> {
+        match self.attribute(Self::ATTRIBUTE_POSITION) {
+            Some(VertexValues::Float32x3(values)) => Some(values),
+            _ => None,
+        }
     }
 
     pub fn indices(&self) -> Option<&IndiceValues> {
@@ -45,22 +128,50 @@ impl GeometryData {
         self.index_values.as_ref().map(|values| values.len())
     }
 
-    pub fn compute_vertex_count(&self) -> usize {
-        let mut vertex_count: Option<usize> = None;
-        for (index, values) in self.vertex_values.iter().enumerate() {
-            let count = values.len();
-            if let Some(vertex_count) = vertex_count {
-                assert_eq!(
-                    vertex_count, count,
-                    "Attribute at index {index} does not have the same number of vertex"
-                );
-            }
-            vertex_count = Some(count);
+    /// Move every vertex to `remap[vertex]`, dropping vertices mapped to `u32::MAX`,
+    /// and rewrite indices accordingly. Restart values are kept.
+    pub fn remap_vertices(&mut self, remap: &[u32], vertex_count: usize) {
+        for values in self.vertex_values.iter_mut() {
+            values.remap(remap, vertex_count);
+        }
+        for target in self.morph_targets.iter_mut() {
+            target.remap(remap, vertex_count);
+        }
+        let restart = self.has_restart();
+        if let Some(indices) = self.index_values.as_ref() {
+            let values = self
+                .vertex_indices()
+                .into_iter()
+                .map(|index| {
+                    if restart && index == u32::MAX {
+                        index
+                    } else {
+                        remap[index as usize]
+                    }
+                })
+                .collect();
+            self.index_values = Some(IndiceValues::with_format(values, indices.index_format()));
         }
-        vertex_count.unwrap_or(0)
+    }
+
+    /// Number of values of the attributes, which must all have the same length.
+    /// Use [`GeometryData::validate`] to check it without panicking.
+    pub fn compute_vertex_count(&self) -> usize {
+        let mut lengths = self.vertex_values.iter().map(|values| values.len());
+        let vertex_count = lengths.next().unwrap_or(0);
+        assert!(
+            lengths.all(|length| length == vertex_count),
+            "[fine::graphic::GeometryData] Attributes do not have the same length."
+        );
+        vertex_count
     }
 
     pub fn create_vertex_buffer(&self, cx: &Context) -> wgpu::Buffer {
+        cx.create_buffer_with_data(wgpu::BufferUsages::VERTEX, &self.interleave())
+    }
+
+    /// Pack all attributes into a single interleaved vertex buffer.
+    pub fn interleave(&self) -> Vec<u8> {
         let vertex_count = self.compute_vertex_count();
         let vertex_size = self.vertex_size as usize;
         let mut attribute_offset = 0;
@@ -68,13 +179,14 @@ impl GeometryData {
         for values in self.vertex_values.iter() {
             let attribute_size = values.attribute_size() as usize;
             let bytes = values.get_bytes();
-            for (index, chunk) in bytes.chunks_exact(attribute_size).enumerate() {
+            let chunks = bytes.chunks_exact(attribute_size).take(vertex_count);
+            for (index, chunk) in chunks.enumerate() {
                 let offset = index * vertex_size + attribute_offset;
                 v[(offset)..(offset + attribute_size)].copy_from_slice(chunk);
             }
             attribute_offset += attribute_size;
         }
-        cx.create_buffer_with_data(wgpu::BufferUsages::VERTEX, &v)
+        v
     }
 
     pub fn create_index_buffer(&self, cx: &Context) -> Option<wgpu::Buffer> {
@@ -107,12 +219,15 @@ impl GeometryData {
                     offset,
                 };
                 offset += values.attribute_size();
-                return attribute;
+                attribute
             })
             .collect::<_>()
     }
 
-    pub fn create_geometry(&self, cx: &super::Context) -> super::Geometry {
-        super::Geometry::new(cx, &self)
+    pub fn create_geometry(
+        &self,
+        cx: &super::Context,
+    ) -> Result<super::Geometry, super::GeometryError> {
+        super::Geometry::new(cx, self)
     }
 }
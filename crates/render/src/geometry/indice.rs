@@ -1,10 +1,41 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum IndiceValues {
     U16(Vec<u16>),
     U32(Vec<u32>),
 }
 
 impl IndiceValues {
+    /// Pick the smallest index format able to address every value.
+    /// `u16::MAX` is kept free as it is the primitive restart value,
+    /// `u32::MAX` values are restarts and stay restarts in either format.
+    pub fn auto(values: Vec<u32>) -> Self {
+        let max = values
+            .iter()
+            .copied()
+            .filter(|value| *value != u32::MAX)
+            .max()
+            .unwrap_or(0);
+        if max < u16::MAX as u32 {
+            Self::U16(values.into_iter().map(|value| value as u16).collect())
+        } else {
+            Self::U32(values)
+        }
+    }
+
+    /// Values in `format`, or in `U32` when a value other than a restart does
+    /// not fit in `u16`
+    pub fn with_format(values: Vec<u32>, format: wgpu::IndexFormat) -> Self {
+        let fits_u16 = values
+            .iter()
+            .all(|value| *value < u16::MAX as u32 || *value == u32::MAX);
+        match format {
+            wgpu::IndexFormat::Uint16 if fits_u16 => {
+                Self::U16(values.into_iter().map(|value| value as u16).collect())
+            }
+            _ => Self::U32(values),
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             IndiceValues::U16(ref values) => values.len(),
@@ -12,6 +43,46 @@ impl IndiceValues {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        match self {
+            IndiceValues::U16(ref values) => values[index] as u32,
+            IndiceValues::U32(ref values) => values[index],
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            IndiceValues::U16(ref values) => Box::new(values.iter().map(|value| *value as u32)),
+            IndiceValues::U32(ref values) => Box::new(values.iter().copied()),
+        }
+    }
+
+    /// Like [`IndiceValues::iter`] with restart values widened to `u32::MAX`
+    pub fn iter_with_restart(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            IndiceValues::U16(ref values) => Box::new(values.iter().map(|value| {
+                if *value == u16::MAX {
+                    u32::MAX
+                } else {
+                    *value as u32
+                }
+            })),
+            IndiceValues::U32(ref values) => Box::new(values.iter().copied()),
+        }
+    }
+
+    /// Index value restarting strip primitives
+    pub fn restart_value(&self) -> u32 {
+        match self {
+            IndiceValues::U16(_) => u16::MAX as u32,
+            IndiceValues::U32(_) => u32::MAX,
+        }
+    }
+
     pub fn get_bytes(&self) -> &[u8] {
         match self {
             IndiceValues::U16(ref values) => bytemuck::cast_slice(values),
@@ -0,0 +1,131 @@
+use crate::context::Context;
+use crate::util::VertexAttribute;
+use bytemuck::Pod;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// Per-instance data bound as a vertex buffer stepped once per instance.
+/// The buffer grows to the next power of two when a write does not fit.
+pub struct InstanceBuffer<T: Pod + VertexAttribute> {
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    count: usize,
+    instance: PhantomData<T>,
+}
+
+impl<T: Pod + VertexAttribute> InstanceBuffer<T> {
+    pub fn new(context: &Context, instances: &[T]) -> Self {
+        let mut buffer = Self::with_capacity(context, instances.len());
+        buffer.write(context, instances);
+        buffer
+    }
+
+    pub fn with_capacity(context: &Context, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buffer: Self::create_buffer(context, capacity),
+            capacity,
+            count: 0,
+            instance: PhantomData,
+        }
+    }
+
+    fn create_buffer(context: &Context, capacity: usize) -> wgpu::Buffer {
+        context.create_buffer(
+            false,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            capacity as u64 * T::STRIDE,
+        )
+    }
+
+    /// Layout to pass next to the geometry layout when creating the pipeline.
+    /// Attributes start at [`INSTANCE_SHADER_LOCATION`](crate::util::INSTANCE_SHADER_LOCATION), see
+    /// [`InstanceBuffer::attributes_after`] for geometries with more attributes.
+    pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        T::buffer_layout()
+    }
+
+    /// Instance attributes moved to start at `first_location`
+    pub fn attributes_at(first_location: u32) -> Vec<wgpu::VertexAttribute> {
+        let first = T::ATTRIBUTES
+            .iter()
+            .map(|attribute| attribute.shader_location)
+            .min()
+            .unwrap_or(0);
+        T::ATTRIBUTES
+            .iter()
+            .map(|attribute| wgpu::VertexAttribute {
+                shader_location: attribute.shader_location - first + first_location,
+                ..*attribute
+            })
+            .collect()
+    }
+
+    /// Instance attributes right after the `vertex_attributes` of a geometry,
+    /// such as [`GeometryData::create_vertex_attributes`](super::GeometryData::create_vertex_attributes)
+    pub fn attributes_after(
+        vertex_attributes: &[wgpu::VertexAttribute],
+    ) -> Vec<wgpu::VertexAttribute> {
+        let first_location = vertex_attributes
+            .iter()
+            .map(|attribute| attribute.shader_location + 1)
+            .max()
+            .unwrap_or(0);
+        Self::attributes_at(first_location)
+    }
+
+    /// Layout of the instances with `attributes` from
+    /// [`InstanceBuffer::attributes_at`] or [`InstanceBuffer::attributes_after`]
+    pub fn buffer_layout_with_attributes(
+        attributes: &[wgpu::VertexAttribute],
+    ) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: T::STRIDE,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes,
+        }
+    }
+
+    /// Replace every instance
+    pub fn write(&mut self, context: &Context, instances: &[T]) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            self.buffer = Self::create_buffer(context, self.capacity);
+        }
+        if !instances.is_empty() {
+            context.enqueue_write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        }
+        self.count = instances.len();
+    }
+
+    /// Replace every instance from column-major model matrices
+    pub fn write_matrices<I>(&mut self, context: &Context, matrices: I)
+    where
+        I: IntoIterator<Item = [f32; 16]>,
+        T: From<[f32; 16]>,
+    {
+        let instances = matrices.into_iter().map(T::from).collect::<Vec<_>>();
+        self.write(context, &instances);
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Range of every instance written, to pass to [`super::Geometry::draw_instanced`]
+    pub fn range(&self) -> Range<u32> {
+        0..self.count as u32
+    }
+
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, slot: u32) {
+        pass.set_vertex_buffer(slot, self.buffer.slice(..));
+    }
+}
@@ -1,10 +1,27 @@
+mod dynamic;
 mod indice;
+mod instance;
 mod vertex;
 mod geometry_data;
+mod morph;
+mod optimize;
+mod pool;
+mod simplify;
+mod topology;
+mod validate;
+pub use dynamic::*;
 pub use indice::*;
+pub use instance::*;
 pub use vertex::*;
 pub use geometry_data::*;
+pub use morph::*;
+pub use optimize::*;
+pub use pool::*;
+pub use simplify::*;
+pub use topology::*;
+pub use validate::*;
 use crate::context::Context;
+use std::ops::Range;
 
 pub struct Geometry {
     pub vertex_buffer: wgpu::Buffer,
@@ -13,17 +30,39 @@ pub struct Geometry {
     pub index_buffer: Option<wgpu::Buffer>,
     pub index_count: Option<usize>,
     pub index_format: Option<wgpu::IndexFormat>,
+    pub topology: wgpu::PrimitiveTopology,
+    pub morph_targets: Option<MorphTargets>,
 }
 
 impl Geometry {
-    pub fn new(context: &Context, geometry: &GeometryData) -> Self {
+    /// Upload a geometry after checking it with [`GeometryData::validate`]
+    pub fn new(context: &Context, geometry: &GeometryData) -> Result<Self, GeometryError> {
+        geometry.validate()?;
+        Ok(Self::with_indices(
+            context,
+            geometry,
+            geometry.indices(),
+            geometry.topology(),
+        ))
+    }
+
+    /// Upload the vertices of a valid geometry, drawn with `indices` as `topology`
+    fn with_indices(
+        context: &Context,
+        geometry: &GeometryData,
+        indices: Option<&IndiceValues>,
+        topology: wgpu::PrimitiveTopology,
+    ) -> Self {
         let vertex_buffer = geometry.create_vertex_buffer(context);
         let vertex_count = geometry.compute_vertex_count();
         let vertex_size = geometry.vertex_size();
 
-        let index_buffer = geometry.create_index_buffer(context);
-        let index_count = geometry.indices().map(|i| i.len());
-        let index_format = geometry.indices().map(|i| i.index_format());
+        let index_buffer = indices.map(|indices| {
+            context.create_buffer_with_data(wgpu::BufferUsages::INDEX, indices.get_bytes())
+        });
+        let index_count = indices.map(|i| i.len());
+        let index_format = indices.map(|i| i.index_format());
+        let morph_targets = MorphTargets::new(context, geometry);
 
         Self {
             vertex_buffer,
@@ -32,6 +71,21 @@ impl Geometry {
             index_buffer,
             index_count,
             index_format,
+            topology,
+            morph_targets,
+        }
+    }
+
+    /// Primitive state to create a pipeline drawing this geometry
+    pub fn primitive_state(&self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: self.topology,
+            strip_index_format: if self.topology.is_strip() {
+                self.index_format
+            } else {
+                None
+            },
+            ..Default::default()
         }
     }
 
@@ -40,6 +94,12 @@ impl Geometry {
     }
 
     pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        self.draw_instanced(pass, 0..1);
+    }
+
+    /// Draw the geometry once per instance of the range. The instance buffer must be bound
+    /// with [`InstanceBuffer::bind`].
+    pub fn draw_instanced<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, instances: Range<u32>) {
         match (
             self.index_buffer.as_ref(),
             self.index_count.as_ref(),
@@ -47,10 +107,10 @@ impl Geometry {
         ) {
             (Some(index_buffer), Some(index_count), Some(index_format)) => {
                 pass.set_index_buffer(index_buffer.slice(..), *index_format);
-                pass.draw_indexed(0..(*index_count as u32), 0, 0..1);
+                pass.draw_indexed(0..(*index_count as u32), 0, instances);
             }
             _ => {
-                pass.draw(0..(self.vertex_count as u32), 0..1);
+                pass.draw(0..(self.vertex_count as u32), instances);
             }
         }
     }
@@ -0,0 +1,302 @@
+use super::{GeometryData, VertexValues};
+use crate::context::Context;
+
+/// Named set of per-vertex deltas added to the base geometry, scaled by a weight
+#[derive(Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub name: String,
+    pub positions: Option<Vec<[f32; 3]>>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub tangents: Option<Vec<[f32; 3]>>,
+}
+
+impl MorphTarget {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_positions(mut self, deltas: Vec<[f32; 3]>) -> Self {
+        self.positions = Some(deltas);
+        self
+    }
+
+    pub fn with_normals(mut self, deltas: Vec<[f32; 3]>) -> Self {
+        self.normals = Some(deltas);
+        self
+    }
+
+    pub fn with_tangents(mut self, deltas: Vec<[f32; 3]>) -> Self {
+        self.tangents = Some(deltas);
+        self
+    }
+
+    /// Every delta list with its attribute name
+    pub fn deltas(&self) -> impl Iterator<Item = (&'static str, &[[f32; 3]])> {
+        [
+            (GeometryData::ATTRIBUTE_POSITION, self.positions.as_deref()),
+            (GeometryData::ATTRIBUTE_NORMAL, self.normals.as_deref()),
+            (GeometryData::ATTRIBUTE_TANGENT, self.tangents.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, deltas)| deltas.map(|deltas| (name, deltas)))
+    }
+
+    /// Same as [`VertexValues::remap`] for every delta list
+    pub fn remap(&mut self, remap: &[u32], len: usize) {
+        for deltas in [&mut self.positions, &mut self.normals, &mut self.tangents] {
+            if let Some(values) = deltas.as_mut() {
+                let mut result = vec![[0.0; 3]; len];
+                for (value, target) in values.iter().zip(remap) {
+                    if *target != u32::MAX {
+                        result[*target as usize] = *value;
+                    }
+                }
+                *values = result;
+            }
+        }
+    }
+}
+
+impl GeometryData {
+    pub fn add_morph_target(&mut self, target: MorphTarget) {
+        self.morph_targets.push(target);
+    }
+
+    pub fn morph_targets(&self) -> &[MorphTarget] {
+        &self.morph_targets
+    }
+
+    pub fn morph_target(&self, name: &str) -> Option<&MorphTarget> {
+        self.morph_targets.iter().find(|target| target.name == name)
+    }
+
+    /// Copy of the geometry with the targets blended in on the CPU and removed
+    pub fn morphed(&self, weights: &[f32]) -> GeometryData {
+        let mut geometry = self.clone();
+        geometry.morph_targets.clear();
+
+        for (target, weight) in self.morph_targets.iter().zip(weights) {
+            if *weight == 0.0 {
+                continue;
+            }
+            for (name, deltas) in target.deltas() {
+                let mut values = match geometry.attribute(name) {
+                    Some(values) => values.clone(),
+                    None => continue,
+                };
+                let components = values.components().min(3);
+                match &mut values {
+                    VertexValues::Float32(v) => blend(v, 1, components, deltas, *weight),
+                    VertexValues::Float32x2(v) => blend(v, 2, components, deltas, *weight),
+                    VertexValues::Float32x3(v) => blend(v, 3, components, deltas, *weight),
+                    VertexValues::Float32x4(v) => blend(v, 4, components, deltas, *weight),
+                }
+                geometry.set_attribute(name, values);
+            }
+        }
+
+        geometry
+    }
+}
+
+fn blend<T: bytemuck::Pod>(
+    values: &mut [T],
+    stride: usize,
+    components: usize,
+    deltas: &[[f32; 3]],
+    weight: f32,
+) {
+    let floats: &mut [f32] = bytemuck::cast_slice_mut(values);
+    for (vertex, delta) in floats.chunks_exact_mut(stride).zip(deltas) {
+        for component in 0..components {
+            vertex[component] += delta[component] * weight;
+        }
+    }
+}
+
+/// Morph targets of a [`super::Geometry`] uploaded as storage buffers, blended in the
+/// vertex shader with the functions of [`MorphTargets::wgsl`].
+///
+/// Deltas are stored as `array<vec4<f32>>` ordered by target, vertex then attribute
+/// (position, normal, tangent). Missing deltas are zero.
+pub struct MorphTargets {
+    names: Vec<String>,
+    vertex_count: usize,
+    weights: Vec<f32>,
+    deltas_buffer: wgpu::Buffer,
+    weights_buffer: wgpu::Buffer,
+    dirty: bool,
+}
+
+impl MorphTargets {
+    /// Upload the targets of a geometry, `None` when it has none
+    pub fn new(context: &Context, geometry: &GeometryData) -> Option<Self> {
+        let targets = geometry.morph_targets();
+        if targets.is_empty() {
+            return None;
+        }
+
+        let vertex_count = geometry.compute_vertex_count();
+        let mut deltas = vec![[0.0f32; 4]; targets.len() * vertex_count * 3];
+        for (target_index, target) in targets.iter().enumerate() {
+            let attributes = [&target.positions, &target.normals, &target.tangents];
+            for (attribute, values) in attributes.iter().enumerate() {
+                if let Some(values) = values {
+                    for (vertex, delta) in values.iter().take(vertex_count).enumerate() {
+                        let index = (target_index * vertex_count + vertex) * 3 + attribute;
+                        deltas[index] = [delta[0], delta[1], delta[2], 0.0];
+                    }
+                }
+            }
+        }
+
+        let weights = vec![0.0; targets.len()];
+        Some(Self {
+            names: targets.iter().map(|target| target.name.clone()).collect(),
+            vertex_count,
+            deltas_buffer: context.create_buffer_with_data(
+                wgpu::BufferUsages::STORAGE,
+                bytemuck::cast_slice(&deltas),
+            ),
+            weights_buffer: context.create_buffer_with_data(
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                &Self::weights_bytes(vertex_count, &weights),
+            ),
+            weights,
+            dirty: false,
+        })
+    }
+
+    fn weights_bytes(vertex_count: usize, weights: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + weights.len() * 4);
+        bytes.extend_from_slice(&(vertex_count as u32).to_ne_bytes());
+        bytes.extend_from_slice(&(weights.len() as u32).to_ne_bytes());
+        bytes.extend_from_slice(bytemuck::cast_slice(weights));
+        bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn set_weight(&mut self, target: usize, weight: f32) {
+        self.weights[target] = weight;
+        self.dirty = true;
+    }
+
+    /// Returns `false` when no target has this name
+    pub fn set_weight_by_name(&mut self, name: &str, weight: f32) -> bool {
+        match self.names.iter().position(|n| n == name) {
+            Some(target) => {
+                self.set_weight(target, weight);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the first weights, as many as given
+    pub fn set_weights(&mut self, weights: &[f32]) {
+        for (target, weight) in self.weights.iter_mut().zip(weights) {
+            *target = *weight;
+        }
+        self.dirty = true;
+    }
+
+    /// Upload weights changed since the last update. Call before drawing.
+    pub fn update(&mut self, context: &Context) {
+        if self.dirty {
+            let bytes = Self::weights_bytes(self.vertex_count, &self.weights);
+            context.enqueue_write_buffer(&self.weights_buffer, 0, &bytes);
+            self.dirty = false;
+        }
+    }
+
+    /// Layout entries of the deltas at `binding` and the weights at `binding + 1`
+    pub fn bind_group_layout_entries(binding: u32) -> [wgpu::BindGroupLayoutEntry; 2] {
+        let entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        [entry(binding), entry(binding + 1)]
+    }
+
+    pub fn bind_group_entries(&self, binding: u32) -> [wgpu::BindGroupEntry<'_>; 2] {
+        [
+            wgpu::BindGroupEntry {
+                binding,
+                resource: self.deltas_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: self.weights_buffer.as_entire_binding(),
+            },
+        ]
+    }
+
+    /// WGSL declarations of the buffers and `morph_position`, `morph_normal` and
+    /// `morph_tangent` functions taking the vertex index and the base value
+    pub fn wgsl(group: u32, binding: u32) -> String {
+        format!(
+            r#"
+struct MorphWeights {{
+    vertex_count: u32,
+    target_count: u32,
+    weights: array<f32>,
+}};
+
+@group({group}) @binding({deltas})
+var<storage, read> morph_deltas: array<vec4<f32>>;
+@group({group}) @binding({weights})
+var<storage, read> morph_weights: MorphWeights;
+
+fn morph_delta(vertex: u32, kind: u32) -> vec3<f32> {{
+    var delta = vec3<f32>(0.0, 0.0, 0.0);
+    for (var t = 0u; t < morph_weights.target_count; t = t + 1u) {{
+        let weight = morph_weights.weights[t];
+        if (weight != 0.0) {{
+            let index = (t * morph_weights.vertex_count + vertex) * 3u + kind;
+            delta = delta + morph_deltas[index].xyz * weight;
+        }}
+    }}
+    return delta;
+}}
+
+fn morph_position(vertex: u32, position: vec3<f32>) -> vec3<f32> {{
+    return position + morph_delta(vertex, 0u);
+}}
+
+fn morph_normal(vertex: u32, normal: vec3<f32>) -> vec3<f32> {{
+    return normalize(normal + morph_delta(vertex, 1u));
+}}
+
+fn morph_tangent(vertex: u32, tangent: vec3<f32>) -> vec3<f32> {{
+    return normalize(tangent + morph_delta(vertex, 2u));
+}}
+"#,
+            deltas = binding,
+            weights = binding + 1,
+        )
+    }
+}
@@ -0,0 +1,591 @@
+use super::{GeometryData, IndiceValues};
+use std::collections::HashMap;
+
+const FORSYTH_CACHE_SIZE: usize = 32;
+const FORSYTH_CACHE_DECAY_POWER: f32 = 1.5;
+const FORSYTH_LAST_TRIANGLE_SCORE: f32 = 0.75;
+const FORSYTH_VALENCE_BOOST_SCALE: f32 = 2.0;
+const FORSYTH_VALENCE_BOOST_POWER: f32 = 0.5;
+
+const OVERDRAW_CACHE_SIZE: usize = 16;
+
+const FETCH_CACHE_LINE_SIZE: usize = 64;
+const FETCH_CACHE_LINE_COUNT: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VertexCacheStatistics {
+    /// Number of vertex shader invocations
+    pub vertices_transformed: usize,
+    /// Average cache miss ratio, transformed vertices per triangle
+    pub acmr: f32,
+    /// Average transformed vertex ratio, transformed vertices per vertex
+    pub atvr: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VertexFetchStatistics {
+    /// Bytes read from the vertex buffer
+    pub bytes_fetched: usize,
+    /// Bytes read divided by the vertex buffer size
+    pub overfetch: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptimizationStatistics {
+    pub vertex_count: usize,
+    pub index_count: usize,
+    pub index_format: Option<wgpu::IndexFormat>,
+    pub vertex_cache: VertexCacheStatistics,
+    pub vertex_fetch: VertexFetchStatistics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptimizationReport {
+    pub before: OptimizationStatistics,
+    pub after: OptimizationStatistics,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizeOptions {
+    /// Merge vertices whose attributes all differ by less than the tolerance
+    pub weld_tolerance: Option<f32>,
+    /// Reorder triangles for the post-transform vertex cache
+    pub vertex_cache: bool,
+    /// Reorder triangle clusters to reduce overdraw, allowing the ACMR to degrade by this factor
+    pub overdraw_threshold: Option<f32>,
+    /// Reorder vertices in the order they are first referenced
+    pub vertex_fetch: bool,
+    /// Store indices as `U16` when possible
+    pub compact_indices: bool,
+    /// Cache size used to compute the statistics
+    pub cache_size: usize,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            weld_tolerance: Some(0.0),
+            vertex_cache: true,
+            overdraw_threshold: Some(1.05),
+            vertex_fetch: true,
+            compact_indices: true,
+            cache_size: 16,
+        }
+    }
+}
+
+impl GeometryData {
+    /// Run every pass enabled in `options` and report statistics before and after.
+    pub fn optimize(&mut self, options: &OptimizeOptions) -> OptimizationReport {
+        let before = self.statistics(options.cache_size);
+
+        if let Some(tolerance) = options.weld_tolerance {
+            self.weld_vertices(tolerance);
+        }
+        if options.vertex_cache {
+            self.optimize_vertex_cache();
+        }
+        if let Some(threshold) = options.overdraw_threshold {
+            self.optimize_overdraw(threshold);
+        }
+        if options.vertex_fetch {
+            self.optimize_vertex_fetch();
+        }
+        if options.compact_indices {
+            self.compact_indices();
+        }
+
+        let after = self.statistics(options.cache_size);
+        OptimizationReport { before, after }
+    }
+
+    /// Merge duplicated vertices and return the number of vertices removed.
+    /// Non-indexed geometry becomes indexed.
+    pub fn weld_vertices(&mut self, tolerance: f32) -> usize {
+        let vertex_count = self.compute_vertex_count();
+        if self.indices().is_none() {
+            self.set_indices(Some(IndiceValues::auto((0..vertex_count as u32).collect())));
+        }
+
+        let (remap, unique_count) = generate_weld_remap(self, tolerance);
+        self.remap_vertices(&remap, unique_count);
+        vertex_count - unique_count
+    }
+
+    /// Reorder triangles to improve post-transform vertex cache hits.
+    /// Only triangle lists are reordered.
+    pub fn optimize_vertex_cache(&mut self) {
+        if self.topology() != wgpu::PrimitiveTopology::TriangleList {
+            return;
+        }
+        let vertex_count = self.compute_vertex_count();
+        if let Some(indices) = self.indices() {
+            let values = indices.iter().collect::<Vec<_>>();
+            let values = optimize_vertex_cache(&values, vertex_count);
+            self.set_indices(Some(IndiceValues::with_format(
+                values,
+                indices.index_format(),
+            )));
+        }
+    }
+
+    /// Reorder triangle clusters front-to-back from the mesh centroid. Should run after
+    /// [`GeometryData::optimize_vertex_cache`]. Only triangle lists are reordered.
+    pub fn optimize_overdraw(&mut self, threshold: f32) {
+        if self.topology() != wgpu::PrimitiveTopology::TriangleList {
+            return;
+        }
+        if let (Some(indices), Some(positions)) = (self.indices(), self.positions()) {
+            let values = indices.iter().collect::<Vec<_>>();
+            let values = optimize_overdraw(&values, positions, threshold);
+            self.set_indices(Some(IndiceValues::with_format(
+                values,
+                indices.index_format(),
+            )));
+        }
+    }
+
+    /// Reorder vertices in the order they are referenced and drop unused vertices.
+    pub fn optimize_vertex_fetch(&mut self) {
+        let vertex_count = self.compute_vertex_count();
+        if self.indices().is_some() {
+            let values = self.referenced_indices();
+            let (remap, unique_count) = generate_vertex_fetch_remap(&values, vertex_count);
+            self.remap_vertices(&remap, unique_count);
+        }
+    }
+
+    /// Switch indices to `U16` when every index fits, `U32` otherwise.
+    pub fn compact_indices(&mut self) {
+        if self.indices().is_some() {
+            let values = IndiceValues::auto(self.vertex_indices());
+            self.set_indices(Some(values));
+        }
+    }
+
+    pub fn statistics(&self, cache_size: usize) -> OptimizationStatistics {
+        let vertex_count = self.compute_vertex_count();
+        let indices = self.referenced_indices();
+
+        OptimizationStatistics {
+            vertex_count,
+            index_count: indices.len(),
+            index_format: self.indices().map(|indices| indices.index_format()),
+            vertex_cache: analyze_vertex_cache(&indices, vertex_count, cache_size),
+            vertex_fetch: analyze_vertex_fetch(&indices, vertex_count, self.vertex_size() as usize),
+        }
+    }
+}
+
+/// Simulate a FIFO post-transform cache of `cache_size` entries.
+pub fn analyze_vertex_cache(
+    indices: &[u32],
+    vertex_count: usize,
+    cache_size: usize,
+) -> VertexCacheStatistics {
+    let mut timestamps = vec![0usize; vertex_count];
+    let mut time = cache_size + 1;
+    let mut misses = 0;
+
+    for index in indices {
+        let index = *index as usize;
+        if time - timestamps[index] > cache_size {
+            timestamps[index] = time;
+            time += 1;
+            misses += 1;
+        }
+    }
+
+    let triangle_count = indices.len() / 3;
+    VertexCacheStatistics {
+        vertices_transformed: misses,
+        acmr: if triangle_count == 0 {
+            0.0
+        } else {
+            misses as f32 / triangle_count as f32
+        },
+        atvr: if vertex_count == 0 {
+            0.0
+        } else {
+            misses as f32 / vertex_count as f32
+        },
+    }
+}
+
+/// Simulate vertex fetches through a direct-mapped memory cache. Only vertices missing the
+/// post-transform cache are fetched.
+pub fn analyze_vertex_fetch(
+    indices: &[u32],
+    vertex_count: usize,
+    vertex_size: usize,
+) -> VertexFetchStatistics {
+    let mut timestamps = vec![0usize; vertex_count];
+    let mut time = OVERDRAW_CACHE_SIZE + 1;
+    let mut lines = [usize::MAX; FETCH_CACHE_LINE_COUNT];
+    let mut bytes_fetched = 0;
+
+    for index in indices {
+        let index = *index as usize;
+        if time - timestamps[index] <= OVERDRAW_CACHE_SIZE {
+            continue;
+        }
+        timestamps[index] = time;
+        time += 1;
+
+        let start = index * vertex_size;
+        let end = start + vertex_size;
+        for line in (start / FETCH_CACHE_LINE_SIZE)..=((end.max(1) - 1) / FETCH_CACHE_LINE_SIZE) {
+            let slot = &mut lines[line % FETCH_CACHE_LINE_COUNT];
+            if *slot != line {
+                *slot = line;
+                bytes_fetched += FETCH_CACHE_LINE_SIZE;
+            }
+        }
+    }
+
+    let buffer_size = vertex_count * vertex_size;
+    VertexFetchStatistics {
+        bytes_fetched,
+        overfetch: if buffer_size == 0 {
+            0.0
+        } else {
+            bytes_fetched as f32 / buffer_size as f32
+        },
+    }
+}
+
+/// Build a remap table merging vertices whose attributes all differ by at most `tolerance`.
+/// Returns the table and the number of unique vertices.
+pub fn generate_weld_remap(geometry: &GeometryData, tolerance: f32) -> (Vec<u32>, usize) {
+    let vertex_count = geometry.compute_vertex_count();
+    let attributes = geometry.values();
+    let equals = |a: usize, b: usize| {
+        attributes.iter().all(|values| {
+            values
+                .get(a)
+                .iter()
+                .zip(values.get(b))
+                .all(|(x, y)| (x - y).abs() <= tolerance)
+        })
+    };
+
+    let mut remap = vec![0u32; vertex_count];
+    let mut representatives: Vec<usize> = Vec::new();
+
+    match geometry.positions() {
+        Some(positions) if tolerance > 0.0 => {
+            // Vertices within tolerance are at most one grid cell away from each other
+            let cell = |position: &[f32; 3]| {
+                [
+                    (position[0] / tolerance).floor() as i64,
+                    (position[1] / tolerance).floor() as i64,
+                    (position[2] / tolerance).floor() as i64,
+                ]
+            };
+            let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+
+            for (vertex, position) in positions.iter().enumerate() {
+                let key = cell(position);
+                let mut found = None;
+                'search: for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        for dz in -1..=1 {
+                            let neighbour = [key[0] + dx, key[1] + dy, key[2] + dz];
+                            let candidates = grid.get(&neighbour).into_iter().flatten();
+                            for unique in candidates {
+                                if equals(representatives[*unique as usize], vertex) {
+                                    found = Some(*unique);
+                                    break 'search;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                remap[vertex] = found.unwrap_or_else(|| {
+                    let unique = representatives.len() as u32;
+                    representatives.push(vertex);
+                    grid.entry(key).or_default().push(unique);
+                    unique
+                });
+            }
+        }
+        _ => {
+            let mut table: HashMap<Vec<u32>, u32> = HashMap::new();
+            for (vertex, target) in remap.iter_mut().enumerate() {
+                let key = attributes
+                    .iter()
+                    .flat_map(|values| values.get(vertex).iter().map(|value| value.to_bits()))
+                    .collect::<Vec<_>>();
+                *target = *table.entry(key).or_insert_with(|| {
+                    representatives.push(vertex);
+                    representatives.len() as u32 - 1
+                });
+            }
+        }
+    }
+
+    (remap, representatives.len())
+}
+
+/// Build a remap table ordering vertices by first reference. Unreferenced vertices are
+/// mapped to `u32::MAX`. Returns the table and the number of referenced vertices.
+pub fn generate_vertex_fetch_remap(indices: &[u32], vertex_count: usize) -> (Vec<u32>, usize) {
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut next = 0;
+    for index in indices {
+        let target = &mut remap[*index as usize];
+        if *target == u32::MAX {
+            *target = next;
+            next += 1;
+        }
+    }
+    (remap, next as usize)
+}
+
+fn forsyth_score(cache_position: Option<usize>, remaining: u32) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        Some(position) if position < 3 => FORSYTH_LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scaler = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scaler).powf(FORSYTH_CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+
+    cache_score
+        + FORSYTH_VALENCE_BOOST_SCALE * (remaining as f32).powf(-FORSYTH_VALENCE_BOOST_POWER)
+}
+
+/// Reorder a triangle list for the post-transform vertex cache.
+/// Tom Forsyth's "Linear-Speed Vertex Cache Optimisation".
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    let indices = &indices[..triangle_count * 3];
+
+    // Triangles adjacent to each vertex, live ones first
+    let mut remaining = vec![0u32; vertex_count];
+    for index in indices {
+        remaining[*index as usize] += 1;
+    }
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for vertex in 0..vertex_count {
+        offsets[vertex + 1] = offsets[vertex] + remaining[vertex] as usize;
+    }
+    let mut adjacency = vec![0u32; indices.len()];
+    let mut cursors = offsets.clone();
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        for vertex in corners {
+            adjacency[cursors[*vertex as usize]] = triangle as u32;
+            cursors[*vertex as usize] += 1;
+        }
+    }
+
+    let mut cache_positions: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores = remaining
+        .iter()
+        .map(|count| forsyth_score(None, *count))
+        .collect::<Vec<_>>();
+    let mut triangle_scores = indices
+        .chunks_exact(3)
+        .map(|corners| corners.iter().map(|v| vertex_scores[*v as usize]).sum())
+        .collect::<Vec<f32>>();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut result = Vec::with_capacity(indices.len());
+    let mut best =
+        (0..triangle_count).max_by(|a, b| triangle_scores[*a].total_cmp(&triangle_scores[*b]));
+    let mut input_cursor = 0;
+
+    while result.len() < indices.len() {
+        let triangle = match best {
+            Some(triangle) => triangle,
+            None => {
+                while emitted[input_cursor] {
+                    input_cursor += 1;
+                }
+                input_cursor
+            }
+        };
+
+        emitted[triangle] = true;
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        result.extend_from_slice(corners);
+
+        for vertex in corners {
+            let vertex = *vertex as usize;
+            let live =
+                &mut adjacency[offsets[vertex]..offsets[vertex] + remaining[vertex] as usize];
+            if let Some(position) = live.iter().position(|t| *t as usize == triangle) {
+                let last = live.len() - 1;
+                live.swap(position, last);
+                remaining[vertex] -= 1;
+            }
+        }
+
+        let mut next_cache = corners.to_vec();
+        next_cache.extend(cache.iter().filter(|v| !corners.contains(v)));
+        for (position, vertex) in next_cache.iter().enumerate() {
+            cache_positions[*vertex as usize] = if position < FORSYTH_CACHE_SIZE {
+                Some(position)
+            } else {
+                None
+            };
+        }
+
+        // Propagate score changes to the live triangles of every touched vertex
+        for vertex in next_cache.iter() {
+            let vertex = *vertex as usize;
+            let score = forsyth_score(cache_positions[vertex], remaining[vertex]);
+            let delta = score - vertex_scores[vertex];
+            vertex_scores[vertex] = score;
+            for t in &adjacency[offsets[vertex]..offsets[vertex] + remaining[vertex] as usize] {
+                triangle_scores[*t as usize] += delta;
+            }
+        }
+
+        next_cache.truncate(FORSYTH_CACHE_SIZE);
+        cache = next_cache;
+
+        best = None;
+        let mut best_score = f32::MIN;
+        for vertex in cache.iter() {
+            let vertex = *vertex as usize;
+            for t in &adjacency[offsets[vertex]..offsets[vertex] + remaining[vertex] as usize] {
+                let score = triangle_scores[*t as usize];
+                if score > best_score {
+                    best_score = score;
+                    best = Some(*t as usize);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Reorder clusters of triangles so outward-facing clusters are drawn first.
+/// Sander et al. "Fast Triangle Reordering for Vertex Locality and Reduced Overdraw".
+pub fn optimize_overdraw(indices: &[u32], positions: &[[f32; 3]], threshold: f32) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return indices.to_vec();
+    }
+
+    let mut timestamps = vec![0usize; positions.len()];
+    let mut time = OVERDRAW_CACHE_SIZE + 1;
+    let mut update_cache = |corners: &[u32], time: &mut usize| {
+        let mut misses = 0;
+        for vertex in corners {
+            let vertex = *vertex as usize;
+            if *time - timestamps[vertex] > OVERDRAW_CACHE_SIZE {
+                timestamps[vertex] = *time;
+                *time += 1;
+                misses += 1;
+            }
+        }
+        misses
+    };
+
+    // Hard boundaries: a triangle missing all its vertices starts a disjoint patch
+    let mut hard = Vec::new();
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        if update_cache(corners, &mut time) == 3 || triangle == 0 {
+            hard.push(triangle);
+        }
+    }
+    hard.push(triangle_count);
+
+    // Soft boundaries: split patches while the local ACMR stays within the threshold
+    let mut clusters = Vec::new();
+    for range in hard.windows(2) {
+        let (start, end) = (range[0], range[1]);
+
+        time += OVERDRAW_CACHE_SIZE + 1;
+        let cluster_misses: usize = (start..end)
+            .map(|t| update_cache(&indices[t * 3..t * 3 + 3], &mut time))
+            .sum();
+        let target = cluster_misses as f32 / (end - start) as f32 * threshold;
+
+        time += OVERDRAW_CACHE_SIZE + 1;
+        clusters.push(start);
+        let (mut misses, mut faces) = (0, 0);
+        for t in start..end {
+            misses += update_cache(&indices[t * 3..t * 3 + 3], &mut time);
+            faces += 1;
+            if t + 1 < end && misses as f32 / faces as f32 <= target {
+                clusters.push(t + 1);
+                time += OVERDRAW_CACHE_SIZE + 1;
+                misses = 0;
+                faces = 0;
+            }
+        }
+    }
+    clusters.push(triangle_count);
+
+    let mut mesh_centroid = [0.0f32; 3];
+    for index in indices {
+        let position = positions[*index as usize];
+        for axis in 0..3 {
+            mesh_centroid[axis] += position[axis];
+        }
+    }
+    for value in mesh_centroid.iter_mut() {
+        *value /= indices.len() as f32;
+    }
+
+    let mut sorted = clusters
+        .windows(2)
+        .map(|range| {
+            let (start, end) = (range[0], range[1]);
+            let mut area = 0.0;
+            let mut centroid = [0.0f32; 3];
+            let mut normal = [0.0f32; 3];
+
+            for corners in indices[start * 3..end * 3].chunks_exact(3) {
+                let a = positions[corners[0] as usize];
+                let b = positions[corners[1] as usize];
+                let c = positions[corners[2] as usize];
+                let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+                let n = [
+                    ab[1] * ac[2] - ab[2] * ac[1],
+                    ab[2] * ac[0] - ab[0] * ac[2],
+                    ab[0] * ac[1] - ab[1] * ac[0],
+                ];
+                let triangle_area = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                for axis in 0..3 {
+                    centroid[axis] += (a[axis] + b[axis] + c[axis]) / 3.0 * triangle_area;
+                    normal[axis] += n[axis];
+                }
+                area += triangle_area;
+            }
+
+            let inverse_area = if area == 0.0 { 0.0 } else { 1.0 / area };
+            let length =
+                (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            let inverse_length = if length == 0.0 { 0.0 } else { 1.0 / length };
+            let key = (0..3)
+                .map(|axis| {
+                    (centroid[axis] * inverse_area - mesh_centroid[axis])
+                        * normal[axis]
+                        * inverse_length
+                })
+                .sum::<f32>();
+
+            (key, start, end)
+        })
+        .collect::<Vec<_>>();
+
+    sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    sorted
+        .into_iter()
+        .flat_map(|(_, start, end)| indices[start * 3..end * 3].iter().copied())
+        .collect()
+}
@@ -0,0 +1,306 @@
+use super::{GeometryData, GeometryError};
+use crate::context::Context;
+use std::ops::Range;
+
+/// First-fit allocator of `u32` ranges. Freed ranges are merged with their neighbours.
+#[derive(Debug, Clone)]
+pub struct RangeAllocator {
+    size: u32,
+    free: Vec<Range<u32>>,
+}
+
+impl RangeAllocator {
+    pub fn new(size: u32) -> Self {
+        let mut free = Vec::new();
+        if size > 0 {
+            free.push(0..size);
+        }
+        Self { size, free }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn free_space(&self) -> u32 {
+        self.free.iter().map(|range| range.end - range.start).sum()
+    }
+
+    pub fn largest_free_range(&self) -> u32 {
+        self.free
+            .iter()
+            .map(|range| range.end - range.start)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn allocate(&mut self, len: u32) -> Option<Range<u32>> {
+        if len == 0 {
+            return Some(0..0);
+        }
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.end - range.start >= len)?;
+        let range = &mut self.free[index];
+        let allocation = range.start..range.start + len;
+        range.start += len;
+        if range.start == range.end {
+            self.free.remove(index);
+        }
+        Some(allocation)
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.start == range.end {
+            return;
+        }
+
+        // Free list stays sorted so neighbours can be merged
+        let index = self
+            .free
+            .iter()
+            .position(|free| free.start > range.start)
+            .unwrap_or(self.free.len());
+        self.free.insert(index, range);
+
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free[index + 1].end;
+            self.free.remove(index + 1);
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free[index].end;
+            self.free.remove(index);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeometryPoolOptions {
+    /// Vertex capacity of each buffer page
+    pub vertices_per_page: u32,
+    /// Index capacity of each buffer page
+    pub indices_per_page: u32,
+}
+
+impl Default for GeometryPoolOptions {
+    fn default() -> Self {
+        Self {
+            vertices_per_page: 1 << 16,
+            indices_per_page: 1 << 18,
+        }
+    }
+}
+
+/// Location of a geometry inside a [`GeometryPool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GeometryHandle {
+    page: usize,
+    vertex_count: u32,
+    pub base_vertex: i32,
+    pub first_index: u32,
+    pub count: u32,
+}
+
+impl GeometryHandle {
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    pub fn index_range(&self) -> Range<u32> {
+        self.first_index..self.first_index + self.count
+    }
+
+    fn vertex_range(&self) -> Range<u32> {
+        self.base_vertex as u32..self.base_vertex as u32 + self.vertex_count
+    }
+}
+
+struct GeometryPoolPage {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    vertices: RangeAllocator,
+    indices: RangeAllocator,
+}
+
+/// Shared vertex and index buffers holding many geometries with the same vertex layout.
+/// Every geometry is drawn indexed with `Uint32` indices.
+pub struct GeometryPool {
+    vertex_size: u64,
+    vertex_attributes: Vec<wgpu::VertexAttribute>,
+    options: GeometryPoolOptions,
+    pages: Vec<GeometryPoolPage>,
+}
+
+impl GeometryPool {
+    pub fn new(
+        vertex_size: u64,
+        vertex_attributes: Vec<wgpu::VertexAttribute>,
+        options: GeometryPoolOptions,
+    ) -> Self {
+        Self {
+            vertex_size,
+            vertex_attributes,
+            options,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Create a pool accepting geometries with the same layout as `geometry`
+    pub fn for_geometry(geometry: &GeometryData, options: GeometryPoolOptions) -> Self {
+        Self::new(
+            geometry.vertex_size(),
+            geometry.create_vertex_attributes(),
+            options,
+        )
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn vertex_buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.vertex_size,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.vertex_attributes,
+        }
+    }
+
+    /// Upload a geometry in the first page with enough room, creating a page if needed.
+    /// The geometry is checked with [`GeometryData::validate`] first.
+    pub fn insert(
+        &mut self,
+        context: &Context,
+        geometry: &GeometryData,
+    ) -> Result<GeometryHandle, GeometryError> {
+        geometry.validate()?;
+        assert!(
+            geometry
+                .create_vertex_attributes()
+                .iter()
+                .map(|attribute| attribute.format)
+                .eq(self
+                    .vertex_attributes
+                    .iter()
+                    .map(|attribute| attribute.format)),
+            "[fine::graphic::GeometryPool] Geometry does not match the pool vertex layout."
+        );
+
+        let vertex_count = geometry.compute_vertex_count() as u32;
+        let indices = geometry.vertex_indices();
+        let index_count = indices.len() as u32;
+
+        let mut allocation = None;
+        for (page_index, page) in self.pages.iter_mut().enumerate() {
+            if let Some(vertices) = page.vertices.allocate(vertex_count) {
+                match page.indices.allocate(index_count) {
+                    Some(indices) => {
+                        allocation = Some((page_index, vertices, indices));
+                        break;
+                    }
+                    None => page.vertices.free(vertices),
+                }
+            }
+        }
+
+        let (page_index, vertices, index_range) = match allocation {
+            Some(allocation) => allocation,
+            None => {
+                let mut page = self.create_page(context, vertex_count, index_count);
+                let vertices = page.vertices.allocate(vertex_count).unwrap();
+                let indices = page.indices.allocate(index_count).unwrap();
+                self.pages.push(page);
+                (self.pages.len() - 1, vertices, indices)
+            }
+        };
+
+        let page = &self.pages[page_index];
+        if vertex_count > 0 {
+            context.enqueue_write_buffer(
+                &page.vertex_buffer,
+                vertices.start as u64 * self.vertex_size,
+                &geometry.interleave(),
+            );
+        }
+        if index_count > 0 {
+            context.enqueue_write_buffer(
+                &page.index_buffer,
+                index_range.start as u64 * 4,
+                bytemuck::cast_slice(&indices),
+            );
+        }
+
+        Ok(GeometryHandle {
+            page: page_index,
+            vertex_count,
+            base_vertex: vertices.start as i32,
+            first_index: index_range.start,
+            count: index_count,
+        })
+    }
+
+    /// Release the ranges of a geometry so they can be reused
+    pub fn remove(&mut self, handle: GeometryHandle) {
+        let page = &mut self.pages[handle.page];
+        page.vertices.free(handle.vertex_range());
+        page.indices.free(handle.index_range());
+    }
+
+    pub fn bind_page<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, page: usize, slot: u32) {
+        let page = &self.pages[page];
+        pass.set_vertex_buffer(slot, page.vertex_buffer.slice(..));
+        pass.set_index_buffer(page.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    }
+
+    /// Draw a geometry. Its page must be bound with [`GeometryPool::bind_page`].
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, handle: &GeometryHandle) {
+        pass.draw_indexed(handle.index_range(), handle.base_vertex, 0..1);
+    }
+
+    /// Draw many geometries, binding each page once
+    pub fn draw_all<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        handles: &[GeometryHandle],
+        slot: u32,
+    ) {
+        let mut handles = handles.to_vec();
+        handles.sort_by_key(|handle| handle.page);
+
+        let mut bound = None;
+        for handle in handles.iter() {
+            if bound != Some(handle.page) {
+                self.bind_page(pass, handle.page, slot);
+                bound = Some(handle.page);
+            }
+            self.draw(pass, handle);
+        }
+    }
+
+    fn create_page(
+        &self,
+        context: &Context,
+        vertex_count: u32,
+        index_count: u32,
+    ) -> GeometryPoolPage {
+        // Oversized geometries get a page of their own
+        let vertex_capacity = self.options.vertices_per_page.max(vertex_count);
+        let index_capacity = self.options.indices_per_page.max(index_count);
+
+        GeometryPoolPage {
+            vertex_buffer: context.create_buffer(
+                false,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                vertex_capacity as u64 * self.vertex_size,
+            ),
+            index_buffer: context.create_buffer(
+                false,
+                wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                index_capacity as u64 * 4,
+            ),
+            vertices: RangeAllocator::new(vertex_capacity),
+            indices: RangeAllocator::new(index_capacity),
+        }
+    }
+}
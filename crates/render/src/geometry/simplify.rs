@@ -0,0 +1,636 @@
+use super::{Geometry, GeometryData, GeometryError, IndiceValues};
+use crate::context::Context;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+const BORDER_EDGE_WEIGHT: f32 = 10.0;
+
+#[derive(Debug, Clone)]
+pub struct SimplifyOptions {
+    /// Stop once the index count is at or below this value
+    pub target_index_count: usize,
+    /// Stop before the error, relative to the mesh extents, goes above this value
+    pub target_error: f32,
+    /// Forbid collapses on open borders
+    pub lock_border: bool,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        Self {
+            target_index_count: 0,
+            target_error: 0.01,
+            lock_border: false,
+        }
+    }
+}
+
+impl SimplifyOptions {
+    pub fn with_target_triangle_count(triangle_count: usize) -> Self {
+        Self {
+            target_index_count: triangle_count * 3,
+            target_error: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_target_error(target_error: f32) -> Self {
+        Self {
+            target_error,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LodChainOptions {
+    /// Maximum number of levels, including the full resolution one
+    pub level_count: usize,
+    /// Index count ratio between two consecutive levels
+    pub reduction: f32,
+    /// Stop generating levels past this relative error
+    pub max_error: f32,
+    pub lock_border: bool,
+}
+
+impl Default for LodChainOptions {
+    fn default() -> Self {
+        Self {
+            level_count: 4,
+            reduction: 0.5,
+            max_error: 0.05,
+            lock_border: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LodLevel {
+    pub indices: IndiceValues,
+    /// Simplification error relative to the mesh extents
+    pub error: f32,
+}
+
+/// Index buffers of decreasing detail sharing the vertices of a single geometry
+#[derive(Debug)]
+pub struct LodChain {
+    levels: Vec<LodLevel>,
+}
+
+impl LodChain {
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Diameter in pixels of a bounding sphere seen by a perspective camera
+    pub fn projected_size(radius: f32, distance: f32, fovy: f32, viewport_height: f32) -> f32 {
+        let distance = distance.max(f32::EPSILON);
+        radius * viewport_height / (distance * (fovy * 0.5).tan())
+    }
+
+    /// Coarsest level whose error stays under `max_pixel_error` once the mesh covers
+    /// `screen_size` pixels
+    pub fn select(&self, screen_size: f32, max_pixel_error: f32) -> usize {
+        self.levels
+            .iter()
+            .rposition(|level| level.error * screen_size <= max_pixel_error)
+            .unwrap_or(0)
+    }
+
+    /// All levels in a single index list, with the index range of each level
+    pub fn concat_indices(&self) -> (IndiceValues, Vec<Range<u32>>) {
+        let mut values = Vec::new();
+        let mut ranges = Vec::with_capacity(self.levels.len());
+        for level in self.levels.iter() {
+            let start = values.len() as u32;
+            values.extend(level.indices.iter());
+            ranges.push(start..values.len() as u32);
+        }
+        (IndiceValues::auto(values), ranges)
+    }
+}
+
+/// A geometry drawn with one of the levels of a [`LodChain`]. Its index buffer
+/// holds every level, see [`LodChain::concat_indices`].
+pub struct LodGeometry {
+    pub geometry: Geometry,
+    ranges: Vec<Range<u32>>,
+    errors: Vec<f32>,
+}
+
+impl LodGeometry {
+    pub fn new(
+        context: &Context,
+        geometry: &GeometryData,
+        chain: &LodChain,
+    ) -> Result<Self, GeometryError> {
+        geometry.validate()?;
+        let (indices, ranges) = chain.concat_indices();
+        // Triangle levels are lists, other levels keep the geometry topology
+        let topology = if geometry.has_triangles() {
+            wgpu::PrimitiveTopology::TriangleList
+        } else {
+            geometry.topology()
+        };
+
+        Ok(Self {
+            geometry: Geometry::with_indices(context, geometry, Some(&indices), topology),
+            ranges,
+            errors: chain.levels().iter().map(|level| level.error).collect(),
+        })
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn select(&self, screen_size: f32, max_pixel_error: f32) -> usize {
+        self.errors
+            .iter()
+            .rposition(|error| error * screen_size <= max_pixel_error)
+            .unwrap_or(0)
+    }
+
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, slot: u32) {
+        self.geometry.bind(pass, slot);
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, level: usize) {
+        let range = self.ranges[level.min(self.ranges.len() - 1)].clone();
+        let geometry = &self.geometry;
+        if let (Some(index_buffer), Some(index_format)) =
+            (geometry.index_buffer.as_ref(), geometry.index_format)
+        {
+            pass.set_index_buffer(index_buffer.slice(..), index_format);
+            pass.draw_indexed(range, 0, 0..1);
+        }
+    }
+}
+
+impl GeometryData {
+    /// Simplify the triangles in place and return the relative error.
+    /// Vertices are kept, call [`GeometryData::optimize_vertex_fetch`] to drop unused ones.
+    /// Point and line geometries are left untouched.
+    pub fn simplify(&mut self, options: &SimplifyOptions) -> f32 {
+        match self.simplify_indices(options) {
+            Some(level) => {
+                self.set_topology(wgpu::PrimitiveTopology::TriangleList);
+                self.set_indices(Some(level.indices));
+                level.error
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Simplified triangle list, `None` without positions or triangles
+    pub fn simplify_indices(&self, options: &SimplifyOptions) -> Option<LodLevel> {
+        if !self.has_triangles() {
+            return None;
+        }
+        let positions = self.positions()?;
+        let indices = self.triangle_list_indices();
+        let (values, error) = simplify(&indices, positions, options);
+        let format = self
+            .indices()
+            .map(|indices| indices.index_format())
+            .unwrap_or(wgpu::IndexFormat::Uint32);
+        Some(LodLevel {
+            indices: IndiceValues::with_format(values, format),
+            error,
+        })
+    }
+
+    /// Levels of decreasing detail. Point and line geometries only get their
+    /// own indices as a single level.
+    pub fn generate_lod_chain(&self, options: &LodChainOptions) -> LodChain {
+        let format = self
+            .indices()
+            .map(|indices| indices.index_format())
+            .unwrap_or(wgpu::IndexFormat::Uint32);
+        if !self.has_triangles() {
+            return LodChain {
+                levels: vec![LodLevel {
+                    indices: IndiceValues::with_format(self.vertex_indices(), format),
+                    error: 0.0,
+                }],
+            };
+        }
+        let indices = self.triangle_list_indices();
+
+        let mut levels = Vec::with_capacity(options.level_count);
+        let mut current = indices;
+        let mut error = 0.0;
+
+        if let Some(positions) = self.positions() {
+            for _ in 1..options.level_count {
+                let target = (current.len() as f32 * options.reduction) as usize / 3 * 3;
+                let (values, level_error) = simplify(
+                    &current,
+                    positions,
+                    &SimplifyOptions {
+                        target_index_count: target,
+                        target_error: options.max_error - error,
+                        lock_border: options.lock_border,
+                    },
+                );
+
+                // Not worth a level of its own
+                if values.is_empty() || values.len() as f32 > current.len() as f32 * 0.95 {
+                    break;
+                }
+
+                let indices = std::mem::replace(&mut current, values);
+                levels.push(LodLevel {
+                    indices: IndiceValues::with_format(indices, format),
+                    error,
+                });
+                // Levels are simplified from each other, errors add up
+                error += level_error;
+            }
+        }
+
+        levels.push(LodLevel {
+            indices: IndiceValues::with_format(current, format),
+            error,
+        });
+
+        LodChain { levels }
+    }
+
+    fn has_triangles(&self) -> bool {
+        matches!(
+            self.topology(),
+            wgpu::PrimitiveTopology::TriangleList | wgpu::PrimitiveTopology::TriangleStrip
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Manifold,
+    Border,
+    Seam,
+    Locked,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric {
+    a00: f32,
+    a11: f32,
+    a22: f32,
+    a10: f32,
+    a20: f32,
+    a21: f32,
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    c: f32,
+    w: f32,
+}
+
+impl Quadric {
+    fn from_plane(n: [f32; 3], d: f32, w: f32) -> Self {
+        Self {
+            a00: w * n[0] * n[0],
+            a11: w * n[1] * n[1],
+            a22: w * n[2] * n[2],
+            a10: w * n[1] * n[0],
+            a20: w * n[2] * n[0],
+            a21: w * n[2] * n[1],
+            b0: w * n[0] * d,
+            b1: w * n[1] * d,
+            b2: w * n[2] * d,
+            c: w * d * d,
+            w,
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        self.a00 += other.a00;
+        self.a11 += other.a11;
+        self.a22 += other.a22;
+        self.a10 += other.a10;
+        self.a20 += other.a20;
+        self.a21 += other.a21;
+        self.b0 += other.b0;
+        self.b1 += other.b1;
+        self.b2 += other.b2;
+        self.c += other.c;
+        self.w += other.w;
+    }
+
+    /// Weighted squared distance from `p` to the accumulated planes
+    fn eval(&self, p: [f32; 3]) -> f32 {
+        let rx = self.b0 + self.a00 * p[0] + self.a10 * p[1] + self.a20 * p[2];
+        let ry = self.b1 + self.a10 * p[0] + self.a11 * p[1] + self.a21 * p[2];
+        let rz = self.b2 + self.a20 * p[0] + self.a21 * p[1] + self.a22 * p[2];
+        let r = rx * p[0]
+            + ry * p[1]
+            + rz * p[2]
+            + self.b0 * p[0]
+            + self.b1 * p[1]
+            + self.b2 * p[2]
+            + self.c;
+        if self.w == 0.0 {
+            0.0
+        } else {
+            r.abs() / self.w
+        }
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn corners_of(corners: &[u32]) -> [u32; 3] {
+    [corners[0], corners[1], corners[2]]
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Quadric error metric simplification of a triangle list by half-edge collapses.
+/// Vertices sharing a position with different attributes form seams that are only
+/// collapsed along themselves. Returns the new indices and the error relative to the
+/// mesh extents.
+pub fn simplify(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    options: &SimplifyOptions,
+) -> (Vec<u32>, f32) {
+    let vertex_count = positions.len();
+    let mut result = indices[..indices.len() / 3 * 3].to_vec();
+    if result.len() <= options.target_index_count {
+        return (result, 0.0);
+    }
+
+    // Work in a unit box so errors are relative to the mesh extents
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for position in positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    let extent = (0..3).map(|axis| max[axis] - min[axis]).fold(0.0, f32::max);
+    let scale = if extent > 0.0 { 1.0 / extent } else { 1.0 };
+    let positions = positions
+        .iter()
+        .map(|p| {
+            [
+                (p[0] - min[0]) * scale,
+                (p[1] - min[1]) * scale,
+                (p[2] - min[2]) * scale,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    // Vertices sharing a position are linked in a ring of wedges
+    let mut canonical = vec![0u32; vertex_count];
+    let mut wedge = (0..vertex_count as u32).collect::<Vec<_>>();
+    let mut wedge_size = vec![0u32; vertex_count];
+    let mut table: HashMap<[u32; 3], u32> = HashMap::new();
+    for (vertex, position) in positions.iter().enumerate() {
+        let key = position.map(|value| value.to_bits());
+        let first = *table.entry(key).or_insert(vertex as u32);
+        canonical[vertex] = first;
+        wedge_size[first as usize] += 1;
+        if first as usize != vertex {
+            wedge[vertex] = wedge[first as usize];
+            wedge[first as usize] = vertex as u32;
+        }
+    }
+
+    let mut edge_counts: HashMap<(u32, u32), u32> = HashMap::new();
+    for corners in result.chunks_exact(3) {
+        for k in 0..3 {
+            let a = canonical[corners[k] as usize];
+            let b = canonical[corners[(k + 1) % 3] as usize];
+            *edge_counts.entry(edge_key(a, b)).or_default() += 1;
+        }
+    }
+    let is_border_edge = |a: u32, b: u32| edge_counts.get(&edge_key(a, b)) == Some(&1);
+
+    let mut border = vec![false; vertex_count];
+    for ((a, b), count) in edge_counts.iter() {
+        if *count == 1 {
+            border[*a as usize] = true;
+            border[*b as usize] = true;
+        }
+    }
+
+    let kinds = (0..vertex_count)
+        .map(|vertex| {
+            let c = canonical[vertex] as usize;
+            match (border[c], wedge_size[c]) {
+                (false, 1) => VertexKind::Manifold,
+                (true, 1) if !options.lock_border => VertexKind::Border,
+                (false, 2) => VertexKind::Seam,
+                _ => VertexKind::Locked,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut quadrics = vec![Quadric::default(); vertex_count];
+    for corners in result.chunks_exact(3) {
+        let c = corners_of(corners).map(|v| canonical[v as usize] as usize);
+        let p = c.map(|v| positions[v]);
+        let normal = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+        let area = dot(normal, normal).sqrt();
+        if area == 0.0 {
+            continue;
+        }
+        let n = normal.map(|value| value / area);
+        let quadric = Quadric::from_plane(n, -dot(n, p[0]), area * 0.5);
+        for vertex in c {
+            quadrics[vertex].add(&quadric);
+        }
+
+        // Keep borders in place with planes perpendicular to the triangle
+        for k in 0..3 {
+            let (a, b) = (c[k], c[(k + 1) % 3]);
+            if !is_border_edge(a as u32, b as u32) {
+                continue;
+            }
+            let edge = sub(positions[b], positions[a]);
+            let length = dot(edge, edge).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let perpendicular = cross(edge.map(|value| value / length), n);
+            let quadric = Quadric::from_plane(
+                perpendicular,
+                -dot(perpendicular, positions[a]),
+                length * BORDER_EDGE_WEIGHT,
+            );
+            quadrics[a].add(&quadric);
+            quadrics[b].add(&quadric);
+        }
+    }
+
+    let max_error = options.target_error * options.target_error;
+    let mut result_error: f32 = 0.0;
+
+    while result.len() > options.target_index_count {
+        let mut edges = HashSet::new();
+        for corners in result.chunks_exact(3) {
+            for k in 0..3 {
+                edges.insert(edge_key(corners[k], corners[(k + 1) % 3]));
+            }
+        }
+
+        let can_collapse = |a: u32, b: u32| {
+            if canonical[a as usize] == canonical[b as usize] {
+                return false;
+            }
+            match kinds[a as usize] {
+                VertexKind::Manifold => true,
+                VertexKind::Border => {
+                    kinds[b as usize] == VertexKind::Border
+                        && is_border_edge(canonical[a as usize], canonical[b as usize])
+                }
+                VertexKind::Seam => {
+                    kinds[b as usize] == VertexKind::Seam
+                        && edges.contains(&edge_key(wedge[a as usize], wedge[b as usize]))
+                }
+                VertexKind::Locked => false,
+            }
+        };
+
+        // Cheapest collapse of every vertex
+        let mut best: Vec<Option<(u32, f32)>> = vec![None; vertex_count];
+        for corners in result.chunks_exact(3) {
+            for k in 0..3 {
+                for (a, b) in [
+                    (corners[k], corners[(k + 1) % 3]),
+                    (corners[(k + 1) % 3], corners[k]),
+                ] {
+                    if !can_collapse(a, b) {
+                        continue;
+                    }
+                    let error =
+                        quadrics[canonical[a as usize] as usize].eval(positions[b as usize]);
+                    match best[a as usize] {
+                        Some((_, current)) if current <= error => {}
+                        _ => best[a as usize] = Some((b, error)),
+                    }
+                }
+            }
+        }
+
+        let mut collapses = best
+            .iter()
+            .enumerate()
+            .filter_map(|(a, collapse)| collapse.map(|(b, error)| (a as u32, b, error)))
+            .collect::<Vec<_>>();
+        collapses.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        let mut offsets = vec![0usize; vertex_count + 1];
+        for index in result.iter() {
+            offsets[*index as usize + 1] += 1;
+        }
+        for vertex in 0..vertex_count {
+            offsets[vertex + 1] += offsets[vertex];
+        }
+        let mut adjacency = vec![0u32; result.len()];
+        let mut cursors = offsets.clone();
+        for (triangle, corners) in result.chunks_exact(3).enumerate() {
+            for vertex in corners {
+                adjacency[cursors[*vertex as usize]] = triangle as u32;
+                cursors[*vertex as usize] += 1;
+            }
+        }
+        let triangles_of =
+            |vertex: u32| &adjacency[offsets[vertex as usize]..offsets[vertex as usize + 1]];
+
+        let has_flips = |a: u32, b: u32| {
+            triangles_of(a).iter().any(|triangle| {
+                let corners = &result[*triangle as usize * 3..*triangle as usize * 3 + 3];
+                if corners.contains(&b) {
+                    return false;
+                }
+                let p = corners_of(corners).map(|v| positions[v as usize]);
+                let q = corners_of(corners).map(|v| positions[if v == a { b } else { v } as usize]);
+                let before = cross(sub(p[1], p[0]), sub(p[2], p[0]));
+                let after = cross(sub(q[1], q[0]), sub(q[2], q[0]));
+                dot(before, after) <= 0.0
+            })
+        };
+
+        let goal = ((result.len() - options.target_index_count) / 3)
+            .div_ceil(2)
+            .max(1);
+        let mut remap = (0..vertex_count as u32).collect::<Vec<_>>();
+        let mut locked = vec![false; vertex_count];
+        let mut applied = 0;
+
+        for (a, b, error) in collapses {
+            if error > max_error || applied >= goal {
+                break;
+            }
+
+            let pairs = if kinds[a as usize] == VertexKind::Seam {
+                vec![(a, b), (wedge[a as usize], wedge[b as usize])]
+            } else {
+                vec![(a, b)]
+            };
+            if pairs
+                .iter()
+                .any(|(a, b)| locked[*a as usize] || locked[*b as usize] || has_flips(*a, *b))
+            {
+                continue;
+            }
+
+            for (a, b) in pairs {
+                remap[a as usize] = b;
+                for triangle in triangles_of(a) {
+                    for vertex in &result[*triangle as usize * 3..*triangle as usize * 3 + 3] {
+                        locked[*vertex as usize] = true;
+                    }
+                }
+                locked[b as usize] = true;
+            }
+            let quadric = quadrics[canonical[a as usize] as usize];
+            quadrics[canonical[b as usize] as usize].add(&quadric);
+
+            result_error = result_error.max(error);
+            applied += 1;
+        }
+
+        if applied == 0 {
+            break;
+        }
+
+        result = result
+            .chunks_exact(3)
+            .map(|corners| corners_of(corners).map(|v| remap[v as usize]))
+            .filter(|c| c[0] != c[1] && c[1] != c[2] && c[0] != c[2])
+            .flatten()
+            .collect();
+    }
+
+    (result, result_error.sqrt())
+}
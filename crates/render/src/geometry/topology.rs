@@ -0,0 +1,114 @@
+use super::{GeometryData, IndiceValues};
+
+impl GeometryData {
+    /// Indices, or every vertex in order when the geometry is not indexed.
+    /// Restart values of strip topologies are widened to `u32::MAX`.
+    pub fn vertex_indices(&self) -> Vec<u32> {
+        match self.indices() {
+            Some(indices) if self.has_restart() => indices.iter_with_restart().collect(),
+            Some(indices) => indices.iter().collect(),
+            None => (0..self.compute_vertex_count() as u32).collect(),
+        }
+    }
+
+    /// Like [`GeometryData::vertex_indices`] without restart values
+    pub fn referenced_indices(&self) -> Vec<u32> {
+        let mut indices = self.vertex_indices();
+        if self.has_restart() {
+            indices.retain(|index| *index != u32::MAX);
+        }
+        indices
+    }
+
+    /// Indices of every triangle as a triangle list. Empty for point and line topologies.
+    pub fn triangle_list_indices(&self) -> Vec<u32> {
+        let indices = self.vertex_indices();
+        match self.topology() {
+            wgpu::PrimitiveTopology::TriangleList => indices,
+            wgpu::PrimitiveTopology::TriangleStrip => triangle_strip_to_list(&indices),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Indices of every line as a line list. Empty for point and triangle topologies.
+    pub fn line_list_indices(&self) -> Vec<u32> {
+        let indices = self.vertex_indices();
+        match self.topology() {
+            wgpu::PrimitiveTopology::LineList => indices,
+            wgpu::PrimitiveTopology::LineStrip => line_strip_to_list(&indices),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Unique edges of triangles and lines, in order of first appearance
+    pub fn to_edges(&self) -> Vec<[u32; 2]> {
+        let lines = match self.topology() {
+            wgpu::PrimitiveTopology::TriangleList | wgpu::PrimitiveTopology::TriangleStrip => {
+                triangle_list_edges(&self.triangle_list_indices())
+            }
+            _ => self.line_list_indices(),
+        };
+        unique_edges(&lines)
+    }
+
+    /// Line list geometry drawing every unique edge, sharing the vertices of this geometry
+    pub fn to_wireframe(&self) -> GeometryData {
+        let mut wireframe = self.clone();
+        let indices = self.to_edges().into_iter().flatten().collect();
+        wireframe.set_topology(wgpu::PrimitiveTopology::LineList);
+        wireframe.set_indices(Some(IndiceValues::auto(indices)));
+        wireframe
+    }
+}
+
+/// Convert triangle strip indices to a triangle list, keeping the winding of every
+/// triangle. Strips restart on `u32::MAX` and degenerate triangles are dropped.
+pub fn triangle_strip_to_list(indices: &[u32]) -> Vec<u32> {
+    let mut list = Vec::with_capacity(indices.len().saturating_sub(2) * 3);
+    for strip in indices.split(|index| *index == u32::MAX) {
+        for (i, window) in strip.windows(3).enumerate() {
+            let (a, b, c) = (window[0], window[1], window[2]);
+            if a == b || b == c || a == c {
+                continue;
+            }
+            if i % 2 == 0 {
+                list.extend([a, b, c]);
+            } else {
+                list.extend([b, a, c]);
+            }
+        }
+    }
+    list
+}
+
+/// Convert line strip indices to a line list. Strips restart on `u32::MAX`.
+pub fn line_strip_to_list(indices: &[u32]) -> Vec<u32> {
+    let mut list = Vec::with_capacity(indices.len().saturating_sub(1) * 2);
+    for strip in indices.split(|index| *index == u32::MAX) {
+        for window in strip.windows(2) {
+            list.extend([window[0], window[1]]);
+        }
+    }
+    list
+}
+
+/// Line list of the three edges of every triangle
+fn triangle_list_edges(indices: &[u32]) -> Vec<u32> {
+    let mut lines = Vec::with_capacity(indices.len() * 2);
+    for triangle in indices.chunks_exact(3) {
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        lines.extend([a, b, b, c, c, a]);
+    }
+    lines
+}
+
+/// Drop duplicated and degenerate lines of a line list, whatever their direction
+pub fn unique_edges(lines: &[u32]) -> Vec<[u32; 2]> {
+    let mut seen = std::collections::HashSet::with_capacity(lines.len() / 2);
+    lines
+        .chunks_exact(2)
+        .filter(|line| line[0] != line[1])
+        .filter(|line| seen.insert((line[0].min(line[1]), line[0].max(line[1]))))
+        .map(|line| [line[0], line[1]])
+        .collect()
+}
@@ -0,0 +1,142 @@
+use super::GeometryData;
+use std::fmt;
+
+/// Reason a [`GeometryData`] cannot be drawn
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeometryError {
+    /// An attribute does not have as many values as the first attribute
+    AttributeLengthMismatch {
+        attribute: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A morph target does not have a delta for every vertex
+    MorphTargetLengthMismatch {
+        target: String,
+        attribute: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An index references a vertex past the end of the attributes
+    IndexOutOfBounds {
+        position: usize,
+        index: u32,
+        vertex_count: usize,
+    },
+    /// A triangle list with a number of indices, or vertices, not a multiple of 3
+    TriangleIndexCount { index_count: usize },
+    /// A position has a NaN component
+    NanPosition { vertex: usize },
+    /// No attribute or no vertex
+    Empty,
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::AttributeLengthMismatch {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "attribute \"{attribute}\" has {found} values, expected {expected}"
+            ),
+            GeometryError::MorphTargetLengthMismatch {
+                target,
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "morph target \"{target}\" has {found} {attribute} deltas, expected {expected}"
+            ),
+            GeometryError::IndexOutOfBounds {
+                position,
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "index {index} at position {position} is out of bounds of {vertex_count} vertices"
+            ),
+            GeometryError::TriangleIndexCount { index_count } => write!(
+                f,
+                "triangle list has {index_count} indices, not a multiple of 3"
+            ),
+            GeometryError::NanPosition { vertex } => {
+                write!(f, "position of vertex {vertex} is NaN")
+            }
+            GeometryError::Empty => write!(f, "geometry is empty"),
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+impl GeometryData {
+    /// Check the geometry can be uploaded and drawn safely
+    pub fn validate(&self) -> Result<(), GeometryError> {
+        let mut attributes = self.attributes();
+        let (_, first) = attributes.next().ok_or(GeometryError::Empty)?;
+        let vertex_count = first.len();
+        for (name, values) in attributes {
+            if values.len() != vertex_count {
+                return Err(GeometryError::AttributeLengthMismatch {
+                    attribute: name.to_string(),
+                    expected: vertex_count,
+                    found: values.len(),
+                });
+            }
+        }
+        if vertex_count == 0 {
+            return Err(GeometryError::Empty);
+        }
+
+        for target in self.morph_targets() {
+            for (attribute, deltas) in target.deltas() {
+                if deltas.len() != vertex_count {
+                    return Err(GeometryError::MorphTargetLengthMismatch {
+                        target: target.name.clone(),
+                        attribute: attribute.to_string(),
+                        expected: vertex_count,
+                        found: deltas.len(),
+                    });
+                }
+            }
+        }
+
+        let restart = self.has_restart();
+        let indices = self.vertex_indices();
+        for (position, index) in indices.iter().copied().enumerate() {
+            if restart && index == u32::MAX {
+                continue;
+            }
+            if index as usize >= vertex_count {
+                return Err(GeometryError::IndexOutOfBounds {
+                    position,
+                    index,
+                    vertex_count,
+                });
+            }
+        }
+
+        if self.topology() == wgpu::PrimitiveTopology::TriangleList
+            && !indices.len().is_multiple_of(3)
+        {
+            return Err(GeometryError::TriangleIndexCount {
+                index_count: indices.len(),
+            });
+        }
+
+        if let Some(values) = self.attribute(Self::ATTRIBUTE_POSITION) {
+            let components = values.components();
+            if let Some(component) = values.as_floats().iter().position(|v| v.is_nan()) {
+                return Err(GeometryError::NanPosition {
+                    vertex: component / components,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
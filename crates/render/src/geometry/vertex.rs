@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum VertexValues {
     Float32(Vec<f32>),
     Float32x2(Vec<[f32; 2]>),
@@ -16,6 +16,55 @@ impl VertexValues {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of `f32` components per vertex
+    pub fn components(&self) -> usize {
+        match self {
+            VertexValues::Float32(_) => 1,
+            VertexValues::Float32x2(_) => 2,
+            VertexValues::Float32x3(_) => 3,
+            VertexValues::Float32x4(_) => 4,
+        }
+    }
+
+    pub fn as_floats(&self) -> &[f32] {
+        match self {
+            VertexValues::Float32(ref values) => values,
+            VertexValues::Float32x2(ref values) => bytemuck::cast_slice(values),
+            VertexValues::Float32x3(ref values) => bytemuck::cast_slice(values),
+            VertexValues::Float32x4(ref values) => bytemuck::cast_slice(values),
+        }
+    }
+
+    /// Components of the vertex at `index`
+    pub fn get(&self, index: usize) -> &[f32] {
+        let components = self.components();
+        &self.as_floats()[index * components..(index + 1) * components]
+    }
+
+    /// Move every value to `remap[index]`, dropping values mapped to `u32::MAX`.
+    pub fn remap(&mut self, remap: &[u32], len: usize) {
+        fn apply<T: Copy + Default>(values: &mut Vec<T>, remap: &[u32], len: usize) {
+            let mut result = vec![T::default(); len];
+            for (value, target) in values.iter().zip(remap) {
+                if *target != u32::MAX {
+                    result[*target as usize] = *value;
+                }
+            }
+            *values = result;
+        }
+
+        match self {
+            VertexValues::Float32(ref mut values) => apply(values, remap, len),
+            VertexValues::Float32x2(ref mut values) => apply(values, remap, len),
+            VertexValues::Float32x3(ref mut values) => apply(values, remap, len),
+            VertexValues::Float32x4(ref mut values) => apply(values, remap, len),
+        }
+    }
+
     pub fn get_bytes(&self) -> &[u8] {
         match self {
             VertexValues::Float32(ref values) => bytemuck::cast_slice(values),
@@ -1,6 +1,7 @@
 pub mod constants;
 pub mod context;
 pub mod geometry;
+pub mod mesh;
 pub mod pipeline;
 pub mod prelude;
 pub mod texture;
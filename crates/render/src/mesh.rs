@@ -1,6 +1,32 @@
+use crate::context::Context;
+use crate::geometry::{GeometryData, GeometryError, IndiceValues};
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub struct Submesh {
+    /// Range in the index buffer, or in the vertex buffer when the mesh has no indices
+    pub range: Range<u32>,
+    /// Added to every index of the range
+    pub base_vertex: i32,
+    pub topology: wgpu::PrimitiveTopology,
+    /// Index of the material used to draw this submesh
+    pub material: usize,
+}
+
 pub struct Mesh {
     attributes: Vec<wgpu::Buffer>,
     indices: Option<wgpu::Buffer>,
+    index_format: wgpu::IndexFormat,
+    vertex_count: usize,
+    vertex_size: u64,
+    vertex_attributes: Vec<wgpu::VertexAttribute>,
+    submeshes: Vec<Submesh>,
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mesh {
@@ -8,9 +34,91 @@ impl Mesh {
         Self {
             attributes: Vec::new(),
             indices: None,
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_count: 0,
+            vertex_size: 0,
+            vertex_attributes: Vec::new(),
+            submeshes: Vec::new(),
         }
     }
 
+    /// Pack geometries sharing the same vertex layout into a single vertex buffer and
+    /// index buffer, one submesh per geometry. Material slots follow the geometry order.
+    /// Every geometry is checked with [`GeometryData::validate`] first.
+    pub fn from_geometries(
+        context: &Context,
+        geometries: &[&GeometryData],
+    ) -> Result<Self, GeometryError> {
+        let mut mesh = Self::new();
+        let first = match geometries.first() {
+            Some(first) => first,
+            None => return Ok(mesh),
+        };
+        for geometry in geometries.iter() {
+            geometry.validate()?;
+        }
+
+        let vertex_attributes = first.create_vertex_attributes();
+        for geometry in geometries.iter() {
+            assert!(
+                geometry
+                    .create_vertex_attributes()
+                    .iter()
+                    .map(|attribute| attribute.format)
+                    .eq(vertex_attributes.iter().map(|attribute| attribute.format)),
+                "[fine::graphic::Mesh] Submeshes must share the same vertex layout."
+            );
+        }
+
+        let indexed = geometries
+            .iter()
+            .any(|geometry| geometry.indices().is_some());
+        let mut indices = Vec::new();
+        let mut base_vertex = 0;
+
+        for (material, geometry) in geometries.iter().enumerate() {
+            let vertex_count = geometry.compute_vertex_count() as u32;
+            let range = if indexed {
+                let start = indices.len() as u32;
+                indices.extend(geometry.vertex_indices());
+                start..indices.len() as u32
+            } else {
+                base_vertex..base_vertex + vertex_count
+            };
+
+            mesh.submeshes.push(Submesh {
+                range,
+                base_vertex: if indexed { base_vertex as i32 } else { 0 },
+                topology: geometry.topology(),
+                material,
+            });
+            base_vertex += vertex_count;
+        }
+
+        mesh.set_attribute(Self::create_vertex_buffer(context, geometries));
+        if indexed {
+            // Indices are local to each submesh, the smallest format usually fits
+            let indices = IndiceValues::auto(indices);
+            mesh.index_format = indices.index_format();
+            mesh.set_indices(Some(
+                context.create_buffer_with_data(wgpu::BufferUsages::INDEX, indices.get_bytes()),
+            ));
+        }
+        mesh.vertex_count = base_vertex as usize;
+        mesh.vertex_size = first.vertex_size();
+        mesh.vertex_attributes = vertex_attributes;
+        Ok(mesh)
+    }
+
+    /// Interleave and concatenate the vertices of every geometry
+    pub fn create_vertex_buffer(context: &Context, geometries: &[&GeometryData]) -> wgpu::Buffer {
+        let bytes = geometries
+            .iter()
+            .flat_map(|geometry| geometry.interleave())
+            .collect::<Vec<_>>();
+        context.create_buffer_with_data(wgpu::BufferUsages::VERTEX, &bytes)
+    }
+
     pub fn set_attribute(&mut self, buffer: wgpu::Buffer) {
         self.attributes.push(buffer);
     }
@@ -19,19 +127,82 @@ impl Mesh {
         self.indices = buffer;
     }
 
+    pub fn set_index_format(&mut self, format: wgpu::IndexFormat) {
+        self.index_format = format;
+    }
+
+    pub fn set_vertex_count(&mut self, vertex_count: usize) {
+        self.vertex_count = vertex_count;
+    }
+
+    pub fn add_submesh(&mut self, submesh: Submesh) {
+        self.submeshes.push(submesh);
+    }
+
+    pub fn set_material(&mut self, submesh: usize, material: usize) {
+        self.submeshes[submesh].material = material;
+    }
+
     pub fn attributes(&self) -> Vec<&wgpu::Buffer> {
-        self.attributes.iter().map(|buffer| buffer).collect()
+        self.attributes.iter().collect()
     }
 
     pub fn indices(&self) -> Option<&wgpu::Buffer> {
         self.indices.as_ref()
     }
 
-    pub fn vertex_count() {
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
 
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
     }
 
-    pub fn create_vertex_buffer(&self) {
+    /// Layout of the shared vertex buffer created by [`Mesh::from_geometries`]
+    pub fn vertex_buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.vertex_size,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.vertex_attributes,
+        }
+    }
 
+    /// Bind every vertex buffer, starting at `slot`, and the index buffer
+    pub fn bind<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, slot: u32) {
+        for (index, buffer) in self.attributes.iter().enumerate() {
+            pass.set_vertex_buffer(slot + index as u32, buffer.slice(..));
+        }
+        if let Some(indices) = self.indices.as_ref() {
+            pass.set_index_buffer(indices.slice(..), self.index_format);
+        }
+    }
+
+    /// Issue one draw call per submesh. Buffers must be bound with [`Mesh::bind`].
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        self.draw_with(pass, |_, _| {});
+    }
+
+    /// Like [`Mesh::draw`], calling `f` before each submesh to set its pipeline and bind groups
+    pub fn draw_with<'a, F>(&'a self, pass: &mut wgpu::RenderPass<'a>, mut f: F)
+    where
+        F: FnMut(&mut wgpu::RenderPass<'a>, &'a Submesh),
+    {
+        for submesh in self.submeshes.iter() {
+            f(pass, submesh);
+            self.draw_submesh(pass, submesh);
+        }
+    }
+
+    pub fn draw_submesh<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, submesh: &Submesh) {
+        if self.indices.is_some() {
+            pass.draw_indexed(submesh.range.clone(), submesh.base_vertex, 0..1);
+        } else {
+            pass.draw(submesh.range.clone(), 0..1);
+        }
     }
 }
@@ -1,5 +1,11 @@
 pub struct BindGroupLayoutBuilder(pub(crate) Vec<wgpu::BindGroupLayoutEntry>);
 
+impl Default for BindGroupLayoutBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BindGroupLayoutBuilder {
     pub fn new() -> Self {
         Self(Vec::new())
@@ -23,19 +29,18 @@ impl BindGroupBuilder {
     }
 
     /// Bind resources to entries
-    pub fn bind<'a, F>(&'a self, f: F) -> Vec<wgpu::BindGroupEntry>
+    pub fn bind<'a, F>(&'a self, f: F) -> Vec<wgpu::BindGroupEntry<'a>>
     where
         F: Fn(&wgpu::BindGroupLayoutEntry) -> Option<wgpu::BindingResource<'a>>,
     {
         self.entries
             .iter()
-            .filter_map(|entry| match f(entry) {
-                Some(resource) => Some(wgpu::BindGroupEntry {
+            .filter_map(|entry| {
+                f(entry).map(|resource| wgpu::BindGroupEntry {
                     binding: entry.binding,
                     resource,
-                }),
-                None => None,
+                })
             })
             .collect()
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
@@ -38,6 +38,21 @@ impl PipelineBuilder {
         self.primitive = primitive;
     }
 
+    /// Set the topology, keeping the rest of the primitive state. Strip topologies
+    /// drawn with indices need the index format to restart strips.
+    pub fn set_topology(
+        &mut self,
+        topology: wgpu::PrimitiveTopology,
+        strip_index_format: Option<wgpu::IndexFormat>,
+    ) {
+        self.primitive.topology = topology;
+        self.primitive.strip_index_format = if topology.is_strip() {
+            strip_index_format
+        } else {
+            None
+        };
+    }
+
     pub fn set_multisample(&mut self, multisample: wgpu::MultisampleState) {
         self.multisample = multisample;
     }
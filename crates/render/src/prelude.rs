@@ -2,6 +2,7 @@ pub use crate::constants::*;
 pub use crate::context::*;
 pub use crate::pipeline::*;
 pub use crate::geometry::*;
+pub use crate::mesh::*;
 pub use crate::texture::*;
 pub use crate::util::*;
 pub use wgpu;
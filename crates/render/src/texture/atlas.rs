@@ -1,26 +1,268 @@
-use super::{RawTexture, Texture};
+use super::{atlas_mip_level_count, MipmapGenerator, TextureDescriptor};
+use super::{AtlasPacker, PackedRect, PackerOptions, RawTexture, Texture};
 use crate::context::Context;
-use crate::prelude::{DEFAULT_TEXTURE_FORMAT, TextureSource};
+use crate::prelude::TextureSource;
 use std::collections::HashMap;
 use std::num::NonZeroU32;
 
+/// How a frame is turned in the atlas
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameRotation {
+    #[default]
+    None,
+    /// Turned 90 degrees clockwise
+    Clockwise,
+    /// Turned 90 degrees counter clockwise
+    CounterClockwise,
+}
+
 #[derive(Clone, Debug)]
 pub struct TextureRect {
     width: u32,
     height: u32,
     x: u32,
     y: u32,
+    /// `width` and `height` are the atlas ones when rotated
+    rotation: FrameRotation,
+    /// Position of the frame in the image it was trimmed from, from the top left
+    offset: (u32, u32),
+    /// Size of the image before trimming, `None` when not trimmed
+    source_size: Option<(u32, u32)>,
+}
+
+impl TextureRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            x,
+            y,
+            rotation: FrameRotation::None,
+            offset: (0, 0),
+            source_size: None,
+        }
+    }
+
+    /// Same rectangle, holding a frame turned by `rotation`
+    pub fn with_rotation(mut self, rotation: FrameRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Same rectangle, holding a frame cut at `offset` out of a
+    /// `source_width` by `source_height` image
+    pub fn with_trim(mut self, offset: (u32, u32), source_width: u32, source_height: u32) -> Self {
+        self.offset = offset;
+        self.source_size = Some((source_width, source_height));
+        self
+    }
+
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the frame is stored turned by 90 degrees
+    pub fn rotated(&self) -> bool {
+        self.rotation != FrameRotation::None
+    }
+
+    pub fn rotation(&self) -> FrameRotation {
+        self.rotation
+    }
+
+    /// Size of the frame, rotation undone
+    pub fn size(&self) -> (u32, u32) {
+        match self.rotation {
+            FrameRotation::None => (self.width, self.height),
+            _ => (self.height, self.width),
+        }
+    }
+
+    /// Position of the frame in the untrimmed image
+    pub fn offset(&self) -> (u32, u32) {
+        self.offset
+    }
+
+    /// Size of the untrimmed image, the frame size when not trimmed
+    pub fn source_size(&self) -> (u32, u32) {
+        self.source_size.unwrap_or_else(|| self.size())
+    }
+
+    /// Whether transparent borders were cut from the image
+    pub fn trimmed(&self) -> bool {
+        self.source_size() != self.size() || self.offset != (0, 0)
+    }
+
+    /// Normalized coordinates of the rectangle in a `width` by `height` layer
+    pub fn uv_rect(&self, layer: u32, width: u32, height: u32) -> UvRect {
+        let (width, height) = (width as f32, height as f32);
+        UvRect {
+            min: [self.x as f32 / width, self.y as f32 / height],
+            max: [
+                (self.x + self.width) as f32 / width,
+                (self.y + self.height) as f32 / height,
+            ],
+            layer,
+            rotation: self.rotation,
+        }
+    }
+}
+
+/// Normalized frame coordinates and the array layer to sample
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub layer: u32,
+    pub rotation: FrameRotation,
+}
+
+impl UvRect {
+    /// `[min.x, min.y, max.x, max.y]`
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.min[0], self.min[1], self.max[0], self.max[1]]
+    }
+
+    /// Coordinates of the top left, top right, bottom right and bottom left
+    /// corners of the original image, rotation undone
+    pub fn corners(&self) -> [[f32; 2]; 4] {
+        let [left, top] = self.min;
+        let [right, bottom] = self.max;
+        match self.rotation {
+            FrameRotation::None => [[left, top], [right, top], [right, bottom], [left, bottom]],
+            FrameRotation::Clockwise => {
+                [[right, top], [right, bottom], [left, bottom], [left, top]]
+            }
+            FrameRotation::CounterClockwise => {
+                [[left, bottom], [left, top], [right, top], [right, bottom]]
+            }
+        }
+    }
+}
+
+/// Origin of a pixel in an array layer, layers are addressed by `z`
+fn layer_origin(x: u32, y: u32, layer: u32) -> wgpu::Origin3d {
+    wgpu::Origin3d { x, y, z: layer }
+}
+
+/// Texels copied from `source` to `destination` in the atlas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameCopy {
+    source: TexturePosition,
+    destination: wgpu::Origin3d,
+    size: (u32, u32),
+}
+
+/// Copies of a `source` frame to `(x, y)` in `layer`, followed by its edges
+/// repeated `extrude` times around it. Edges past the `layer_size` are left out.
+fn frame_copies(
+    source: &TextureRect,
+    (x, y): TexturePosition,
+    layer: u32,
+    extrude: u32,
+    (layer_width, layer_height): (u32, u32),
+) -> Vec<FrameCopy> {
+    let (width, height) = (source.width, source.height);
+    let (left, top) = (source.x, source.y);
+    let (right, bottom) = (left + width - 1, top + height - 1);
+
+    let mut copies = Vec::new();
+    let mut copy = |source, (x, y): (Option<u32>, Option<u32>), (width, height): (u32, u32)| {
+        if let (Some(x), Some(y)) = (x, y) {
+            if x + width <= layer_width && y + height <= layer_height {
+                copies.push(FrameCopy {
+                    source,
+                    destination: layer_origin(x, y, layer),
+                    size: (width, height),
+                });
+            }
+        }
+    };
+
+    copy((left, top), (Some(x), Some(y)), (width, height));
+    for i in 1..=extrude {
+        let (near_x, near_y) = (x.checked_sub(i), y.checked_sub(i));
+        let (far_x, far_y) = (Some(x + width - 1 + i), Some(y + height - 1 + i));
+        let column = (1, height);
+        let row = (width, 1);
+        copy((left, top), (near_x, Some(y)), column);
+        copy((right, top), (far_x, Some(y)), column);
+        copy((left, top), (Some(x), near_y), row);
+        copy((left, bottom), (Some(x), far_y), row);
+        for j in 1..=extrude {
+            let (near_y, far_y) = (y.checked_sub(j), Some(y + height - 1 + j));
+            copy((left, top), (near_x, near_y), (1, 1));
+            copy((right, top), (far_x, near_y), (1, 1));
+            copy((left, bottom), (near_x, far_y), (1, 1));
+            copy((right, bottom), (far_x, far_y), (1, 1));
+        }
+    }
+    copies
+}
+
+/// Check that a `width` by `height` frame at `(x, y)` fits the layers of an atlas
+fn check_frame(
+    (atlas_width, atlas_height, layer_count): (u32, u32, u32),
+    (x, y): TexturePosition,
+    (width, height): (u32, u32),
+    layer: u32,
+) {
+    assert!(
+        layer < layer_count,
+        "[fine::graphic::TextureAtlas] Cannot add more layers."
+    );
+    assert!(
+        x.checked_add(width)
+            .is_some_and(|right| right <= atlas_width),
+        "[fine::graphic::TextureAtlas] width is bigger than the atlas width."
+    );
+    assert!(
+        y.checked_add(height)
+            .is_some_and(|bottom| bottom <= atlas_height),
+        "[fine::graphic::TextureAtlas] height is bigger than the atlas height."
+    );
+}
+
+/// Room taken in the packer by a frame
+#[derive(Debug, Clone, Copy)]
+enum PackerSlot {
+    Packed(PackedRect),
+    /// Rectangle given to [`AtlasPacker::reserve`]
+    Reserved {
+        layer: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
 }
 
 pub type TexturePosition = (u32, u32);
 
 pub struct TextureAtlas {
     layer_count: u32,
+    mip_level_count: u32,
+    descriptor: TextureDescriptor,
     width: u32,
     height: u32,
     texture: wgpu::Texture,
     view: wgpu::TextureView,
     rectangles: HashMap<String, (u32, TextureRect)>,
+    packer: AtlasPacker,
+    /// Packer room of the frames placed by this atlas, given back when replaced
+    slots: HashMap<String, PackerSlot>,
 }
 
 impl TextureAtlas {
@@ -28,43 +270,112 @@ impl TextureAtlas {
     const MAX_LAYER_COUNT: usize = 256;
 
     pub fn new(gpu: &mut Context, width: u32, height: u32, layer_count: u32) -> Self {
-        let texture = gpu.create_texture(&wgpu::TextureDescriptor {
-            label: Some("TextureAtlas::new"),
-            size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: layer_count,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: DEFAULT_TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
-        });
+        Self::with_packer(gpu, width, height, layer_count, PackerOptions::default())
+    }
+
+    /// Atlas placing frames without destination with the given packer options
+    pub fn with_packer(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        options: PackerOptions,
+    ) -> Self {
+        Self::with_mip_levels(gpu, width, height, layer_count, options, 1)
+    }
+
+    /// Atlas with room for mipmaps, filled by [`TextureAtlas::generate_mipmaps`].
+    /// Levels are limited to the ones keeping frames apart, see
+    /// [`atlas_mip_level_count`] with the packer extrusion.
+    pub fn with_mip_levels(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        options: PackerOptions,
+        mip_level_count: u32,
+    ) -> Self {
+        let descriptor = TextureDescriptor::new().with_mip_level_count(mip_level_count);
+        Self::with_descriptor(gpu, width, height, layer_count, options, &descriptor)
+    }
+
+    /// Atlas of any uncompressed format. It is always a 2D array, usable as
+    /// copy source and destination and for sampling. Mip levels are limited
+    /// as for [`TextureAtlas::with_mip_levels`].
+    pub fn with_descriptor(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        options: PackerOptions,
+        descriptor: &TextureDescriptor,
+    ) -> Self {
+        assert!(
+            !descriptor.format().describe().is_compressed(),
+            "[fine::graphic::TextureAtlas] compressed formats are not supported."
+        );
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layer_count,
+        };
+        let mip_level_count = descriptor
+            .mip_level_count(size)
+            .min(atlas_mip_level_count(options.extrude));
+        let mut usage = descriptor.usage()
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING;
+        if mip_level_count > 1 {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let descriptor = descriptor
+            .with_dimension(wgpu::TextureDimension::D2)
+            .with_usage(usage)
+            .with_sample_count(1)
+            .with_mip_level_count(mip_level_count);
+
+        let texture = gpu.create_texture(&descriptor.to_wgpu(Some("TextureAtlas::new"), size));
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
             label: None,
-            format: Some(DEFAULT_TEXTURE_FORMAT),
+            format: Some(descriptor.format()),
             dimension: Some(wgpu::TextureViewDimension::D2Array),
             aspect: wgpu::TextureAspect::default(),
             base_mip_level: 0,
-            mip_level_count: NonZeroU32::new(1),
+            mip_level_count: NonZeroU32::new(mip_level_count),
             base_array_layer: 0,
             array_layer_count: NonZeroU32::new(layer_count),
         });
 
         Self {
             layer_count,
+            mip_level_count,
+            descriptor,
             width,
             height,
             texture,
             view,
             rectangles: HashMap::new(),
+            packer: AtlasPacker::new(width, height, layer_count, options),
+            slots: HashMap::new(),
         }
     }
 
     pub fn frame(&self, name: impl Into<String>) -> Option<(u32, TextureRect)> {
-        self.rectangles.get(&name.into()).map(|r| r.clone())
+        self.rectangles.get(&name.into()).cloned()
+    }
+
+    /// Normalized coordinates and layer of a frame, ready for shaders
+    pub fn uv_rect(&self, name: &str) -> Option<UvRect> {
+        self.rectangles
+            .get(name)
+            .map(|(layer, rect)| rect.uv_rect(*layer, self.width, self.height))
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = (&str, u32, &TextureRect)> {
+        self.rectangles
+            .iter()
+            .map(|(name, (layer, rect))| (name.as_str(), *layer, rect))
     }
 
     pub fn width(&self) -> u32 {
@@ -83,6 +394,42 @@ impl TextureAtlas {
         (self.width, self.height, self.layer_count)
     }
 
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.descriptor.format()
+    }
+
+    /// Fill the mip levels of every layer from the frames, on the GPU.
+    /// Run it again after adding frames.
+    pub fn generate_mipmaps(&self, gpu: &mut Context, generator: &mut MipmapGenerator, srgb: bool) {
+        generator.generate(
+            gpu,
+            &self.texture,
+            self.descriptor.format(),
+            (self.width, self.height),
+            self.layer_count,
+            self.mip_level_count,
+            srgb,
+        );
+    }
+
+    pub fn packer(&self) -> &AtlasPacker {
+        &self.packer
+    }
+
+    /// Area of the packed frames over the area of the layers used, between 0 and 1
+    pub fn packing_efficiency(&self) -> f32 {
+        self.packer.efficiency()
+    }
+
+    /// Copy `source` to `destination` in `layer`, extruded as set by the packer
+    /// options. Without destination the frame is packed in `layer` or the next
+    /// layer with room, padded as well. The rotation of `source` is kept as is.
+    /// A frame of the same name is replaced, and removed when this one does not fit.
+    /// Returns the frame, or `None` when no layer has room.
     pub fn append_raw_texture<S>(
         &mut self,
         name: S,
@@ -91,57 +438,181 @@ impl TextureAtlas {
         texture: &RawTexture,
         source: TextureRect,
         destination: Option<TexturePosition>,
-    ) where
+    ) -> Option<(u32, TextureRect)>
+    where
         S: Into<String>,
     {
-        self.assert(source.width, source.height, layer);
+        let name = name.into();
+        self.remove_frame(&name);
 
-        let origin = if let Some(origin) = destination {
-            wgpu::Origin3d {
-                x: origin.0,
-                y: origin.1,
-                z: 0,
+        let extrude = self.packer.options().extrude;
+        let (layer, x, y, slot) = match destination {
+            Some((x, y)) => {
+                let size = (source.width, source.height);
+                check_frame(self.dimensions(), (x, y), size, layer);
+                // Keep packed frames away from it and its edges inside the layer
+                let (left, top) = (x.saturating_sub(extrude), y.saturating_sub(extrude));
+                let right = (x + size.0).saturating_add(extrude).min(self.width);
+                let bottom = (y + size.1).saturating_add(extrude).min(self.height);
+                let (width, height) = (right - left, bottom - top);
+                self.packer.reserve(layer, left, top, width, height);
+                let slot = PackerSlot::Reserved {
+                    layer,
+                    x: left,
+                    y: top,
+                    width,
+                    height,
+                };
+                (layer, x, y, slot)
+            }
+            None => {
+                let packed = self.packer.pack(source.width, source.height, layer, false)?;
+                (packed.layer, packed.x, packed.y, PackerSlot::Packed(packed))
             }
-        } else {
-            wgpu::Origin3d::ZERO
         };
 
+        let layer_size = (self.width, self.height);
+        for copy in frame_copies(&source, (x, y), layer, extrude, layer_size) {
+            self.copy_raw(gpu, texture, &copy);
+        }
+
+        // Rotation and trim are kept from the source
+        let rect = TextureRect { x, y, ..source };
+        let _ = self.slots.insert(name.clone(), slot);
+        let _ = self.rectangles.insert(name, (layer, rect.clone()));
+        Some((layer, rect))
+    }
+
+    fn copy_raw(&self, gpu: &mut Context, texture: &RawTexture, copy: &FrameCopy) {
         gpu.write_texture(
             TextureSource::Texture(wgpu::ImageCopyTexture {
                 texture: texture.as_raw(),
                 mip_level: 0,
                 origin: wgpu::Origin3d {
-                    x: source.x,
-                    y: source.y,
+                    x: copy.source.0,
+                    y: copy.source.1,
                     z: 0,
                 },
                 aspect: wgpu::TextureAspect::All,
             }),
             wgpu::ImageCopyTexture {
                 texture: &self.texture,
-                mip_level: layer,
-                origin,
+                mip_level: 0,
+                origin: copy.destination,
                 aspect: wgpu::TextureAspect::All,
             },
             wgpu::Extent3d {
-                width: source.width,
-                height: source.height,
+                width: copy.size.0,
+                height: copy.size.1,
                 depth_or_array_layers: 1,
             },
         );
+    }
 
-        let _ = self.rectangles.insert(
-            name.into(),
-            (
-                layer,
-                TextureRect {
-                    width: source.width,
-                    height: source.height,
-                    x: origin.x,
-                    y: origin.y,
-                },
-            ),
+    /// Pack `width` by `height` pixels in the first layer with room, rotating
+    /// them when the packer allows it. Edges are extruded on the CPU. A frame of
+    /// the same name is replaced, and removed when this one does not fit.
+    /// Returns the frame, or `None` when no layer has room.
+    pub fn pack_bytes<S>(
+        &mut self,
+        name: S,
+        gpu: &mut Context,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Option<(u32, TextureRect)>
+    where
+        S: Into<String>,
+    {
+        assert_eq!(
+            bytes.len() as u64,
+            width as u64 * height as u64 * self.descriptor.block_size() as u64,
+            "[fine::graphic::TextureAtlas] bytes do not match the frame size."
         );
+        let name = name.into();
+        self.remove_frame(&name);
+
+        let packed = self.packer.pack(width, height, 0, true)?;
+        let rect = self.write_packed(gpu, bytes, width, &packed);
+        let _ = self.slots.insert(name.clone(), PackerSlot::Packed(packed));
+        let _ = self.rectangles.insert(name, (packed.layer, rect.clone()));
+        Some((packed.layer, rect))
+    }
+
+    /// Upload `bytes` to a placement, turned and extruded as the packer asks
+    pub(super) fn write_packed(
+        &self,
+        gpu: &mut Context,
+        bytes: &[u8],
+        width: u32,
+        packed: &PackedRect,
+    ) -> TextureRect {
+        let extrude = self.packer.options().extrude;
+        let texel = self.descriptor.block_size();
+        let (frame_width, frame_height) = (packed.width, packed.height);
+        let block_width = frame_width + extrude * 2;
+        let block_height = frame_height + extrude * 2;
+        let mut block = Vec::with_capacity((block_width * block_height * texel) as usize);
+        for block_y in 0..block_height {
+            for block_x in 0..block_width {
+                let x = block_x.saturating_sub(extrude).min(frame_width - 1);
+                let y = block_y.saturating_sub(extrude).min(frame_height - 1);
+                // Turned clockwise, the source column is the frame row
+                let (source_x, source_y) = if packed.rotated {
+                    (y, frame_width - 1 - x)
+                } else {
+                    (x, y)
+                };
+                let offset = ((source_y * width + source_x) * texel) as usize;
+                block.extend_from_slice(&bytes[offset..offset + texel as usize]);
+            }
+        }
+
+        gpu.enqueue_write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: layer_origin(packed.x - extrude, packed.y - extrude, packed.layer),
+                aspect: wgpu::TextureAspect::All,
+            },
+            &block,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(texel * block_width),
+                rows_per_image: NonZeroU32::new(block_height),
+            },
+            wgpu::Extent3d {
+                width: block_width,
+                height: block_height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let mut rect = TextureRect::new(packed.x, packed.y, frame_width, frame_height);
+        if packed.rotated {
+            rect.rotation = FrameRotation::Clockwise;
+        }
+        rect
+    }
+
+    pub(super) fn set_frame(&mut self, name: String, layer: u32, rect: TextureRect) {
+        let _ = self.rectangles.insert(name, (layer, rect));
+    }
+
+    /// Forget a frame, giving back its packer room when this atlas placed it
+    pub(super) fn remove_frame(&mut self, name: &str) -> Option<(u32, TextureRect)> {
+        match self.slots.remove(name) {
+            Some(PackerSlot::Packed(packed)) => self.packer.free(&packed),
+            Some(PackerSlot::Reserved {
+                layer,
+                x,
+                y,
+                width,
+                height,
+            }) => self.packer.unreserve(layer, x, y, width, height),
+            None => {}
+        }
+        self.rectangles.remove(name)
     }
 
     pub fn append_bytes<F>(
@@ -154,8 +625,10 @@ impl TextureAtlas {
     ) where
         F: FnMut(&mut TextureAtlasFromBytes),
     {
-        let texture =
-            &RawTexture::from_bytes(gpu, width, height, wgpu::TextureUsages::COPY_SRC, bytes);
+        let descriptor = TextureDescriptor::new()
+            .with_format(self.descriptor.format())
+            .with_usage(wgpu::TextureUsages::COPY_SRC);
+        let texture = &RawTexture::from_bytes_with_descriptor(gpu, width, height, &descriptor, bytes);
 
         f(&mut TextureAtlasFromBytes {
             gpu,
@@ -169,33 +642,18 @@ impl TextureAtlas {
             let width = frame.1.width;
             let height = frame.1.height;
 
-            let raw = gpu.create_texture(&wgpu::TextureDescriptor {
-                label: Some("[fine::graphic::TextureAtlas] frame_to_raw_texture"),
-                format: DEFAULT_TEXTURE_FORMAT,
-                dimension: wgpu::TextureDimension::D2,
-                size: wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                usage: wgpu::TextureUsages::COPY_DST,
-            });
+            let descriptor = TextureDescriptor::new().with_format(self.descriptor.format());
+            let raw = RawTexture::with_descriptor(gpu, width, height, 1, &descriptor);
 
             gpu.write_texture(
                 TextureSource::Texture(wgpu::ImageCopyTexture {
                     texture: &self.texture,
-                    mip_level: frame.0,
-                    origin: wgpu::Origin3d {
-                        x: frame.1.x,
-                        y: frame.1.y,
-                        z: 0,
-                    },
+                    mip_level: 0,
+                    origin: layer_origin(frame.1.x, frame.1.y, frame.0),
                     aspect: wgpu::TextureAspect::All,
                 }),
                 wgpu::ImageCopyTexture {
-                    texture: &raw,
+                    texture: raw.as_raw(),
                     mip_level: 0,
                     origin: wgpu::Origin3d::ZERO,
                     aspect: wgpu::TextureAspect::All,
@@ -207,7 +665,7 @@ impl TextureAtlas {
                 },
             );
 
-            RawTexture::new(raw, width, height)
+            raw
         })
     }
 
@@ -215,24 +673,9 @@ impl TextureAtlas {
         self.frame_to_raw(gpu, name).map(|raw| {
             let width = raw.width();
             let height = raw.height();
-            Texture::from_raw(&raw, width, height)
+            Texture::from_raw(gpu, &raw, width, height)
         })
     }
-
-    fn assert(&self, width: u32, height: u32, layer: u32) {
-        assert!(
-            layer <= self.layer_count,
-            "[fine::graphic::TextureAtlas] Cannot add more layers."
-        );
-        assert!(
-            width <= self.width,
-            "[fine::graphic::TextureAtlas] width is bigger than the atlas width."
-        );
-        assert!(
-            height <= self.height,
-            "[fine::graphic::TextureAtlas] height is bigger than the atlas height."
-        );
-    }
 }
 
 impl super::AsTextureView for TextureAtlas {
@@ -264,21 +707,182 @@ impl<'a> TextureAtlasFromBytes<'a> {
     where
         S: Into<String>,
     {
-        self.atlas
-            .append_raw_texture(name, layer, self.gpu, &self.texture, source, destination);
+        let _ = self
+            .atlas
+            .append_raw_texture(name, layer, self.gpu, self.texture, source, destination);
         self
     }
 }
 
 #[cfg(feature = "use-image")]
 impl TextureAtlas {
-    pub fn append_image<F>(&mut self, gpu: &mut Gpu, img: &image::DynamicImage, f: F)
+    /// Same as [`TextureAtlas::append_bytes`], the image must have the atlas
+    /// format or its sRGB or linear variant
+    pub fn append_image<F>(
+        &mut self,
+        gpu: &mut Context,
+        image: &super::DecodedImage,
+        f: F,
+    ) -> Result<(), super::ImageError>
     where
         F: FnMut(&mut TextureAtlasFromBytes),
     {
-        let img = img.to_bgra();
-        let (width, height) = img.dimensions();
-        let bytes = &img.into_raw()[..];
-        self.append_bytes(gpu, bytes, width, height, f);
+        image.check_format(self.format())?;
+        self.append_bytes(gpu, &image.bytes, image.width, image.height, f);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::PackingAlgorithm;
+
+    const ATLAS: (u32, u32, u32) = (64, 32, 3);
+
+    #[test]
+    #[should_panic(expected = "Cannot add more layers")]
+    fn layer_count_is_exclusive() {
+        check_frame(ATLAS, (0, 0), (1, 1), ATLAS.2);
+    }
+
+    #[test]
+    #[should_panic(expected = "bigger than the atlas width")]
+    fn frame_must_fit_the_layer() {
+        check_frame(ATLAS, (48, 0), (32, 16), 0);
+    }
+
+    #[test]
+    fn frames_are_copied_to_their_layer() {
+        let (width, height, layer_count) = ATLAS;
+        let options = PackerOptions {
+            extrude: 1,
+            ..PackerOptions::default()
+        };
+        let mut packer = AtlasPacker::new(width, height, layer_count, options);
+        let source = TextureRect::new(4, 8, 16, 16);
+        // Three extruded frames per layer
+        for index in 0..9 {
+            let packed = packer.pack(16, 16, 0, false).unwrap();
+            assert_eq!(packed.layer, index / 3);
+            let copies = frame_copies(&source, (packed.x, packed.y), packed.layer, 1, (64, 32));
+            assert_eq!(
+                copies[0],
+                FrameCopy {
+                    source: (4, 8),
+                    destination: wgpu::Origin3d {
+                        x: packed.x,
+                        y: packed.y,
+                        z: packed.layer,
+                    },
+                    size: (16, 16),
+                }
+            );
+            // Four edges and four corners around the frame, in the same layer
+            assert_eq!(copies.len(), 9);
+            assert!(copies.iter().all(|copy| copy.destination.z == packed.layer));
+            let texels = copies.iter().map(|copy| copy.size.0 * copy.size.1);
+            assert_eq!(texels.sum::<u32>(), 18 * 18);
+        }
+        assert!(packer.pack(16, 16, 0, false).is_none());
+    }
+
+    #[test]
+    fn edges_past_the_layer_are_not_copied() {
+        let source = TextureRect::new(0, 0, 16, 8);
+        // Only the right column, the bottom row and their corner fit
+        let copies = frame_copies(&source, (0, 0), 1, 1, (64, 32));
+        assert_eq!(copies.len(), 4);
+        assert!(copies.iter().all(|copy| copy.destination.z == 1));
+        let texels = copies.iter().map(|copy| copy.size.0 * copy.size.1);
+        assert_eq!(texels.sum::<u32>(), 17 * 9);
+
+        // Against the far corner
+        let copies = frame_copies(&source, (48, 24), 0, 2, (64, 32));
+        let texels = copies.iter().map(|copy| copy.size.0 * copy.size.1);
+        assert_eq!(texels.sum::<u32>(), 18 * 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "bigger than the atlas width")]
+    fn frame_position_does_not_overflow() {
+        check_frame(ATLAS, (u32::MAX, 0), (2, 1), 0);
+    }
+
+    #[test]
+    fn reserved_frames_are_given_back() {
+        let (width, height, _) = ATLAS;
+        let options = PackerOptions {
+            algorithm: PackingAlgorithm::MaxRects,
+            ..PackerOptions::default()
+        };
+        let mut packer = AtlasPacker::new(width, height, 1, options);
+        packer.reserve(0, 0, 0, 32, 32);
+        packer.reserve(0, 32, 0, 32, 32);
+        assert!(packer.pack(32, 32, 0, false).is_none());
+        packer.unreserve(0, 32, 0, 32, 32);
+        let packed = packer.pack(32, 32, 0, false).unwrap();
+        assert_eq!((packed.x, packed.y), (32, 0));
+    }
+
+    #[test]
+    fn uv_rects_of_a_three_layer_atlas() {
+        let (width, height, layer_count) = ATLAS;
+        let mut packer = AtlasPacker::new(width, height, layer_count, PackerOptions::default());
+        let packed = (0..6)
+            .map(|_| packer.pack(32, 32, 0, false).unwrap())
+            .collect::<Vec<_>>();
+        assert!(packer.pack(32, 32, 0, false).is_none());
+        assert_eq!(packer.layers_used(), 3);
+
+        for (index, packed) in packed.iter().enumerate() {
+            assert_eq!(packed.layer, index as u32 / 2);
+            let rect = TextureRect::new(packed.x, packed.y, packed.width, packed.height);
+            let uv = rect.uv_rect(packed.layer, width, height);
+            assert_eq!(uv.layer, packed.layer);
+            assert_eq!(uv.max[1] - uv.min[1], 1.0);
+            assert_eq!(uv.max[0] - uv.min[0], 0.5);
+        }
+
+        let uv = TextureRect::new(16, 8, 32, 16).uv_rect(2, width, height);
+        assert_eq!(uv.to_array(), [0.25, 0.25, 0.75, 0.75]);
+        assert_eq!(uv.layer, 2);
+        assert_eq!(uv.corners()[0], [0.25, 0.25]);
+    }
+
+    #[test]
+    fn reserved_frames_are_not_packed_over() {
+        let (width, height, layer_count) = ATLAS;
+        for algorithm in [PackingAlgorithm::Skyline, PackingAlgorithm::MaxRects] {
+            let options = PackerOptions {
+                algorithm,
+                ..PackerOptions::default()
+            };
+            let mut packer = AtlasPacker::new(width, height, layer_count, options);
+            packer.reserve(0, 24, 0, 16, 16);
+            let packed = packer.pack(32, 32, 0, false).unwrap();
+            assert_eq!(packed.layer, 1);
+
+            let packed = packer.pack(16, 16, 0, false).unwrap();
+            assert_eq!(packed.layer, 0);
+            assert!(packed.x + 16 <= 24 || packed.x >= 40 || packed.y >= 16);
+        }
+    }
+
+    #[test]
+    fn rotated_corners_undo_the_rotation() {
+        let rect = TextureRect::new(0, 0, 32, 16).with_rotation(FrameRotation::Clockwise);
+        let uv = rect.uv_rect(1, 64, 32);
+        assert!(rect.rotated());
+        assert_eq!(
+            uv.corners(),
+            [[0.5, 0.0], [0.5, 0.5], [0.0, 0.5], [0.0, 0.0]]
+        );
+
+        let rect = rect.with_rotation(FrameRotation::CounterClockwise);
+        assert_eq!(
+            rect.uv_rect(1, 64, 32).corners(),
+            [[0.0, 0.5], [0.0, 0.0], [0.5, 0.0], [0.5, 0.5]]
+        );
     }
 }
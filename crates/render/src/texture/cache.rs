@@ -0,0 +1,431 @@
+use super::{
+    AsTextureView, AtlasPacker, PackedRect, PackerOptions, TextureAtlas, TextureRect, UvRect,
+};
+use crate::context::Context;
+use std::collections::HashMap;
+
+/// Frame placed somewhere else by a defragmentation
+#[derive(Debug, Clone)]
+pub struct MovedFrame {
+    pub name: String,
+    pub layer: u32,
+    pub rect: TextureRect,
+}
+
+/// Result of [`AtlasCache::insert`]
+#[derive(Debug, Clone)]
+pub struct CacheInsertion {
+    /// Layer and placement, `None` when the frame did not fit
+    pub frame: Option<(u32, TextureRect)>,
+    /// Frames evicted to make room, even when the frame did not fit after all
+    pub evicted: Vec<String>,
+    /// Frames moved by a defragmentation to make room
+    pub moved: Vec<MovedFrame>,
+}
+
+/// Placement if any, evicted frames and moved frames
+type Placement = (Option<PackedRect>, Vec<String>, Vec<String>);
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    packed: PackedRect,
+    width: u32,
+    /// Kept to upload the frame again when it moves
+    bytes: Vec<u8>,
+    last_used: u64,
+}
+
+/// Placements of the cached frames, without any GPU work
+#[derive(Debug, Clone)]
+struct CacheLayout {
+    packer: AtlasPacker,
+    entries: HashMap<String, CacheEntry>,
+    frame: u64,
+}
+
+impl CacheLayout {
+    fn new(width: u32, height: u32, layer_count: u32, options: PackerOptions) -> Self {
+        Self {
+            packer: AtlasPacker::new(width, height, layer_count, options),
+            entries: HashMap::new(),
+            frame: 0,
+        }
+    }
+
+    fn touch(&mut self, name: &str) -> Option<&CacheEntry> {
+        let frame = self.frame;
+        self.entries.get_mut(name).map(|entry| {
+            entry.last_used = frame;
+            &*entry
+        })
+    }
+
+    fn remove(&mut self, name: &str) -> Option<CacheEntry> {
+        let entry = self.entries.remove(name)?;
+        self.packer.free(&entry.packed);
+        Some(entry)
+    }
+
+    /// Place a frame under `name`. A frame of the same name is freed once the
+    /// new one has room, and stays when it does not fit.
+    fn insert(&mut self, name: &str, width: u32, height: u32, bytes: &[u8]) -> Placement {
+        let (packed, evicted, mut moved) = self.place(width, height, name);
+        let packed = match packed {
+            Some(packed) => packed,
+            None => return (None, evicted, moved),
+        };
+
+        self.remove(name);
+        moved.retain(|moved| moved != name);
+        let entry = CacheEntry {
+            packed,
+            width,
+            bytes: bytes.to_vec(),
+            last_used: self.frame,
+        };
+        let _ = self.entries.insert(name.to_string(), entry);
+        (Some(packed), evicted, moved)
+    }
+
+    /// Least recently used frame not used during the current frame, other than `keep`
+    fn least_recently_used(&self, keep: &str) -> Option<String> {
+        self.entries
+            .iter()
+            .filter(|(name, entry)| entry.last_used < self.frame && name.as_str() != keep)
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Place a frame, evicting least recently used frames other than `keep` when
+    /// there is no room, then defragmenting when it still does not fit. Returns
+    /// the placement, the evicted frames and the moved ones.
+    fn place(&mut self, width: u32, height: u32, keep: &str) -> Placement {
+        let mut evicted = Vec::new();
+        let mut moved = Vec::new();
+
+        // Nothing to evict for a frame bigger than a layer
+        let options = *self.packer.options();
+        let (layer_width, layer_height) = self.packer.layer_size();
+        let mut empty = AtlasPacker::new(layer_width, layer_height, 1, options);
+        if empty.pack(width, height, 0, true).is_none() {
+            return (None, evicted, moved);
+        }
+
+        let mut defragmented = false;
+        loop {
+            if let Some(packed) = self.packer.pack(width, height, 0, true) {
+                return (Some(packed), evicted, moved);
+            }
+            if let Some(name) = self.least_recently_used(keep) {
+                self.remove(&name);
+                evicted.push(name);
+                continue;
+            }
+            if defragmented {
+                return (None, evicted, moved);
+            }
+            moved = self.defragment();
+            defragmented = true;
+        }
+    }
+
+    /// Pack every frame again, biggest first then in place order. Returns the
+    /// moved frames, or nothing when the new layout would not hold every frame.
+    fn defragment(&mut self) -> Vec<String> {
+        let mut names = self.entries.keys().cloned().collect::<Vec<_>>();
+        names.sort_by_key(|name| {
+            let packed = &self.entries[name].packed;
+            let (width, height) = (packed.width, packed.height);
+            (
+                std::cmp::Reverse(width.max(height)),
+                std::cmp::Reverse(width.min(height)),
+                (packed.layer, packed.y, packed.x),
+            )
+        });
+
+        let mut packer = self.packer.clone();
+        packer.clear();
+        let mut placements = Vec::with_capacity(names.len());
+        for name in names {
+            let entry = &self.entries[&name];
+            let (width, height) = if entry.packed.rotated {
+                (entry.packed.height, entry.packed.width)
+            } else {
+                (entry.packed.width, entry.packed.height)
+            };
+            match packer.pack(width, height, 0, true) {
+                Some(packed) => placements.push((name, packed)),
+                None => return Vec::new(),
+            }
+        }
+
+        self.packer = packer;
+        let mut moved = Vec::new();
+        for (name, packed) in placements {
+            let entry = self.entries.get_mut(&name).unwrap();
+            if entry.packed != packed {
+                entry.packed = packed;
+                moved.push(name);
+            }
+        }
+        moved
+    }
+}
+
+/// Atlas for content rasterized at runtime, such as glyphs or thumbnails.
+/// Frames are inserted on demand and the least recently used ones are evicted
+/// when the atlas is full. Frames used during the current frame, see
+/// [`AtlasCache::next_frame`], are never evicted.
+pub struct AtlasCache {
+    atlas: TextureAtlas,
+    layout: CacheLayout,
+}
+
+impl AtlasCache {
+    /// Cache packing with `options`. Skyline only reuses the room of evicted
+    /// frames once their layer is empty, MaxRects reuses it right away.
+    pub fn new(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        layer_count: u32,
+        options: PackerOptions,
+    ) -> Self {
+        Self {
+            atlas: TextureAtlas::with_packer(gpu, width, height, layer_count, options),
+            layout: CacheLayout::new(width, height, layer_count, options),
+        }
+    }
+
+    pub fn atlas(&self) -> &TextureAtlas {
+        &self.atlas
+    }
+
+    pub fn len(&self) -> usize {
+        self.layout.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.entries.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.layout.entries.contains_key(name)
+    }
+
+    /// Start a new frame, frames used before become evictable
+    pub fn next_frame(&mut self) {
+        self.layout.frame += 1;
+    }
+
+    /// Frame placement, marking it used
+    pub fn get(&mut self, name: &str) -> Option<(u32, TextureRect)> {
+        self.layout.touch(name)?;
+        self.atlas.frame(name)
+    }
+
+    /// Normalized coordinates and layer of a frame, marking it used
+    pub fn uv_rect(&mut self, name: &str) -> Option<UvRect> {
+        self.layout.touch(name)?;
+        self.atlas.uv_rect(name)
+    }
+
+    /// Insert or replace a frame of `width` by `height` texels of the atlas
+    /// format. The frame is missing from the result when it does not fit, even
+    /// with every evictable frame gone, a replaced frame is then kept.
+    pub fn insert<S>(
+        &mut self,
+        name: S,
+        gpu: &mut Context,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+    ) -> CacheInsertion
+    where
+        S: Into<String>,
+    {
+        let block_size = self.atlas.format().describe().block_size as u64;
+        assert_eq!(
+            bytes.len() as u64,
+            width as u64 * height as u64 * block_size,
+            "[fine::graphic::AtlasCache] bytes do not match the frame size."
+        );
+        let name = name.into();
+
+        let (packed, evicted, moved) = self.layout.insert(&name, width, height, bytes);
+        for name in evicted.iter() {
+            self.atlas.remove_frame(name);
+        }
+        let moved = self.upload_moved(gpu, moved);
+        let packed = match packed {
+            Some(packed) => packed,
+            None => {
+                return CacheInsertion {
+                    frame: None,
+                    evicted,
+                    moved,
+                }
+            }
+        };
+
+        let rect = self.atlas.write_packed(gpu, bytes, width, &packed);
+        self.atlas.set_frame(name, packed.layer, rect.clone());
+
+        CacheInsertion {
+            frame: Some((packed.layer, rect)),
+            evicted,
+            moved,
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.atlas.remove_frame(name);
+        self.layout.remove(name).is_some()
+    }
+
+    /// Pack every frame again to gather the free room. Returns the frames
+    /// which moved, their cached coordinates must be refreshed.
+    pub fn defragment(&mut self, gpu: &mut Context) -> Vec<MovedFrame> {
+        let moved = self.layout.defragment();
+        self.upload_moved(gpu, moved)
+    }
+
+    fn upload_moved(&mut self, gpu: &mut Context, names: Vec<String>) -> Vec<MovedFrame> {
+        names
+            .into_iter()
+            .map(|name| {
+                let entry = &self.layout.entries[&name];
+                let rect = self
+                    .atlas
+                    .write_packed(gpu, &entry.bytes, entry.width, &entry.packed);
+                self.atlas
+                    .set_frame(name.clone(), entry.packed.layer, rect.clone());
+                MovedFrame {
+                    name,
+                    layer: entry.packed.layer,
+                    rect,
+                }
+            })
+            .collect()
+    }
+
+    /// Packed area over the area of the layers used, between 0 and 1
+    pub fn packing_efficiency(&self) -> f32 {
+        self.layout.packer.efficiency()
+    }
+}
+
+impl AsTextureView for AtlasCache {
+    fn as_view(&self) -> &wgpu::TextureView {
+        self.atlas.as_view()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::PackingAlgorithm;
+
+    /// One 32 by 32 layer holding four 16 by 16 frames
+    fn layout() -> CacheLayout {
+        let options = PackerOptions {
+            algorithm: PackingAlgorithm::MaxRects,
+            ..PackerOptions::default()
+        };
+        CacheLayout::new(32, 32, 1, options)
+    }
+
+    fn fill(layout: &mut CacheLayout, names: &[&str]) {
+        for name in names {
+            assert!(layout.insert(name, 16, 16, &[]).0.is_some());
+            layout.frame += 1;
+        }
+    }
+
+    #[test]
+    fn least_recently_used_frames_are_evicted_first() {
+        let mut layout = layout();
+        fill(&mut layout, &["a", "b", "c", "d"]);
+        assert!(layout.touch("a").is_some());
+        layout.frame += 1;
+
+        let (packed, evicted, moved) = layout.insert("e", 16, 16, &[]);
+        assert!(packed.is_some());
+        assert_eq!(evicted, ["b"]);
+        assert!(moved.is_empty());
+
+        let (_, evicted, _) = layout.insert("f", 16, 16, &[]);
+        assert_eq!(evicted, ["c"]);
+        assert!(layout.entries.contains_key("a"));
+    }
+
+    #[test]
+    fn frames_used_this_frame_are_kept() {
+        let mut layout = layout();
+        fill(&mut layout, &["a", "b", "c", "d"]);
+        for name in ["a", "b", "c", "d"] {
+            assert!(layout.touch(name).is_some());
+        }
+
+        let (packed, evicted, _) = layout.insert("e", 16, 16, &[]);
+        assert!(packed.is_none());
+        assert!(evicted.is_empty());
+        assert_eq!(layout.entries.len(), 4);
+
+        // Bigger than a layer, nothing is evicted for it
+        layout.frame += 1;
+        let (packed, evicted, _) = layout.insert("e", 64, 16, &[]);
+        assert!(packed.is_none());
+        assert!(evicted.is_empty());
+    }
+
+    #[test]
+    fn defragmentation_comes_after_eviction() {
+        let mut layout = layout();
+        fill(&mut layout, &["a", "b", "c", "d"]);
+        // Free two opposite corners, a 32 by 16 frame needs a whole row
+        let corners = ["a", "b", "c", "d"].map(|name| {
+            let packed = layout.entries[name].packed;
+            (name, (packed.x, packed.y))
+        });
+        for (name, position) in corners {
+            if position == (0, 0) || position == (16, 16) {
+                assert!(layout.remove(name).is_some());
+            }
+        }
+        let kept = layout.entries.keys().cloned().collect::<Vec<_>>();
+        for name in kept.iter() {
+            assert!(layout.touch(name).is_some());
+        }
+
+        let (packed, evicted, moved) = layout.insert("wide", 32, 16, &[]);
+        assert!(packed.is_some());
+        assert!(evicted.is_empty());
+        assert!(!moved.is_empty());
+        assert!(moved.iter().all(|name| kept.contains(name)));
+        assert_eq!(layout.entries.len(), 3);
+    }
+
+    #[test]
+    fn replaced_frames_are_freed_after_placing() {
+        let mut layout = layout();
+        fill(&mut layout, &["a", "b", "c", "d"]);
+        let old = layout.entries["a"].packed;
+
+        // No room without evicting, and `a` is not evicted for itself
+        for name in ["b", "c", "d"] {
+            assert!(layout.touch(name).is_some());
+        }
+        let (packed, evicted, _) = layout.insert("a", 32, 32, &[]);
+        assert!(packed.is_none());
+        assert!(evicted.is_empty());
+        assert_eq!(layout.entries["a"].packed, old);
+
+        layout.frame += 1;
+        let (packed, evicted, _) = layout.insert("a", 16, 16, &[]);
+        assert_eq!(evicted.len(), 1);
+        assert_ne!(evicted[0], "a");
+        assert_eq!(layout.entries["a"].packed, packed.unwrap());
+        assert_eq!(layout.entries.len(), 3);
+    }
+}
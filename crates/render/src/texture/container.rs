@@ -0,0 +1,631 @@
+use super::{
+    checked_texture_byte_size, decompress_image, decompressed_format, texture_byte_size,
+    TextureDescriptor,
+};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xab, b'K', b'T', b'X', b' ', b'2', b'0', 0xbb, b'\r', b'\n', 0x1a, b'\n',
+];
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+#[derive(Debug)]
+pub enum TextureFileError {
+    Io(std::io::Error),
+    /// Neither a KTX2 nor a DDS file
+    UnknownContainer,
+    /// Truncated or inconsistent file
+    Invalid(&'static str),
+    /// Pixel format without a `wgpu` equivalent, by its Vulkan, DXGI or
+    /// FourCC code
+    UnsupportedFormat(u32),
+    /// Zstd, zlib or BasisLZ supercompressed KTX2 file
+    UnsupportedSupercompression(u32),
+    /// The device lacks the features of a format which cannot be decompressed
+    /// on the CPU
+    MissingFeatures {
+        format: wgpu::TextureFormat,
+        features: wgpu::Features,
+    },
+}
+
+impl fmt::Display for TextureFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureFileError::Io(error) => write!(f, "{error}"),
+            TextureFileError::UnknownContainer => write!(f, "not a KTX2 or DDS file"),
+            TextureFileError::Invalid(message) => write!(f, "invalid texture file: {message}"),
+            TextureFileError::UnsupportedFormat(code) => {
+                write!(f, "unsupported pixel format {code:#x}")
+            }
+            TextureFileError::UnsupportedSupercompression(scheme) => {
+                write!(f, "unsupported KTX2 supercompression scheme {scheme}")
+            }
+            TextureFileError::MissingFeatures { format, features } => {
+                write!(f, "{format:?} needs the {features:?} device features")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureFileError {}
+
+impl From<std::io::Error> for TextureFileError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Texture read from a KTX2 or DDS file, with its mip chain, array layers and
+/// cube faces as stored.
+#[derive(Debug, Clone)]
+pub struct TextureFile {
+    pub format: wgpu::TextureFormat,
+    pub dimension: wgpu::TextureDimension,
+    pub width: u32,
+    pub height: u32,
+    /// Depth of 3D textures, 1 otherwise
+    pub depth: u32,
+    /// Array layers, 1 when the texture is not an array
+    pub layer_count: u32,
+    /// 6 for cube maps, 1 otherwise
+    pub face_count: u32,
+    /// Levels from the base one. Each level holds every layer, face by face,
+    /// then every depth slice, with tightly packed rows of texels or blocks.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl TextureFile {
+    pub fn load<P>(path: P) -> Result<Self, TextureFileError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_memory(&std::fs::read(path)?)
+    }
+
+    /// KTX2 or DDS file, told apart by their identifier
+    pub fn from_memory(bytes: &[u8]) -> Result<Self, TextureFileError> {
+        if bytes.starts_with(&KTX2_IDENTIFIER) {
+            Self::from_ktx2(bytes)
+        } else if bytes.starts_with(DDS_MAGIC) {
+            Self::from_dds(bytes)
+        } else {
+            Err(TextureFileError::UnknownContainer)
+        }
+    }
+
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, TextureFileError> {
+        let reader = Reader(bytes);
+        if !bytes.starts_with(&KTX2_IDENTIFIER) {
+            return Err(TextureFileError::UnknownContainer);
+        }
+        let vk_format = reader.u32(12)?;
+        let width = reader.u32(20)?;
+        let height = reader.u32(24)?;
+        let depth = reader.u32(28)?;
+        let layer_count = reader.u32(32)?.max(1);
+        let face_count = reader.u32(36)?;
+        // No level means the mip chain is to be generated
+        let level_count = reader.u32(40)?.max(1);
+        let supercompression = reader.u32(44)?;
+
+        if supercompression != 0 {
+            return Err(TextureFileError::UnsupportedSupercompression(
+                supercompression,
+            ));
+        }
+        let format =
+            ktx2_format(vk_format).ok_or(TextureFileError::UnsupportedFormat(vk_format))?;
+        let dimension = if depth > 0 {
+            wgpu::TextureDimension::D3
+        } else if height > 0 {
+            wgpu::TextureDimension::D2
+        } else {
+            wgpu::TextureDimension::D1
+        };
+
+        let mut file = Self {
+            format,
+            dimension,
+            width,
+            height: height.max(1),
+            depth: depth.max(1),
+            layer_count,
+            face_count,
+            levels: Vec::new(),
+        };
+        // The level count is only trusted once validated
+        file.validate(level_count)?;
+        file.levels.reserve(level_count as usize);
+
+        for level in 0..level_count as usize {
+            let offset = reader.u64(80 + level * 24)?;
+            let length = reader.u64(88 + level * 24)?;
+            if length != file.level_byte_size(level as u32) {
+                return Err(TextureFileError::Invalid(
+                    "level size does not match the format",
+                ));
+            }
+            let data = reader.bytes(offset, length)?;
+            file.levels.push(data.to_vec());
+        }
+        Ok(file)
+    }
+
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, TextureFileError> {
+        const MIPMAP_COUNT: u32 = 0x20000;
+        const CUBE_MAP: u32 = 0x200;
+        const CUBE_MAP_FACES: u32 = 0xfc00;
+        const VOLUME: u32 = 0x200000;
+        const DX10_MISC_CUBE_MAP: u32 = 0x4;
+
+        let reader = Reader(bytes);
+        if !bytes.starts_with(DDS_MAGIC) {
+            return Err(TextureFileError::UnknownContainer);
+        }
+        if reader.u32(4)? != 124 {
+            return Err(TextureFileError::Invalid("bad DDS header size"));
+        }
+        let flags = reader.u32(8)?;
+        let height = reader.u32(12)?.max(1);
+        let width = reader.u32(16)?;
+        let depth = reader.u32(24)?;
+        let level_count = if flags & MIPMAP_COUNT != 0 {
+            reader.u32(28)?.max(1)
+        } else {
+            1
+        };
+        let four_cc = reader.u32(84)?;
+        let caps2 = reader.u32(112)?;
+
+        let (format, dimension, layer_count, cube_map, mut offset) =
+            if &four_cc.to_le_bytes() == b"DX10" {
+                let dxgi_format = reader.u32(128)?;
+                let format = dds_dxgi_format(dxgi_format)
+                    .ok_or(TextureFileError::UnsupportedFormat(dxgi_format))?;
+                let dimension = match reader.u32(132)? {
+                    2 => wgpu::TextureDimension::D1,
+                    3 => wgpu::TextureDimension::D2,
+                    4 => wgpu::TextureDimension::D3,
+                    _ => return Err(TextureFileError::Invalid("unknown DDS resource dimension")),
+                };
+                let cube_map = reader.u32(136)? & DX10_MISC_CUBE_MAP != 0;
+                let layer_count = reader.u32(140)?.max(1);
+                (format, dimension, layer_count, cube_map, 148)
+            } else {
+                let format = dds_legacy_format(&reader)?;
+                let dimension = if caps2 & VOLUME != 0 {
+                    wgpu::TextureDimension::D3
+                } else {
+                    wgpu::TextureDimension::D2
+                };
+                let cube_map = caps2 & CUBE_MAP != 0;
+                if cube_map && caps2 & CUBE_MAP_FACES != CUBE_MAP_FACES {
+                    return Err(TextureFileError::Invalid("DDS cube map without every face"));
+                }
+                (format, dimension, 1, cube_map, 128)
+            };
+
+        let mut file = Self {
+            format,
+            dimension,
+            width,
+            height,
+            depth: if dimension == wgpu::TextureDimension::D3 {
+                depth.max(1)
+            } else {
+                1
+            },
+            layer_count,
+            face_count: if cube_map { 6 } else { 1 },
+            levels: Vec::new(),
+        };
+        file.validate(level_count)?;
+        file.levels = vec![Vec::new(); level_count as usize];
+
+        // DDS stores the mip chain of each layer and face one after the other
+        for _ in 0..file.layer_count * file.face_count {
+            for (level, data) in file.levels.iter_mut().enumerate() {
+                let size = file_level_size((width, height, file.depth), dimension, level as u32);
+                let length = texture_byte_size(format, size, dimension, 1);
+                data.extend_from_slice(reader.bytes(offset as u64, length)?);
+                offset += length as usize;
+            }
+        }
+        Ok(file)
+    }
+
+    fn validate(&self, level_count: u32) -> Result<(), TextureFileError> {
+        if self.width == 0 {
+            return Err(TextureFileError::Invalid("texture without texels"));
+        }
+        if self.face_count != 1 && self.face_count != 6 {
+            return Err(TextureFileError::Invalid("face count is neither 1 nor 6"));
+        }
+        if self.face_count == 6
+            && (self.dimension != wgpu::TextureDimension::D2 || self.width != self.height)
+        {
+            return Err(TextureFileError::Invalid("cube map faces are not square"));
+        }
+        if self.dimension == wgpu::TextureDimension::D3 && self.layer_count > 1 {
+            return Err(TextureFileError::Invalid(
+                "3D texture arrays are not supported",
+            ));
+        }
+        if self.layer_count.checked_mul(self.face_count).is_none()
+            || checked_texture_byte_size(self.format, self.size(), self.dimension, 1).is_none()
+        {
+            return Err(TextureFileError::Invalid("texture is too large"));
+        }
+        if level_count
+            > self
+                .descriptor()
+                .with_mip_level_count(u32::MAX)
+                .mip_level_count(self.size())
+        {
+            return Err(TextureFileError::Invalid(
+                "more levels than the full mip chain",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_cube_map(&self) -> bool {
+        self.face_count == 6
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// Size of the base level, `depth_or_array_layers` counts cube faces
+    pub fn size(&self) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: match self.dimension {
+                wgpu::TextureDimension::D3 => self.depth,
+                _ => self.layer_count * self.face_count,
+            },
+        }
+    }
+
+    pub fn level_size(&self, level: u32) -> wgpu::Extent3d {
+        self.size()
+            .mip_level_size(level, self.dimension == wgpu::TextureDimension::D3)
+    }
+
+    fn level_byte_size(&self, level: u32) -> u64 {
+        texture_byte_size(self.format, self.level_size(level), self.dimension, 1)
+    }
+
+    /// Descriptor of a texture holding every level
+    pub fn descriptor(&self) -> TextureDescriptor {
+        TextureDescriptor::new()
+            .with_format(self.format)
+            .with_dimension(self.dimension)
+            .with_mip_level_count(self.mip_level_count())
+    }
+
+    pub fn view_dimension(&self) -> wgpu::TextureViewDimension {
+        match self.dimension {
+            wgpu::TextureDimension::D1 => wgpu::TextureViewDimension::D1,
+            wgpu::TextureDimension::D3 => wgpu::TextureViewDimension::D3,
+            wgpu::TextureDimension::D2 => match (self.is_cube_map(), self.layer_count > 1) {
+                (true, true) => wgpu::TextureViewDimension::CubeArray,
+                (true, false) => wgpu::TextureViewDimension::Cube,
+                (false, true) => wgpu::TextureViewDimension::D2Array,
+                (false, false) => wgpu::TextureViewDimension::D2,
+            },
+        }
+    }
+
+    /// Device features the format needs
+    pub fn required_features(&self) -> wgpu::Features {
+        self.format.describe().required_features
+    }
+
+    /// Whether a device with `features` can hold the texture as it is.
+    /// Compressed textures must also be 2D, with a size made of whole blocks.
+    pub fn is_supported(&self, features: wgpu::Features) -> bool {
+        let info = self.format.describe();
+        if !features.contains(info.required_features) {
+            return false;
+        }
+        !info.is_compressed()
+            || (self.dimension == wgpu::TextureDimension::D2
+                && self.width.is_multiple_of(info.block_dimensions.0 as u32)
+                && self.height.is_multiple_of(info.block_dimensions.1 as u32))
+    }
+
+    /// Same texture decoded on the CPU, see
+    /// [`decompressed_format`](super::decompressed_format). `None` when the
+    /// format cannot be decoded.
+    pub fn decompress(&self) -> Option<Self> {
+        let format = decompressed_format(self.format)?;
+        let levels = (0..self.mip_level_count())
+            .map(|level| {
+                let size = self.level_size(level);
+                let image_size = texture_byte_size(self.format, size, wgpu::TextureDimension::D2, 1)
+                    as usize
+                    / size.depth_or_array_layers as usize;
+                let image_size = image_size.max(1);
+                let mut data = Vec::new();
+                for image in self.levels[level as usize].chunks_exact(image_size) {
+                    data.extend(decompress_image(
+                        self.format,
+                        size.width,
+                        size.height,
+                        image,
+                    )?);
+                }
+                Some(data)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            format,
+            levels,
+            ..self.clone_header()
+        })
+    }
+
+    fn clone_header(&self) -> Self {
+        Self {
+            levels: Vec::new(),
+            ..*self
+        }
+    }
+
+    /// The texture itself when a device with `features` supports it,
+    /// decompressed on the CPU otherwise
+    pub fn for_features(&self, features: wgpu::Features) -> Result<Cow<'_, Self>, TextureFileError> {
+        if self.is_supported(features) {
+            return Ok(Cow::Borrowed(self));
+        }
+        self.decompress()
+            .map(Cow::Owned)
+            .ok_or(TextureFileError::MissingFeatures {
+                format: self.format,
+                features: self.required_features() - features,
+            })
+    }
+}
+
+fn file_level_size(
+    (width, height, depth): (u32, u32, u32),
+    dimension: wgpu::TextureDimension,
+    level: u32,
+) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: depth,
+    }
+    .mip_level_size(level, dimension == wgpu::TextureDimension::D3)
+}
+
+/// Little endian reads failing on truncated files
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: u64, length: u64) -> Result<&'a [u8], TextureFileError> {
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .and_then(|(offset, length)| self.0.get(offset..offset.checked_add(length)?))
+            .ok_or(TextureFileError::Invalid("unexpected end of file"))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, TextureFileError> {
+        let bytes = self.bytes(offset as u64, 4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, TextureFileError> {
+        let bytes = self.bytes(offset as u64, 8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+/// `wgpu` format of a `VkFormat`
+fn ktx2_format(vk_format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match vk_format {
+        9 => R8Unorm,
+        10 => R8Snorm,
+        16 => Rg8Unorm,
+        17 => Rg8Snorm,
+        37 => Rgba8Unorm,
+        38 => Rgba8Snorm,
+        43 => Rgba8UnormSrgb,
+        44 => Bgra8Unorm,
+        50 => Bgra8UnormSrgb,
+        64 => Rgb10a2Unorm,
+        76 => R16Float,
+        83 => Rg16Float,
+        91 => Rgba16Unorm,
+        97 => Rgba16Float,
+        100 => R32Float,
+        103 => Rg32Float,
+        109 => Rgba32Float,
+        122 => Rg11b10Float,
+        123 => Rgb9e5Ufloat,
+        131 | 133 => Bc1RgbaUnorm,
+        132 | 134 => Bc1RgbaUnormSrgb,
+        135 => Bc2RgbaUnorm,
+        136 => Bc2RgbaUnormSrgb,
+        137 => Bc3RgbaUnorm,
+        138 => Bc3RgbaUnormSrgb,
+        139 => Bc4RUnorm,
+        140 => Bc4RSnorm,
+        141 => Bc5RgUnorm,
+        142 => Bc5RgSnorm,
+        143 => Bc6hRgbUfloat,
+        144 => Bc6hRgbSfloat,
+        145 => Bc7RgbaUnorm,
+        146 => Bc7RgbaUnormSrgb,
+        147 => Etc2Rgb8Unorm,
+        148 => Etc2Rgb8UnormSrgb,
+        149 => Etc2Rgb8A1Unorm,
+        150 => Etc2Rgb8A1UnormSrgb,
+        151 => Etc2Rgba8Unorm,
+        152 => Etc2Rgba8UnormSrgb,
+        153 => EacR11Unorm,
+        154 => EacR11Snorm,
+        155 => EacRg11Unorm,
+        156 => EacRg11Snorm,
+        157..=184 => {
+            let channel = if vk_format % 2 == 1 {
+                wgpu::AstcChannel::Unorm
+            } else {
+                wgpu::AstcChannel::UnormSrgb
+            };
+            astc_format((vk_format - 157) / 2, channel)
+        }
+        // VK_EXT_texture_compression_astc_hdr
+        1000066000..=1000066013 => astc_format(vk_format - 1000066000, wgpu::AstcChannel::Hdr),
+        _ => return None,
+    })
+}
+
+fn astc_format(block: u32, channel: wgpu::AstcChannel) -> wgpu::TextureFormat {
+    use wgpu::AstcBlock::*;
+    let block = [
+        B4x4, B5x4, B5x5, B6x5, B6x6, B8x5, B8x6, B8x8, B10x5, B10x6, B10x8, B10x10, B12x10, B12x12,
+    ][block as usize];
+    wgpu::TextureFormat::Astc { block, channel }
+}
+
+/// `wgpu` format of a `DXGI_FORMAT`
+fn dds_dxgi_format(dxgi_format: u32) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match dxgi_format {
+        2 => Rgba32Float,
+        10 => Rgba16Float,
+        11 => Rgba16Unorm,
+        24 => Rgb10a2Unorm,
+        26 => Rg11b10Float,
+        28 => Rgba8Unorm,
+        29 => Rgba8UnormSrgb,
+        31 => Rgba8Snorm,
+        34 => Rg16Float,
+        41 => R32Float,
+        49 => Rg8Unorm,
+        51 => Rg8Snorm,
+        54 => R16Float,
+        61 => R8Unorm,
+        63 => R8Snorm,
+        67 => Rgb9e5Ufloat,
+        71 => Bc1RgbaUnorm,
+        72 => Bc1RgbaUnormSrgb,
+        74 => Bc2RgbaUnorm,
+        75 => Bc2RgbaUnormSrgb,
+        77 => Bc3RgbaUnorm,
+        78 => Bc3RgbaUnormSrgb,
+        80 => Bc4RUnorm,
+        81 => Bc4RSnorm,
+        83 => Bc5RgUnorm,
+        84 => Bc5RgSnorm,
+        87 => Bgra8Unorm,
+        91 => Bgra8UnormSrgb,
+        95 => Bc6hRgbUfloat,
+        96 => Bc6hRgbSfloat,
+        98 => Bc7RgbaUnorm,
+        99 => Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+/// Format of a DDS file without the DX10 header, from its pixel format
+fn dds_legacy_format(reader: &Reader) -> Result<wgpu::TextureFormat, TextureFileError> {
+    use wgpu::TextureFormat::*;
+    const FOUR_CC: u32 = 0x4;
+    const RGB: u32 = 0x40;
+    const LUMINANCE: u32 = 0x20000;
+
+    let flags = reader.u32(80)?;
+    let four_cc = reader.u32(84)?;
+    let bit_count = reader.u32(88)?;
+    let masks = [
+        reader.u32(92)?,
+        reader.u32(96)?,
+        reader.u32(100)?,
+        reader.u32(104)?,
+    ];
+    let format = if flags & FOUR_CC != 0 {
+        match &four_cc.to_le_bytes() {
+            b"DXT1" => Some(Bc1RgbaUnorm),
+            b"DXT2" | b"DXT3" => Some(Bc2RgbaUnorm),
+            b"DXT4" | b"DXT5" => Some(Bc3RgbaUnorm),
+            b"ATI1" | b"BC4U" => Some(Bc4RUnorm),
+            b"BC4S" => Some(Bc4RSnorm),
+            b"ATI2" | b"BC5U" => Some(Bc5RgUnorm),
+            b"BC5S" => Some(Bc5RgSnorm),
+            // D3DFMT_A16B16G16R16F and D3DFMT_A32B32G32R32F
+            _ if four_cc == 113 => Some(Rgba16Float),
+            _ if four_cc == 116 => Some(Rgba32Float),
+            _ => None,
+        }
+    } else if flags & RGB != 0 && bit_count == 32 {
+        match masks {
+            [0xff, 0xff00, 0xff0000, 0xff000000] => Some(Rgba8Unorm),
+            [0xff0000, 0xff00, 0xff, 0xff000000] => Some(Bgra8Unorm),
+            _ => None,
+        }
+    } else if flags & LUMINANCE != 0 && bit_count == 8 {
+        Some(R8Unorm)
+    } else {
+        None
+    };
+    format.ok_or(TextureFileError::UnsupportedFormat(four_cc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// KTX2 header of a 2D Rgba8Unorm texture, without level index
+    fn ktx2_header(width: u32, height: u32, level_count: u32) -> Vec<u8> {
+        let mut bytes = KTX2_IDENTIFIER.to_vec();
+        for value in [37, 1, width, height, 0, 0, 1, level_count, 0] {
+            bytes.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        bytes.resize(80, 0);
+        bytes
+    }
+
+    fn invalid(bytes: &[u8]) -> &'static str {
+        match TextureFile::from_memory(bytes) {
+            Err(TextureFileError::Invalid(message)) => message,
+            other => panic!("expected an invalid file, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn level_count_is_validated_before_allocating() {
+        let bytes = ktx2_header(4, 4, u32::MAX);
+        assert_eq!(invalid(&bytes), "more levels than the full mip chain");
+    }
+
+    #[test]
+    fn oversized_textures_are_rejected() {
+        let bytes = ktx2_header(u32::MAX, 1, 1);
+        assert_eq!(invalid(&bytes), "texture is too large");
+    }
+
+    #[test]
+    fn single_level_texture() {
+        let mut bytes = ktx2_header(2, 2, 1);
+        bytes.extend_from_slice(&u64::to_le_bytes(104));
+        bytes.extend_from_slice(&u64::to_le_bytes(16));
+        bytes.extend_from_slice(&u64::to_le_bytes(16));
+        bytes.extend((0..16).map(|value| value as u8));
+
+        let file = TextureFile::from_memory(&bytes).unwrap();
+        assert_eq!(file.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!((file.width, file.height, file.mip_level_count()), (2, 2, 1));
+        assert_eq!(file.levels, vec![(0..16).collect::<Vec<u8>>()]);
+    }
+}
@@ -0,0 +1,263 @@
+use super::{AsTextureView, MipmapGenerator, RawTexture, Sampler, SamplerDescriptor};
+use crate::context::Context;
+use crate::pipeline::RenderPipeline;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+/// Cube map faces unfolded into a single image. Faces are stored in cube
+/// layers in the +X, -X, +Y, -Y, +Z, -Z order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeCross {
+    /// Four faces wide and three high, -X +Z +X -Z in the middle row
+    Horizontal,
+    /// Three faces wide and four high, -Z upside down in the bottom row
+    Vertical,
+}
+
+impl CubeCross {
+    /// Layout of a `width` by `height` image, `None` when it is neither
+    pub fn detect(width: u32, height: u32) -> Option<Self> {
+        if width > 0 && width.is_multiple_of(4) && width / 4 * 3 == height {
+            Some(Self::Horizontal)
+        } else if width > 0 && width.is_multiple_of(3) && width / 3 * 4 == height {
+            Some(Self::Vertical)
+        } else {
+            None
+        }
+    }
+
+    /// Width and height of a face
+    pub fn face_size(&self, width: u32) -> u32 {
+        match self {
+            Self::Horizontal => width / 4,
+            Self::Vertical => width / 3,
+        }
+    }
+
+    /// Column and row of each face, in faces
+    fn positions(&self) -> [(u32, u32); 6] {
+        match self {
+            Self::Horizontal => [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)],
+            Self::Vertical => [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)],
+        }
+    }
+
+    /// Cut a `width` wide image of `texel_size` bytes texels, rows tightly
+    /// packed, into six faces
+    pub fn split(&self, width: u32, texel_size: usize, bytes: &[u8]) -> [Vec<u8>; 6] {
+        let size = self.face_size(width) as usize;
+        let row_size = width as usize * texel_size;
+        assert!(
+            bytes.len() >= row_size * size * if *self == Self::Horizontal { 3 } else { 4 },
+            "[fine::graphic::CubeCross] bytes do not match the image size."
+        );
+
+        let mut faces = self.positions().map(|(column, row)| {
+            let mut face = Vec::with_capacity(size * size * texel_size);
+            for y in 0..size {
+                let start =
+                    (row as usize * size + y) * row_size + column as usize * size * texel_size;
+                face.extend_from_slice(&bytes[start..start + size * texel_size]);
+            }
+            face
+        });
+        if *self == Self::Vertical {
+            // Turn -Z right side up
+            let face = &mut faces[5];
+            let texel_count = face.len() / texel_size;
+            for texel in 0..texel_count / 2 {
+                let other = texel_count - 1 - texel;
+                for byte in 0..texel_size {
+                    face.swap(texel * texel_size + byte, other * texel_size + byte);
+                }
+            }
+        }
+        faces
+    }
+}
+
+const EQUIRECTANGULAR_SHADER: &str = r#"
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) face: u32,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @builtin(instance_index) face: u32,
+) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    out.face = face;
+    return out;
+}
+
+// Direction of a texel of a face, from -1 to 1 across it and downwards
+fn direction(face: u32, s: f32, t: f32) -> vec3<f32> {
+    switch (face) {
+        case 0u: { return vec3<f32>(1.0, -t, -s); }
+        case 1u: { return vec3<f32>(-1.0, -t, s); }
+        case 2u: { return vec3<f32>(s, 1.0, t); }
+        case 3u: { return vec3<f32>(s, -1.0, -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.0); }
+        default: { return vec3<f32>(-s, -t, -1.0); }
+    }
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coords = in.uv * 2.0 - 1.0;
+    let dir = normalize(direction(in.face, coords.x, coords.y));
+    let pi = 3.14159265359;
+    let uv = vec2<f32>(
+        atan2(dir.z, dir.x) / (2.0 * pi) + 0.5,
+        acos(clamp(dir.y, -1.0, 1.0)) / pi,
+    );
+    return textureSampleLevel(source, source_sampler, uv, 0.0);
+}
+"#;
+
+/// Renders the faces of cube maps from equirectangular panoramas, longitude
+/// along the width and the top row looking up. Cube textures need the
+/// `RENDER_ATTACHMENT` usage, and `TEXTURE_BINDING` for their mip levels to
+/// be generated.
+#[derive(Default)]
+pub struct EquirectangularConverter {
+    pipelines: HashMap<wgpu::TextureFormat, RenderPipeline>,
+    sampler: Option<Sampler>,
+    mipmaps: MipmapGenerator,
+}
+
+impl EquirectangularConverter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pipeline(&mut self, gpu: &Context, format: wgpu::TextureFormat) -> &RenderPipeline {
+        self.pipelines.entry(format).or_insert_with(|| {
+            let shader = gpu.create_wgsl_shader(EQUIRECTANGULAR_SHADER);
+            gpu.create_pipeline()
+                .create_bind_group_layout(&[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ])
+                .into_render_pipeline(
+                    wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(format.into())],
+                    }),
+                )
+        })
+    }
+
+    /// Render the six faces of the first cube of `target` from `source`, then
+    /// fill its other mip levels
+    pub fn convert<S>(&mut self, gpu: &mut Context, source: &S, target: &RawTexture)
+    where
+        S: AsTextureView,
+    {
+        assert!(
+            target.layer_count() >= 6,
+            "[fine::graphic::EquirectangularConverter] Target is not a cube map."
+        );
+        let format = target.format();
+        // Wrap around horizontally, not over the poles
+        let sampler = self
+            .sampler
+            .get_or_insert_with(|| {
+                let descriptor = SamplerDescriptor::linear().with_address_modes(
+                    wgpu::AddressMode::Repeat,
+                    wgpu::AddressMode::ClampToEdge,
+                    wgpu::AddressMode::ClampToEdge,
+                );
+                Sampler::new(gpu, &descriptor)
+            })
+            .clone();
+
+        let pipeline = self.pipeline(gpu, format);
+        let bind_group = pipeline
+            .create_bind_group(
+                0,
+                &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source.as_view()),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler.as_raw()),
+                    },
+                ],
+            )
+            .unwrap();
+
+        for face in 0..6 {
+            let view = target.as_raw().create_view(&wgpu::TextureViewDescriptor {
+                label: Some("EquirectangularConverter::convert"),
+                format: Some(format),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: NonZeroU32::new(1),
+                base_array_layer: face,
+                array_layer_count: NonZeroU32::new(1),
+            });
+            let mut pass = gpu.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("EquirectangularConverter::convert"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pipeline.use_pass(&mut pass);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, face..face + 1);
+        }
+
+        if target.mip_level_count() > 1 {
+            self.mipmaps.generate(
+                gpu,
+                target.as_raw(),
+                format,
+                target.dimensions(),
+                6,
+                target.mip_level_count(),
+                false,
+            );
+        }
+    }
+}
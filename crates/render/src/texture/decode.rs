@@ -0,0 +1,213 @@
+use super::mipmap::{linear_to_srgb, srgb_to_linear};
+use super::TextureDescriptor;
+use image::DynamicImage;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    /// Texels do not fit the texture, such as a float image in an 8-bit atlas
+    FormatMismatch {
+        expected: wgpu::TextureFormat,
+        found: wgpu::TextureFormat,
+    },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(error) => write!(f, "{error}"),
+            ImageError::Decode(error) => write!(f, "invalid image: {error}"),
+            ImageError::FormatMismatch { expected, found } => {
+                write!(f, "expected a {expected:?} image, found {found:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<std::io::Error> for ImageError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<image::ImageError> for ImageError {
+    fn from(error: image::ImageError) -> Self {
+        Self::Decode(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOptions {
+    /// Colors are sRGB encoded, turn it off for normal maps, masks or other
+    /// data. HDR and EXR images are always linear.
+    pub srgb: bool,
+    /// Multiply colors by alpha, in linear space
+    pub premultiply_alpha: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            premultiply_alpha: false,
+        }
+    }
+}
+
+/// Image decoded into texels ready to upload.
+///
+/// 8-bit images become `Bgra8UnormSrgb`, or `Bgra8Unorm` for linear data,
+/// the channel order of [`DEFAULT_TEXTURE_FORMAT`](crate::prelude::DEFAULT_TEXTURE_FORMAT).
+/// 16-bit and float images become linear `Rgba16Float`.
+#[derive(Debug, Clone)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl DecodedImage {
+    /// Decode a PNG, JPEG, TGA, BMP, HDR or EXR file, guessing the format from
+    /// its content
+    pub fn load<P>(path: P, options: &ImageOptions) -> Result<Self, ImageError>
+    where
+        P: AsRef<Path>,
+    {
+        let image = image::io::Reader::open(path)?
+            .with_guessed_format()?
+            .decode()?;
+        Ok(Self::from_dynamic_image(&image, options))
+    }
+
+    /// Decode an encoded image held in memory
+    pub fn from_memory(bytes: &[u8], options: &ImageOptions) -> Result<Self, ImageError> {
+        let image = image::load_from_memory(bytes)?;
+        Ok(Self::from_dynamic_image(&image, options))
+    }
+
+    pub fn from_dynamic_image(image: &DynamicImage, options: &ImageOptions) -> Self {
+        let (width, height) = (image.width(), image.height());
+        match image {
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_) => {
+                let mut bytes = image.to_rgba8().into_raw();
+                for texel in bytes.chunks_exact_mut(4) {
+                    if options.premultiply_alpha {
+                        premultiply_unorm8(texel, options.srgb);
+                    }
+                    texel.swap(0, 2);
+                }
+                let descriptor =
+                    TextureDescriptor::new().with_format(wgpu::TextureFormat::Bgra8Unorm);
+                let descriptor = if options.srgb {
+                    descriptor.with_srgb()
+                } else {
+                    descriptor
+                };
+                Self {
+                    width,
+                    height,
+                    format: descriptor.format(),
+                    bytes,
+                }
+            }
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => {
+                let texels = image.to_rgba16().into_raw();
+                let texels = texels.chunks_exact(4).map(|texel| {
+                    let mut texel = [0, 1, 2, 3].map(|i| texel[i] as f32 / u16::MAX as f32);
+                    if options.srgb {
+                        for value in texel[..3].iter_mut() {
+                            *value = srgb_to_linear(*value);
+                        }
+                    }
+                    texel
+                });
+                Self::from_linear_texels(width, height, texels, options)
+            }
+            // Float images, and any layout added to `image` later
+            _ => {
+                let texels = image.to_rgba32f().into_raw();
+                let texels = texels
+                    .chunks_exact(4)
+                    .map(|texel| [texel[0], texel[1], texel[2], texel[3]]);
+                Self::from_linear_texels(width, height, texels, options)
+            }
+        }
+    }
+
+    fn from_linear_texels<I>(width: u32, height: u32, texels: I, options: &ImageOptions) -> Self
+    where
+        I: Iterator<Item = [f32; 4]>,
+    {
+        let mut bytes = Vec::with_capacity((width * height * 8) as usize);
+        for mut texel in texels {
+            if options.premultiply_alpha {
+                let alpha = texel[3];
+                for value in texel[..3].iter_mut() {
+                    *value *= alpha;
+                }
+            }
+            for value in texel {
+                bytes.extend_from_slice(&half::f16::from_f32(value).to_le_bytes());
+            }
+        }
+        Self {
+            width,
+            height,
+            format: wgpu::TextureFormat::Rgba16Float,
+            bytes,
+        }
+    }
+
+    /// Descriptor of a texture holding the image
+    pub fn descriptor(&self) -> TextureDescriptor {
+        TextureDescriptor::new().with_format(self.format)
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Fails unless the texels can be uploaded to a `format` texture as they
+    /// are. sRGB and linear variants of a format hold the same texels.
+    pub fn check_format(&self, format: wgpu::TextureFormat) -> Result<(), ImageError> {
+        let srgb = |format| {
+            TextureDescriptor::new()
+                .with_format(format)
+                .with_srgb()
+                .format()
+        };
+        if srgb(self.format) == srgb(format) {
+            Ok(())
+        } else {
+            Err(ImageError::FormatMismatch {
+                expected: format,
+                found: self.format,
+            })
+        }
+    }
+}
+
+fn premultiply_unorm8(texel: &mut [u8], srgb: bool) {
+    let alpha = texel[3] as f32 / 255.0;
+    for value in texel[..3].iter_mut() {
+        let color = *value as f32 / 255.0;
+        let color = if srgb {
+            linear_to_srgb(srgb_to_linear(color) * alpha)
+        } else {
+            color * alpha
+        };
+        *value = (color * 255.0).round() as u8;
+    }
+}
@@ -0,0 +1,657 @@
+//! ASTC LDR blocks of 2D block sizes. HDR endpoints and reserved encodings
+//! decode to the magenta error color.
+
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Quantization levels of weights and color endpoints, in increasing order
+const RANGES: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256,
+];
+
+/// Trits, quints and bits of each value of a range
+fn range_encoding(levels: u32) -> (bool, bool, u32) {
+    match levels {
+        3 => (true, false, 0),
+        5 => (false, true, 0),
+        6 => (true, false, 1),
+        10 => (false, true, 1),
+        12 => (true, false, 2),
+        20 => (false, true, 2),
+        24 => (true, false, 3),
+        40 => (false, true, 3),
+        48 => (true, false, 4),
+        80 => (false, true, 4),
+        96 => (true, false, 5),
+        160 => (false, true, 5),
+        192 => (true, false, 6),
+        _ => (false, false, levels.trailing_zeros()),
+    }
+}
+
+/// Bits used by `count` values of a range
+fn encoded_bits(levels: u32, count: u32) -> u32 {
+    match range_encoding(levels) {
+        (true, _, bits) => (8 * count).div_ceil(5) + count * bits,
+        (_, true, bits) => (7 * count).div_ceil(3) + count * bits,
+        (_, _, bits) => count * bits,
+    }
+}
+
+/// Little endian bit reader, reading zeros past the end
+struct Bits(u128);
+
+impl Bits {
+    fn get(&self, start: u32, count: u32) -> u32 {
+        if start >= 128 || count == 0 {
+            return 0;
+        }
+        ((self.0 >> start) & ((1 << count) - 1)) as u32
+    }
+}
+
+fn trits(t: u32) -> [u32; 5] {
+    let bit = |n: u32| t >> n & 1;
+    let (c, t3, t4);
+    if t >> 2 & 7 == 7 {
+        c = (t >> 5 & 7) << 2 | (t & 3);
+        t4 = 2;
+        t3 = 2;
+    } else {
+        c = t & 0x1f;
+        if t >> 5 & 3 == 3 {
+            t4 = 2;
+            t3 = bit(7);
+        } else {
+            t4 = bit(7);
+            t3 = t >> 5 & 3;
+        }
+    }
+    let cbit = |n: u32| c >> n & 1;
+    let (t0, t1, t2);
+    if c & 3 == 3 {
+        t2 = 2;
+        t1 = cbit(4);
+        t0 = cbit(3) << 1 | (cbit(2) & !cbit(3) & 1);
+    } else if c >> 2 & 3 == 3 {
+        t2 = 2;
+        t1 = 2;
+        t0 = c & 3;
+    } else {
+        t2 = cbit(4);
+        t1 = c >> 2 & 3;
+        t0 = cbit(1) << 1 | (cbit(0) & !cbit(1) & 1);
+    }
+    [t0, t1, t2, t3, t4]
+}
+
+fn quints(q: u32) -> [u32; 3] {
+    let bit = |n: u32| q >> n & 1;
+    if q >> 1 & 3 == 3 && q >> 5 & 3 == 0 {
+        let q2 = bit(0) << 2 | (bit(4) & !bit(0) & 1) << 1 | (bit(3) & !bit(0) & 1);
+        return [4, 4, q2];
+    }
+    let (c, q2);
+    if q >> 1 & 3 == 3 {
+        q2 = 4;
+        c = (q >> 3 & 3) << 3 | (!(q >> 5) & 3) << 1 | bit(0);
+    } else {
+        q2 = q >> 5 & 3;
+        c = q & 0x1f;
+    }
+    if c & 7 == 5 {
+        [c >> 3 & 3, 4, q2]
+    } else {
+        [c & 7, c >> 3 & 3, q2]
+    }
+}
+
+/// Integer sequence of `count` values of a range starting at bit `start`.
+/// Each value is its bits and its trit or quint.
+fn decode_integers(bits: &Bits, start: u32, levels: u32, count: usize) -> Vec<(u32, u32)> {
+    let (has_trits, has_quints, width) = range_encoding(levels);
+    let mut values = Vec::with_capacity(count);
+    let mut position = start;
+    let mut read = |count: u32| {
+        let value = bits.get(position, count);
+        position += count;
+        value
+    };
+    while values.len() < count {
+        if has_trits {
+            let mut m = [0; 5];
+            let mut t = 0;
+            for (i, (shift, size)) in [(0, 2), (2, 2), (4, 1), (5, 2), (7, 1)]
+                .into_iter()
+                .enumerate()
+            {
+                m[i] = read(width);
+                t |= read(size) << shift;
+            }
+            for (m, t) in m.into_iter().zip(trits(t)) {
+                values.push((m, t));
+            }
+        } else if has_quints {
+            let mut m = [0; 3];
+            let mut q = 0;
+            for (i, (shift, size)) in [(0, 3), (3, 2), (5, 2)].into_iter().enumerate() {
+                m[i] = read(width);
+                q |= read(size) << shift;
+            }
+            for (m, q) in m.into_iter().zip(quints(q)) {
+                values.push((m, q));
+            }
+        } else {
+            values.push((read(width), 0));
+        }
+    }
+    values.truncate(count);
+    values
+}
+
+/// Color endpoint value to `0..=255`
+fn unquantize_color(levels: u32, (m, d): (u32, u32)) -> u32 {
+    let (has_trits, has_quints, width) = range_encoding(levels);
+    if !has_trits && !has_quints {
+        // Replicate the bits up to 8 bits
+        let mut value = 0;
+        let mut shift = 8i32 - width as i32;
+        while shift > -(width as i32) {
+            value |= if shift >= 0 { m << shift } else { m >> -shift };
+            shift -= width as i32;
+        }
+        return value & 0xff;
+    }
+    let a = if m & 1 == 1 { 0x1ff } else { 0 };
+    let bit = |n: u32| m >> n & 1;
+    let (b, c) = if has_trits {
+        match width {
+            1 => (0, 204),
+            2 => {
+                let b = bit(1);
+                (b << 8 | b << 4 | b << 2 | b << 1, 93)
+            }
+            3 => {
+                let cb = m >> 1 & 3;
+                (cb << 7 | cb << 2 | cb, 44)
+            }
+            4 => {
+                let dcb = m >> 1 & 7;
+                (dcb << 6 | dcb, 22)
+            }
+            5 => {
+                let edcb = m >> 1 & 0xf;
+                (edcb << 5 | edcb >> 2, 11)
+            }
+            _ => {
+                let fedcb = m >> 1 & 0x1f;
+                (fedcb << 4 | fedcb >> 4, 5)
+            }
+        }
+    } else {
+        match width {
+            1 => (0, 113),
+            2 => {
+                let b = bit(1);
+                (b << 8 | b << 3 | b << 2, 54)
+            }
+            3 => {
+                let cb = m >> 1 & 3;
+                (cb << 7 | cb << 1 | cb >> 1, 26)
+            }
+            4 => {
+                let dcb = m >> 1 & 7;
+                (dcb << 6 | dcb >> 1, 13)
+            }
+            _ => {
+                let edcb = m >> 1 & 0xf;
+                (edcb << 5 | edcb >> 3, 6)
+            }
+        }
+    };
+    let t = (d * c + b) ^ a;
+    (a & 0x80) | (t >> 2)
+}
+
+/// Weight value to `0..=64`
+fn unquantize_weight(levels: u32, (m, d): (u32, u32)) -> u32 {
+    let (has_trits, has_quints, width) = range_encoding(levels);
+    let value = if !has_trits && !has_quints {
+        let mut value = 0;
+        let mut shift = 6i32 - width as i32;
+        while shift > -(width as i32) {
+            value |= if shift >= 0 { m << shift } else { m >> -shift };
+            shift -= width as i32;
+        }
+        value & 0x3f
+    } else if width == 0 {
+        if has_trits {
+            [0, 32, 63][d as usize]
+        } else {
+            [0, 16, 32, 47, 63][d as usize]
+        }
+    } else {
+        let a = if m & 1 == 1 { 0x7f } else { 0 };
+        let (b, c) = match (has_trits, width) {
+            (true, 1) => (0, 50),
+            (true, 2) => {
+                let b = m >> 1 & 1;
+                (b << 6 | b << 2 | b, 23)
+            }
+            (true, _) => {
+                let cb = m >> 1 & 3;
+                (cb << 5 | cb, 11)
+            }
+            (false, 1) => (0, 28),
+            (false, _) => {
+                let b = m >> 1 & 1;
+                (b << 6 | b << 1 | b, 13)
+            }
+        };
+        let t = (d * c + b) ^ a;
+        (a & 0x20) | (t >> 2)
+    };
+    if value > 32 {
+        value + 1
+    } else {
+        value
+    }
+}
+
+struct BlockMode {
+    width: u32,
+    height: u32,
+    dual_plane: bool,
+    levels: u32,
+}
+
+fn decode_block_mode(mode: u32) -> Option<BlockMode> {
+    let bit = |n: u32| mode >> n & 1;
+    let a = mode >> 5 & 3;
+    let b = mode >> 7 & 3;
+    let (width, height, r, high, dual_plane);
+    if mode & 3 != 0 {
+        r = (mode & 3) << 1 | bit(4);
+        high = bit(9);
+        dual_plane = bit(10) == 1;
+        (width, height) = match mode >> 2 & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if bit(8) == 0 => (a + 2, bit(7) + 6),
+            _ => (bit(7) + 2, a + 2),
+        };
+    } else {
+        r = (mode >> 2 & 3) << 1 | bit(4);
+        if r < 2 {
+            return None;
+        }
+        match b {
+            0 => {
+                (width, height) = (12, a + 2);
+                high = bit(9);
+                dual_plane = bit(10) == 1;
+            }
+            1 => {
+                (width, height) = (a + 2, 12);
+                high = bit(9);
+                dual_plane = bit(10) == 1;
+            }
+            2 => {
+                (width, height) = (a + 6, (mode >> 9 & 3) + 6);
+                high = 0;
+                dual_plane = false;
+            }
+            _ => {
+                (width, height) = match a {
+                    0 => (6, 10),
+                    1 => (10, 6),
+                    _ => return None,
+                };
+                high = bit(9);
+                dual_plane = bit(10) == 1;
+            }
+        }
+    }
+    if r < 2 {
+        return None;
+    }
+    let levels = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32][(high * 6 + r - 2) as usize];
+    Some(BlockMode {
+        width,
+        height,
+        dual_plane,
+        levels,
+    })
+}
+
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+fn select_partition(seed: u32, x: u32, y: u32, partition_count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+    let seed = seed + (partition_count - 1) * 1024;
+    let rnum = hash52(seed);
+    let mut seeds = [
+        rnum & 0xf,
+        rnum >> 4 & 0xf,
+        rnum >> 8 & 0xf,
+        rnum >> 12 & 0xf,
+        rnum >> 16 & 0xf,
+        rnum >> 20 & 0xf,
+        rnum >> 24 & 0xf,
+        rnum >> 28 & 0xf,
+    ];
+    for seed in seeds.iter_mut() {
+        *seed *= *seed;
+    }
+    let (sh1, sh2) = if seed & 1 == 1 {
+        (
+            if seed & 2 == 2 { 4 } else { 5 },
+            if partition_count == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if partition_count == 3 { 6 } else { 5 },
+            if seed & 2 == 2 { 4 } else { 5 },
+        )
+    };
+    for (i, seed) in seeds.iter_mut().enumerate() {
+        *seed >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+    // The z terms of 3D blocks vanish in 2D
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3f;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3f;
+    let mut c = (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3f;
+    let mut d = (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3f;
+    if partition_count < 4 {
+        d = 0;
+    }
+    if partition_count < 3 {
+        c = 0;
+    }
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// Returns the base and the signed offset
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let b = (b >> 1) | (a & 0x80);
+    let mut a = (a >> 1) & 0x3f;
+    if a & 0x20 != 0 {
+        a -= 0x40;
+    }
+    (b, a)
+}
+
+fn blue_contract([r, g, b, a]: [i32; 4]) -> [i32; 4] {
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+/// Endpoints of an LDR color endpoint mode, `None` for HDR modes
+fn decode_endpoints(mode: u32, v: &[i32]) -> Option<[[i32; 4]; 2]> {
+    let endpoints = match mode {
+        0 => [[v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]],
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xc0);
+            let l1 = (l0 + (v[1] & 0x3f)).min(255);
+            [[l0, l0, l0, 255], [l1, l1, l1, 255]]
+        }
+        4 => [[v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]],
+        5 => {
+            let (l, dl) = bit_transfer_signed(v[1], v[0]);
+            let (a, da) = bit_transfer_signed(v[3], v[2]);
+            [[l, l, l, a], [l + dl, l + dl, l + dl, a + da]]
+        }
+        6 => [
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                255,
+            ],
+            [v[0], v[1], v[2], 255],
+        ],
+        8 | 12 => {
+            let (a0, a1) = if mode == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                [[v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1]]
+            } else {
+                [
+                    blue_contract([v[1], v[3], v[5], a1]),
+                    blue_contract([v[0], v[2], v[4], a0]),
+                ]
+            }
+        }
+        9 | 13 => {
+            let (r, dr) = bit_transfer_signed(v[1], v[0]);
+            let (g, dg) = bit_transfer_signed(v[3], v[2]);
+            let (b, db) = bit_transfer_signed(v[5], v[4]);
+            let (a, da) = if mode == 13 {
+                bit_transfer_signed(v[7], v[6])
+            } else {
+                (255, 0)
+            };
+            if dr + dg + db >= 0 {
+                [[r, g, b, a], [r + dr, g + dg, b + db, a + da]]
+            } else {
+                [
+                    blue_contract([r + dr, g + dg, b + db, a + da]),
+                    blue_contract([r, g, b, a]),
+                ]
+            }
+        }
+        10 => [
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                v[4],
+            ],
+            [v[0], v[1], v[2], v[5]],
+        ],
+        _ => return None,
+    };
+    Some(endpoints.map(|endpoint| endpoint.map(|value| value.clamp(0, 255))))
+}
+
+/// Decode a block of `block_width` by `block_height` texels into RGBA texels
+pub(super) fn decode_astc(
+    block: &[u8],
+    (block_width, block_height): (u32, u32),
+    srgb: bool,
+    texels: &mut [u8],
+) {
+    if decode_block(block, (block_width, block_height), srgb, texels).is_none() {
+        for texel in texels.chunks_exact_mut(4) {
+            texel.copy_from_slice(&ERROR_COLOR);
+        }
+    }
+}
+
+fn decode_block(
+    block: &[u8],
+    (block_width, block_height): (u32, u32),
+    srgb: bool,
+    texels: &mut [u8],
+) -> Option<()> {
+    let bits = Bits(u128::from_le_bytes(block.try_into().unwrap()));
+
+    // Void extent, a constant color
+    if bits.get(0, 9) == 0x1fc {
+        if bits.get(9, 1) == 1 {
+            return None;
+        }
+        let color = [0, 1, 2, 3].map(|channel| (bits.get(64 + channel * 16, 16) >> 8) as u8);
+        for texel in texels.chunks_exact_mut(4) {
+            texel.copy_from_slice(&color);
+        }
+        return Some(());
+    }
+
+    let mode = decode_block_mode(bits.get(0, 11))?;
+    let partition_count = bits.get(11, 2) + 1;
+    let plane_count = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = (mode.width * mode.height * plane_count) as usize;
+    if mode.width > block_width
+        || mode.height > block_height
+        || weight_count > 64
+        || (mode.dual_plane && partition_count == 4)
+    {
+        return None;
+    }
+    let weight_bits = encoded_bits(mode.levels, weight_count as u32);
+    if !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+
+    // Color endpoint modes
+    let mut modes = [0u32; 4];
+    let mut below_weights = 128 - weight_bits;
+    let color_start;
+    if partition_count == 1 {
+        modes[0] = bits.get(13, 4);
+        color_start = 17;
+    } else {
+        let mut encoded = bits.get(23, 6);
+        if encoded & 3 == 0 {
+            modes = [encoded >> 2 & 0xf; 4];
+        } else {
+            let extra_bits = 3 * partition_count - 4;
+            below_weights -= extra_bits;
+            encoded |= bits.get(below_weights, extra_bits) << 6;
+            let base_class = (encoded & 3) - 1;
+            for (partition, mode) in modes[..partition_count as usize].iter_mut().enumerate() {
+                let class = base_class + (encoded >> (2 + partition) & 1);
+                let low = encoded >> (2 + partition_count as usize + 2 * partition) & 3;
+                *mode = class << 2 | low;
+            }
+        }
+        color_start = 29;
+    }
+    let plane_channel = if mode.dual_plane {
+        below_weights -= 2;
+        Some(bits.get(below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    // Color endpoints, with the highest range fitting the room left
+    let value_count: u32 = modes[..partition_count as usize]
+        .iter()
+        .map(|mode| 2 * (mode / 4 + 1))
+        .sum();
+    if value_count > 18 || below_weights < color_start {
+        return None;
+    }
+    let color_bits = below_weights - color_start;
+    let color_levels = *RANGES
+        .iter()
+        .rev()
+        .find(|levels| encoded_bits(**levels, value_count) <= color_bits)?;
+    if color_levels < 6 {
+        return None;
+    }
+    let values = decode_integers(&bits, color_start, color_levels, value_count as usize)
+        .into_iter()
+        .map(|value| unquantize_color(color_levels, value) as i32)
+        .collect::<Vec<_>>();
+    let mut endpoints = [[[0i32; 4]; 2]; 4];
+    let mut offset = 0;
+    for (partition, mode) in modes[..partition_count as usize].iter().enumerate() {
+        let count = (2 * (mode / 4 + 1)) as usize;
+        endpoints[partition] = decode_endpoints(*mode, &values[offset..offset + count])?;
+        offset += count;
+    }
+
+    // Weights are stored backwards from the end of the block
+    let reversed = Bits(bits.0.reverse_bits());
+    let mut weights = decode_integers(&reversed, 0, mode.levels, weight_count)
+        .into_iter()
+        .map(|value| unquantize_weight(mode.levels, value))
+        .collect::<Vec<_>>();
+    // Room for the infill reading past the last row and column
+    weights.resize(
+        weight_count + (mode.width as usize + 1) * plane_count as usize,
+        0,
+    );
+
+    let seed = bits.get(13, 10);
+    let small_block = block_width * block_height < 31;
+    let ds = (1024 + block_width / 2) / (block_width - 1);
+    let dt = (1024 + block_height / 2) / (block_height - 1);
+    for y in 0..block_height {
+        for x in 0..block_width {
+            let partition = if partition_count > 1 {
+                select_partition(seed, x, y, partition_count, small_block)
+            } else {
+                0
+            };
+
+            // Bilinear infill from the weight grid
+            let gs = (ds * x * (mode.width - 1) + 32) >> 6;
+            let gt = (dt * y * (mode.height - 1) + 32) >> 6;
+            let (js, fs) = (gs >> 4, gs & 0xf);
+            let (jt, ft) = (gt >> 4, gt & 0xf);
+            let w11 = (fs * ft + 8) >> 4;
+            let w10 = ft - w11;
+            let w01 = fs - w11;
+            let w00 = 16 + w11 - fs - ft;
+            let v0 = (js + jt * mode.width) as usize;
+            let grid = mode.width as usize;
+            let weight = |plane: usize| {
+                let at = |index: usize| weights[index * plane_count as usize + plane];
+                (at(v0) * w00
+                    + at(v0 + 1) * w01
+                    + at(v0 + grid) * w10
+                    + at(v0 + grid + 1) * w11
+                    + 8)
+                    >> 4
+            };
+            let plane0 = weight(0);
+            let plane1 = if mode.dual_plane { weight(1) } else { plane0 };
+
+            let [e0, e1] = endpoints[partition];
+            let texel = &mut texels[((y * block_width + x) * 4) as usize..][..4];
+            for channel in 0..4 {
+                let weight = if plane_channel == Some(channel) {
+                    plane1
+                } else {
+                    plane0
+                } as i32;
+                let expand = |value: i32| {
+                    if srgb {
+                        value << 8 | 0x80
+                    } else {
+                        value << 8 | value
+                    }
+                };
+                let (c0, c1) = (expand(e0[channel]), expand(e1[channel]));
+                let color = (c0 * (64 - weight) + c1 * weight + 32) >> 6;
+                texel[channel] = (color >> 8) as u8;
+            }
+        }
+    }
+    Some(())
+}
@@ -0,0 +1,357 @@
+//! BC1 to BC5 and BC7 blocks, 4x4 texels
+
+/// Subsets of the 2 subsets partitions, bit `i` for texel `i`
+const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// Subsets of the 3 subsets partitions, bits `2i..2i+2` for texel `i`
+const PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// Anchor texel of the second subset of the 2 subsets partitions
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of the second and third subsets of the 3 subsets partitions
+const ANCHORS_3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6,
+        8, 5, 15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8,
+        5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3,
+        15, 6, 10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15,
+        15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+    ],
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) as u8 & 0x1f;
+    let g = (color >> 5) as u8 & 0x3f;
+    let b = color as u8 & 0x1f;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Colors of a BC1 block into RGBA texels. The 3 colors mode with
+/// transparent black is only used by BC1.
+fn decode_colors(block: &[u8], texels: &mut [u8], bc1: bool) {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let (c0, c1) = (rgb565(color0), rgb565(color1));
+    let mut palette = [
+        [c0[0], c0[1], c0[2], 255],
+        [c1[0], c1[1], c1[2], 255],
+        [0; 4],
+        [0; 4],
+    ];
+    for channel in 0..3 {
+        let (a, b) = (c0[channel] as u32, c1[channel] as u32);
+        if color0 > color1 || !bc1 {
+            palette[2][channel] = ((2 * a + b) / 3) as u8;
+            palette[3][channel] = ((a + 2 * b) / 3) as u8;
+        } else {
+            palette[2][channel] = ((a + b) / 2) as u8;
+        }
+    }
+    palette[2][3] = 255;
+    if color0 > color1 || !bc1 {
+        palette[3][3] = 255;
+    }
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (texel, color) in texels.chunks_exact_mut(4).enumerate() {
+        color.copy_from_slice(&palette[(indices >> (2 * texel) & 3) as usize]);
+    }
+}
+
+/// 8 values interpolated from two endpoints, with 3 bits indices
+fn decode_interpolated(block: &[u8], signed: bool) -> [i32; 16] {
+    let (e0, e1) = if signed {
+        let e0 = (block[0] as i8).max(-127) as i32;
+        let e1 = (block[1] as i8).max(-127) as i32;
+        (e0, e1)
+    } else {
+        (block[0] as i32, block[1] as i32)
+    };
+    let (min, max) = if signed { (-127, 127) } else { (0, 255) };
+    let mut palette = [e0, e1, 0, 0, 0, 0, min, max];
+    if e0 > e1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i as i32) * e0 + i as i32 * e1) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i as i32) * e0 + i as i32 * e1) / 5;
+        }
+    }
+
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    let mut values = [0; 16];
+    for (texel, value) in values.iter_mut().enumerate() {
+        *value = palette[(indices >> (3 * texel) & 7) as usize];
+    }
+    values
+}
+
+pub(super) fn decode_bc1(block: &[u8], texels: &mut [u8]) {
+    decode_colors(block, texels, true);
+}
+
+pub(super) fn decode_bc2(block: &[u8], texels: &mut [u8]) {
+    decode_colors(&block[8..], texels, false);
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    for (texel, color) in texels.chunks_exact_mut(4).enumerate() {
+        color[3] = (alpha >> (4 * texel) & 0xf) as u8 * 17;
+    }
+}
+
+pub(super) fn decode_bc3(block: &[u8], texels: &mut [u8]) {
+    decode_colors(&block[8..], texels, false);
+    let alpha = decode_interpolated(&block[..8], false);
+    for (color, alpha) in texels.chunks_exact_mut(4).zip(alpha) {
+        color[3] = alpha as u8;
+    }
+}
+
+/// One channel texels, signed values are stored as `i8`
+pub(super) fn decode_bc4(block: &[u8], texels: &mut [u8], signed: bool) {
+    for (texel, value) in texels.iter_mut().zip(decode_interpolated(block, signed)) {
+        *texel = value as u8;
+    }
+}
+
+/// Two channels texels, signed values are stored as `i8`
+pub(super) fn decode_bc5(block: &[u8], texels: &mut [u8], signed: bool) {
+    let red = decode_interpolated(&block[..8], signed);
+    let green = decode_interpolated(&block[8..], signed);
+    for (texel, rg) in texels.chunks_exact_mut(2).enumerate() {
+        rg[0] = red[texel] as u8;
+        rg[1] = green[texel] as u8;
+    }
+}
+
+/// Little endian bit reader over a 128 bits block
+struct Bits(u128);
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.0 & ((1 << count) - 1)) as u32;
+        self.0 >>= count;
+        value
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// One P-bit per endpoint
+    endpoint_pbits: bool,
+    /// One P-bit per subset
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const fn bc7_mode(
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    (color_bits, alpha_bits): (u32, u32),
+    (endpoint_pbits, shared_pbits): (bool, bool),
+    (index_bits, secondary_index_bits): (u32, u32),
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_pbits,
+        shared_pbits,
+        index_bits,
+        secondary_index_bits,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    bc7_mode(3, 4, 0, 0, (4, 0), (true, false), (3, 0)),
+    bc7_mode(2, 6, 0, 0, (6, 0), (false, true), (3, 0)),
+    bc7_mode(3, 6, 0, 0, (5, 0), (false, false), (2, 0)),
+    bc7_mode(2, 6, 0, 0, (7, 0), (true, false), (2, 0)),
+    bc7_mode(1, 0, 2, 1, (5, 6), (false, false), (2, 3)),
+    bc7_mode(1, 0, 2, 0, (7, 8), (false, false), (2, 2)),
+    bc7_mode(1, 0, 0, 0, (7, 7), (true, false), (4, 0)),
+    bc7_mode(2, 6, 0, 0, (5, 5), (true, false), (2, 0)),
+];
+
+fn bc7_weights(bits: u32) -> &'static [u32] {
+    match bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+/// Subset of a texel and whether it is the anchor of its subset
+fn bc7_subset(subsets: usize, partition: usize, texel: usize) -> (usize, bool) {
+    match subsets {
+        2 => {
+            let subset = (PARTITIONS_2[partition] >> texel & 1) as usize;
+            let anchor = if subset == 0 {
+                0
+            } else {
+                ANCHORS_2[partition] as usize
+            };
+            (subset, texel == anchor)
+        }
+        3 => {
+            let subset = (PARTITIONS_3[partition] >> (2 * texel) & 3) as usize;
+            let anchor = if subset == 0 {
+                0
+            } else {
+                ANCHORS_3[subset - 1][partition] as usize
+            };
+            (subset, texel == anchor)
+        }
+        _ => (0, texel == 0),
+    }
+}
+
+pub(super) fn decode_bc7(block: &[u8], texels: &mut [u8]) {
+    let mut bits = Bits(u128::from_le_bytes(block.try_into().unwrap()));
+    let mode_index = block[0].trailing_zeros() as usize;
+    if mode_index >= BC7_MODES.len() {
+        // Reserved mode, transparent black
+        texels.fill(0);
+        return;
+    }
+    let mode = &BC7_MODES[mode_index];
+    bits.read(mode_index as u32 + 1);
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // Endpoints of each subset, channel by channel
+    let mut endpoints = [[[0u32; 4]; 2]; 3];
+    for channel in 0..4 {
+        let channel_bits = if channel < 3 {
+            mode.color_bits
+        } else {
+            mode.alpha_bits
+        };
+        for subset in endpoints[..mode.subsets].iter_mut() {
+            for endpoint in subset.iter_mut() {
+                endpoint[channel] = bits.read(channel_bits);
+            }
+        }
+    }
+
+    let mut pbits = [[0u32; 2]; 3];
+    if mode.endpoint_pbits {
+        for subset in pbits[..mode.subsets].iter_mut() {
+            subset[0] = bits.read(1);
+            subset[1] = bits.read(1);
+        }
+    } else if mode.shared_pbits {
+        for subset in pbits[..mode.subsets].iter_mut() {
+            let pbit = bits.read(1);
+            *subset = [pbit, pbit];
+        }
+    }
+    let has_pbits = mode.endpoint_pbits || mode.shared_pbits;
+
+    for (subset, endpoints) in endpoints[..mode.subsets].iter_mut().enumerate() {
+        for (endpoint, color) in endpoints.iter_mut().enumerate() {
+            for (channel, value) in color.iter_mut().enumerate() {
+                let mut width = if channel < 3 {
+                    mode.color_bits
+                } else {
+                    mode.alpha_bits
+                };
+                if width == 0 {
+                    *value = 255;
+                    continue;
+                }
+                if has_pbits {
+                    *value = *value << 1 | pbits[subset][endpoint];
+                    width += 1;
+                }
+                *value = (*value << (8 - width)) | (*value >> (2 * width - 8));
+            }
+        }
+    }
+
+    let mut indices = [0u32; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        let (_, anchor) = bc7_subset(mode.subsets, partition, texel);
+        *index = bits.read(mode.index_bits - anchor as u32);
+    }
+    let mut secondary = [0u32; 16];
+    if mode.secondary_index_bits > 0 {
+        for (texel, index) in secondary.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - (texel == 0) as u32);
+        }
+    }
+
+    for (texel, color) in texels.chunks_exact_mut(4).enumerate() {
+        let (subset, _) = bc7_subset(mode.subsets, partition, texel);
+        let [e0, e1] = endpoints[subset];
+        let (color_weight, alpha_weight) = if mode.secondary_index_bits == 0 {
+            let weight = bc7_weights(mode.index_bits)[indices[texel] as usize];
+            (weight, weight)
+        } else {
+            let primary = bc7_weights(mode.index_bits)[indices[texel] as usize];
+            let secondary = bc7_weights(mode.secondary_index_bits)[secondary[texel] as usize];
+            if index_selection == 0 {
+                (primary, secondary)
+            } else {
+                (secondary, primary)
+            }
+        };
+        for channel in 0..4 {
+            let weight = if channel < 3 {
+                color_weight
+            } else {
+                alpha_weight
+            };
+            color[channel] = (((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6) as u8;
+        }
+        match rotation {
+            1 => color.swap(0, 3),
+            2 => color.swap(1, 3),
+            3 => color.swap(2, 3),
+            _ => {}
+        }
+    }
+}
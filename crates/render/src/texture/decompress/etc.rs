@@ -0,0 +1,309 @@
+//! ETC2 and EAC blocks, 4x4 texels indexed column by column
+
+const MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn extend4(value: u8) -> i32 {
+    (value as i32) * 17
+}
+
+fn extend5(value: u8) -> i32 {
+    (value << 3 | value >> 2) as i32
+}
+
+fn extend6(value: u8) -> i32 {
+    (value << 2 | value >> 4) as i32
+}
+
+fn extend7(value: u8) -> i32 {
+    (value << 1 | value >> 6) as i32
+}
+
+fn offset(base: [i32; 3], delta: i32) -> [u8; 3] {
+    base.map(|value| (value + delta).clamp(0, 255) as u8)
+}
+
+/// Signed 3 bits delta of the differential mode
+fn delta(value: u8) -> i32 {
+    ((value as i32 & 7) << 29) >> 29
+}
+
+/// Texel `(x, y)` of a block is at `y * 4 + x` in `texels` and at `x * 4 + y`
+/// in the block indices
+fn index(indices: u32, x: usize, y: usize) -> usize {
+    let bit = x * 4 + y;
+    ((indices >> (bit + 16) & 1) << 1 | indices >> bit & 1) as usize
+}
+
+/// RGB block into RGBA texels. With `punchthrough`, the differential bit
+/// tells whether the block is opaque and index 2 is transparent otherwise.
+fn decode_rgb(block: &[u8], texels: &mut [u8], punchthrough: bool) {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let indices = bits as u32;
+    let differential = block[3] & 2 != 0;
+    let opaque = !punchthrough || differential;
+
+    let mut write = |x: usize, y: usize, color: [u8; 3], transparent: bool| {
+        let texel = &mut texels[(y * 4 + x) * 4..][..4];
+        if transparent {
+            texel.fill(0);
+        } else {
+            texel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    };
+
+    if punchthrough || differential {
+        let r = (block[0] >> 3) as i32 + delta(block[0]);
+        let g = (block[1] >> 3) as i32 + delta(block[1]);
+        let b = (block[2] >> 3) as i32 + delta(block[2]);
+
+        if !(0..32).contains(&r) {
+            // T mode
+            let c1 = [
+                extend4((block[0] >> 1 & 0xc) | (block[0] & 3)),
+                extend4(block[1] >> 4),
+                extend4(block[1] & 0xf),
+            ];
+            let c2 = [
+                extend4(block[2] >> 4),
+                extend4(block[2] & 0xf),
+                extend4(block[3] >> 4),
+            ];
+            let distance = DISTANCES[((block[3] >> 1 & 6) | (block[3] & 1)) as usize];
+            let paint = [
+                offset(c1, 0),
+                offset(c2, distance),
+                offset(c2, 0),
+                offset(c2, -distance),
+            ];
+            for y in 0..4 {
+                for x in 0..4 {
+                    let index = index(indices, x, y);
+                    write(x, y, paint[index], !opaque && index == 2);
+                }
+            }
+            return;
+        }
+
+        if !(0..32).contains(&g) {
+            // H mode
+            let r1 = block[0] >> 3 & 0xf;
+            let g1 = (block[0] & 7) << 1 | (block[1] >> 4 & 1);
+            let b1 = (block[1] & 8) | (block[1] & 3) << 1 | block[2] >> 7;
+            let r2 = block[2] >> 3 & 0xf;
+            let g2 = (block[2] & 7) << 1 | block[3] >> 7;
+            let b2 = block[3] >> 3 & 0xf;
+            let c1 = [extend4(r1), extend4(g1), extend4(b1)];
+            let c2 = [extend4(r2), extend4(g2), extend4(b2)];
+            let order = ((r1 as u32) << 8 | (g1 as u32) << 4 | b1 as u32)
+                >= ((r2 as u32) << 8 | (g2 as u32) << 4 | b2 as u32);
+            let distance = DISTANCES[((block[3] & 4) | (block[3] & 1) << 1 | order as u8) as usize];
+            let paint = [
+                offset(c1, distance),
+                offset(c1, -distance),
+                offset(c2, distance),
+                offset(c2, -distance),
+            ];
+            for y in 0..4 {
+                for x in 0..4 {
+                    let index = index(indices, x, y);
+                    write(x, y, paint[index], !opaque && index == 2);
+                }
+            }
+            return;
+        }
+
+        if !(0..32).contains(&b) {
+            // Planar mode, always opaque
+            let o = [
+                extend6((bits >> 57) as u8 & 0x3f),
+                extend7(((bits >> 56 & 1) << 6 | bits >> 49 & 0x3f) as u8),
+                extend6(((bits >> 48 & 1) << 5 | (bits >> 43 & 3) << 3 | bits >> 39 & 7) as u8),
+            ];
+            let h = [
+                extend6(((bits >> 34 & 0x1f) << 1 | bits >> 32 & 1) as u8),
+                extend7((bits >> 25) as u8 & 0x7f),
+                extend6((bits >> 19) as u8 & 0x3f),
+            ];
+            let v = [
+                extend6((bits >> 13) as u8 & 0x3f),
+                extend7((bits >> 6) as u8 & 0x7f),
+                extend6(bits as u8 & 0x3f),
+            ];
+            for y in 0..4 {
+                for x in 0..4 {
+                    let color = [0, 1, 2].map(|c| {
+                        let value =
+                            (x as i32 * (h[c] - o[c]) + y as i32 * (v[c] - o[c]) + 4 * o[c] + 2)
+                                >> 2;
+                        value.clamp(0, 255) as u8
+                    });
+                    write(x, y, color, false);
+                }
+            }
+            return;
+        }
+    }
+
+    // Punchthrough blocks have no individual mode
+    let bases = if punchthrough || differential {
+        let r = block[0] >> 3;
+        let g = block[1] >> 3;
+        let b = block[2] >> 3;
+        let first = [extend5(r), extend5(g), extend5(b)];
+        let second = [
+            extend5((r as i32 + delta(block[0])) as u8),
+            extend5((g as i32 + delta(block[1])) as u8),
+            extend5((b as i32 + delta(block[2])) as u8),
+        ];
+        [first, second]
+    } else {
+        [
+            [
+                extend4(block[0] >> 4),
+                extend4(block[1] >> 4),
+                extend4(block[2] >> 4),
+            ],
+            [
+                extend4(block[0] & 0xf),
+                extend4(block[1] & 0xf),
+                extend4(block[2] & 0xf),
+            ],
+        ]
+    };
+    let tables = [block[3] >> 5, block[3] >> 2 & 7];
+    let flip = block[3] & 1 != 0;
+
+    for y in 0..4 {
+        for x in 0..4 {
+            let subblock = if flip { y >= 2 } else { x >= 2 } as usize;
+            let [a, b] = MODIFIERS[tables[subblock] as usize];
+            let index = index(indices, x, y);
+            let modifier = match (index, opaque) {
+                (0, true) => a,
+                (0, false) => 0,
+                (1, _) => b,
+                (2, _) => -a,
+                _ => -b,
+            };
+            write(
+                x,
+                y,
+                offset(bases[subblock], modifier),
+                !opaque && index == 2,
+            );
+        }
+    }
+}
+
+/// EAC block into 11 bits values, `-1023..=1023` when signed
+fn decode_eac(block: &[u8], signed: bool) -> [i32; 16] {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xf) as usize];
+    let mut values = [0; 16];
+    for y in 0..4 {
+        for x in 0..4 {
+            let index = (bits >> (45 - 3 * (x * 4 + y)) & 7) as usize;
+            let modifier = modifiers[index];
+            let modifier = if multiplier == 0 {
+                modifier
+            } else {
+                modifier * multiplier * 8
+            };
+            values[y * 4 + x] = if signed {
+                let base = (block[0] as i8).max(-127) as i32;
+                (base * 8 + modifier).clamp(-1023, 1023)
+            } else {
+                (block[0] as i32 * 8 + 4 + modifier).clamp(0, 2047)
+            };
+        }
+    }
+    values
+}
+
+/// 11 bits values as 8 bits, signed values stored as `i8`
+fn eac_to_u8(value: i32, signed: bool) -> u8 {
+    if signed {
+        ((value * 127 + value.signum() * 511) / 1023) as i8 as u8
+    } else {
+        ((value * 255 + 1023) / 2047) as u8
+    }
+}
+
+/// 8 bits alpha of an EAC block
+fn decode_alpha(block: &[u8]) -> [u8; 16] {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let modifiers = EAC_MODIFIERS[(block[1] & 0xf) as usize];
+    let mut values = [0; 16];
+    for y in 0..4 {
+        for x in 0..4 {
+            let index = (bits >> (45 - 3 * (x * 4 + y)) & 7) as usize;
+            values[y * 4 + x] = (base + modifiers[index] * multiplier).clamp(0, 255) as u8;
+        }
+    }
+    values
+}
+
+pub(super) fn decode_etc2_rgb(block: &[u8], texels: &mut [u8]) {
+    decode_rgb(block, texels, false);
+}
+
+pub(super) fn decode_etc2_rgb_a1(block: &[u8], texels: &mut [u8]) {
+    decode_rgb(block, texels, true);
+}
+
+pub(super) fn decode_etc2_rgba(block: &[u8], texels: &mut [u8]) {
+    decode_rgb(&block[8..], texels, false);
+    for (texel, alpha) in texels.chunks_exact_mut(4).zip(decode_alpha(&block[..8])) {
+        texel[3] = alpha;
+    }
+}
+
+/// One channel texels, signed values are stored as `i8`
+pub(super) fn decode_eac_r(block: &[u8], texels: &mut [u8], signed: bool) {
+    for (texel, value) in texels.iter_mut().zip(decode_eac(block, signed)) {
+        *texel = eac_to_u8(value, signed);
+    }
+}
+
+/// Two channels texels, signed values are stored as `i8`
+pub(super) fn decode_eac_rg(block: &[u8], texels: &mut [u8], signed: bool) {
+    let red = decode_eac(&block[..8], signed);
+    let green = decode_eac(&block[8..], signed);
+    for (texel, rg) in texels.chunks_exact_mut(2).enumerate() {
+        rg[0] = eac_to_u8(red[texel], signed);
+        rg[1] = eac_to_u8(green[texel], signed);
+    }
+}
@@ -0,0 +1,161 @@
+mod astc;
+mod bc;
+mod etc;
+
+/// Uncompressed format a compressed `format` is decoded to on the CPU, `None`
+/// when it cannot be. BC6H and HDR ASTC are not decoded.
+pub fn decompressed_format(format: wgpu::TextureFormat) -> Option<wgpu::TextureFormat> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        Bc1RgbaUnorm | Bc2RgbaUnorm | Bc3RgbaUnorm | Bc7RgbaUnorm | Etc2Rgb8Unorm
+        | Etc2Rgb8A1Unorm | Etc2Rgba8Unorm => Rgba8Unorm,
+        Bc1RgbaUnormSrgb | Bc2RgbaUnormSrgb | Bc3RgbaUnormSrgb | Bc7RgbaUnormSrgb
+        | Etc2Rgb8UnormSrgb | Etc2Rgb8A1UnormSrgb | Etc2Rgba8UnormSrgb => Rgba8UnormSrgb,
+        Bc4RUnorm | EacR11Unorm => R8Unorm,
+        Bc4RSnorm | EacR11Snorm => R8Snorm,
+        Bc5RgUnorm | EacRg11Unorm => Rg8Unorm,
+        Bc5RgSnorm | EacRg11Snorm => Rg8Snorm,
+        Astc {
+            channel: wgpu::AstcChannel::Unorm,
+            ..
+        } => Rgba8Unorm,
+        Astc {
+            channel: wgpu::AstcChannel::UnormSrgb,
+            ..
+        } => Rgba8UnormSrgb,
+        _ => return None,
+    })
+}
+
+/// Decode a `width` by `height` image of `format` blocks into tightly packed
+/// texels of [`decompressed_format`]. `None` when the format cannot be decoded
+/// or `bytes` is too short.
+pub fn decompress_image(
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    bytes: &[u8],
+) -> Option<Vec<u8>> {
+    use wgpu::TextureFormat::*;
+    let texel_size = decompressed_format(format)?.describe().block_size as usize;
+    let info = format.describe();
+    let (block_width, block_height) = (
+        info.block_dimensions.0 as usize,
+        info.block_dimensions.1 as usize,
+    );
+    let block_size = info.block_size as usize;
+    let decode = |block: &[u8], texels: &mut [u8]| match format {
+        Bc1RgbaUnorm | Bc1RgbaUnormSrgb => bc::decode_bc1(block, texels),
+        Bc2RgbaUnorm | Bc2RgbaUnormSrgb => bc::decode_bc2(block, texels),
+        Bc3RgbaUnorm | Bc3RgbaUnormSrgb => bc::decode_bc3(block, texels),
+        Bc4RUnorm | Bc4RSnorm => bc::decode_bc4(block, texels, format == Bc4RSnorm),
+        Bc5RgUnorm | Bc5RgSnorm => bc::decode_bc5(block, texels, format == Bc5RgSnorm),
+        Bc7RgbaUnorm | Bc7RgbaUnormSrgb => bc::decode_bc7(block, texels),
+        Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb => etc::decode_etc2_rgb(block, texels),
+        Etc2Rgb8A1Unorm | Etc2Rgb8A1UnormSrgb => etc::decode_etc2_rgb_a1(block, texels),
+        Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb => etc::decode_etc2_rgba(block, texels),
+        EacR11Unorm | EacR11Snorm => etc::decode_eac_r(block, texels, format == EacR11Snorm),
+        EacRg11Unorm | EacRg11Snorm => etc::decode_eac_rg(block, texels, format == EacRg11Snorm),
+        _ => astc::decode_astc(
+            block,
+            (block_width as u32, block_height as u32),
+            format.describe().srgb,
+            texels,
+        ),
+    };
+
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = width.div_ceil(block_width);
+    let blocks_y = height.div_ceil(block_height);
+    if bytes.len() < blocks_x * blocks_y * block_size {
+        return None;
+    }
+
+    let mut image = vec![0; width * height * texel_size];
+    let mut texels = vec![0; block_width * block_height * texel_size];
+    for (index, block) in bytes
+        .chunks_exact(block_size)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        decode(block, &mut texels);
+        let (x, y) = (
+            index % blocks_x * block_width,
+            index / blocks_x * block_height,
+        );
+        // Blocks past the right and bottom edges are cropped
+        let row_width = block_width.min(width - x) * texel_size;
+        for row in 0..block_height.min(height - y) {
+            let source = row * block_width * texel_size;
+            let destination = ((y + row) * width + x) * texel_size;
+            image[destination..destination + row_width]
+                .copy_from_slice(&texels[source..source + row_width]);
+        }
+    }
+    Some(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texel(image: &[u8], x: usize, y: usize) -> [u8; 4] {
+        image[(y * 4 + x) * 4..][..4].try_into().unwrap()
+    }
+
+    #[test]
+    fn bc1_solid_block() {
+        // Red and black endpoints, every texel on the first one
+        let block = [0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let image = decompress_image(wgpu::TextureFormat::Bc1RgbaUnorm, 4, 4, &block).unwrap();
+        assert_eq!(image.len(), 64);
+        assert!(image.chunks_exact(4).all(|texel| texel == [255, 0, 0, 255]));
+
+        // Every texel on the color two thirds of the way to black
+        let block = [0x00, 0xf8, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff];
+        let image = decompress_image(wgpu::TextureFormat::Bc1RgbaUnorm, 4, 4, &block).unwrap();
+        assert!(image.chunks_exact(4).all(|texel| texel == [85, 0, 0, 255]));
+
+        // The 3 colors mode makes the last index transparent black
+        let block = [0x00, 0x00, 0x00, 0xf8, 0xff, 0xff, 0xff, 0xff];
+        let image = decompress_image(wgpu::TextureFormat::Bc1RgbaUnorm, 4, 4, &block).unwrap();
+        assert!(image.chunks_exact(4).all(|texel| texel == [0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn etc2_individual_block() {
+        // Base colors 0x884422 on the left half and 0xff0000 on the right,
+        // first modifier table, texel (1, 0) on the -8 modifier
+        let block = [0x8f, 0x40, 0x20, 0x00, 0x00, 0x10, 0x00, 0x10];
+        let image = decompress_image(wgpu::TextureFormat::Etc2Rgb8Unorm, 4, 4, &block).unwrap();
+        assert_eq!(texel(&image, 0, 0), [138, 70, 36, 255]);
+        assert_eq!(texel(&image, 1, 0), [128, 60, 26, 255]);
+        assert_eq!(texel(&image, 1, 3), [138, 70, 36, 255]);
+        assert_eq!(texel(&image, 2, 0), [255, 2, 2, 255]);
+        assert_eq!(texel(&image, 3, 3), [255, 2, 2, 255]);
+    }
+
+    #[test]
+    fn astc_void_extent_block() {
+        // Constant color block of UNORM16 channels
+        let block = [
+            0xfc, 0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x80, 0x00, 0x00,
+            0xff, 0xff,
+        ];
+        let format = wgpu::TextureFormat::Astc {
+            block: wgpu::AstcBlock::B4x4,
+            channel: wgpu::AstcChannel::Unorm,
+        };
+        let color = [255, 128, 0, 255];
+        let image = decompress_image(format, 4, 4, &block).unwrap();
+        assert!(image.chunks_exact(4).all(|texel| texel == color));
+    }
+
+    #[test]
+    fn blocks_are_cropped_to_the_image() {
+        let block = [0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let image = decompress_image(wgpu::TextureFormat::Bc1RgbaUnorm, 3, 2, &block).unwrap();
+        assert_eq!(image.len(), 3 * 2 * 4);
+        assert!(decompress_image(wgpu::TextureFormat::Bc1RgbaUnorm, 8, 4, &block).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use super::mip_level_count;
+use crate::prelude::DEFAULT_TEXTURE_FORMAT;
+
+/// Bytes of a row of `width` texels, compressed formats count rows of blocks
+pub fn bytes_per_row(format: wgpu::TextureFormat, width: u32) -> u32 {
+    checked_bytes_per_row(format, width)
+        .expect("[fine::graphic::TextureDescriptor] Row size does not fit in u32.")
+}
+
+/// [`bytes_per_row`], `None` when it does not fit in `u32`
+pub fn checked_bytes_per_row(format: wgpu::TextureFormat, width: u32) -> Option<u32> {
+    let info = format.describe();
+    let block_width = info.block_dimensions.0 as u32;
+    width
+        .div_ceil(block_width)
+        .checked_mul(info.block_size as u32)
+}
+
+/// [`bytes_per_row`] rounded up for buffer to texture copies
+pub fn padded_bytes_per_row(format: wgpu::TextureFormat, width: u32) -> u32 {
+    bytes_per_row(format, width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Rows of texels, or of blocks for compressed formats
+pub fn rows_per_image(format: wgpu::TextureFormat, height: u32) -> u32 {
+    height.div_ceil(format.describe().block_dimensions.1 as u32)
+}
+
+/// Bytes of a `width` by `height` image of `depth_or_array_layers` layers,
+/// for every mip level
+pub fn texture_byte_size(
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    dimension: wgpu::TextureDimension,
+    mip_level_count: u32,
+) -> u64 {
+    checked_texture_byte_size(format, size, dimension, mip_level_count)
+        .expect("[fine::graphic::TextureDescriptor] Texture size does not fit in u64.")
+}
+
+/// [`texture_byte_size`], `None` when it does not fit in `u64`
+pub fn checked_texture_byte_size(
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    dimension: wgpu::TextureDimension,
+    mip_level_count: u32,
+) -> Option<u64> {
+    (0..mip_level_count).try_fold(0u64, |total, level| {
+        let size = size.mip_level_size(level, dimension == wgpu::TextureDimension::D3);
+        (checked_bytes_per_row(format, size.width)? as u64)
+            .checked_mul(rows_per_image(format, size.height) as u64)?
+            .checked_mul(size.depth_or_array_layers as u64)?
+            .checked_add(total)
+    })
+}
+
+/// How textures are created, sizes aside
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureDescriptor {
+    format: wgpu::TextureFormat,
+    dimension: wgpu::TextureDimension,
+    usage: wgpu::TextureUsages,
+    sample_count: u32,
+    mip_level_count: u32,
+}
+
+impl Default for TextureDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureDescriptor {
+    /// 2D [`DEFAULT_TEXTURE_FORMAT`] texture for sampling, without mipmaps
+    pub fn new() -> Self {
+        Self {
+            format: DEFAULT_TEXTURE_FORMAT,
+            dimension: wgpu::TextureDimension::D2,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            sample_count: 1,
+            mip_level_count: 1,
+        }
+    }
+
+    /// Texture to render to and sample
+    pub fn render_target(format: wgpu::TextureFormat) -> Self {
+        Self::new().with_format(format).with_usage(
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        )
+    }
+
+    /// Depth buffer, such as `Depth32Float` or `Depth24PlusStencil8`
+    pub fn depth(format: wgpu::TextureFormat) -> Self {
+        Self::new()
+            .with_format(format)
+            .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    }
+
+    pub fn with_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Same format read and written as sRGB, when it has such a variant
+    pub fn with_srgb(mut self) -> Self {
+        use wgpu::TextureFormat::*;
+        self.format = match self.format {
+            Rgba8Unorm => Rgba8UnormSrgb,
+            Bgra8Unorm => Bgra8UnormSrgb,
+            Bc1RgbaUnorm => Bc1RgbaUnormSrgb,
+            Bc2RgbaUnorm => Bc2RgbaUnormSrgb,
+            Bc3RgbaUnorm => Bc3RgbaUnormSrgb,
+            Bc7RgbaUnorm => Bc7RgbaUnormSrgb,
+            Etc2Rgb8Unorm => Etc2Rgb8UnormSrgb,
+            Etc2Rgb8A1Unorm => Etc2Rgb8A1UnormSrgb,
+            Etc2Rgba8Unorm => Etc2Rgba8UnormSrgb,
+            Astc { block, .. } => Astc {
+                block,
+                channel: wgpu::AstcChannel::UnormSrgb,
+            },
+            format => format,
+        };
+        self
+    }
+
+    pub fn with_dimension(mut self, dimension: wgpu::TextureDimension) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Replace the usages, `COPY_DST` is kept to upload data
+    pub fn with_usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage = usage | wgpu::TextureUsages::COPY_DST;
+        self
+    }
+
+    pub fn add_usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage |= usage;
+        self
+    }
+
+    /// Samples per texel, multisampled textures have a single mip level
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count.max(1);
+        self
+    }
+
+    /// Levels including the base one, clamped to the full chain of the
+    /// texture size. `u32::MAX` asks for the full chain.
+    pub fn with_mip_level_count(mut self, mip_level_count: u32) -> Self {
+        self.mip_level_count = mip_level_count.max(1);
+        self
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn dimension(&self) -> wgpu::TextureDimension {
+        self.dimension
+    }
+
+    pub fn usage(&self) -> wgpu::TextureUsages {
+        self.usage
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Levels of a texture of `size`
+    pub fn mip_level_count(&self, size: wgpu::Extent3d) -> u32 {
+        if self.sample_count > 1 {
+            return 1;
+        }
+        let full = match self.dimension {
+            wgpu::TextureDimension::D1 => 1,
+            wgpu::TextureDimension::D2 => mip_level_count(size.width, size.height),
+            wgpu::TextureDimension::D3 => {
+                mip_level_count(size.width.max(size.depth_or_array_layers), size.height)
+            }
+        };
+        self.mip_level_count.min(full)
+    }
+
+    /// Bytes per texel, or per block for compressed formats
+    pub fn block_size(&self) -> u32 {
+        self.format.describe().block_size as u32
+    }
+
+    pub fn bytes_per_row(&self, width: u32) -> u32 {
+        bytes_per_row(self.format, width)
+    }
+
+    /// Bytes of every level and layer of a texture of `size`
+    pub fn byte_size(&self, size: wgpu::Extent3d) -> u64 {
+        texture_byte_size(
+            self.format,
+            size,
+            self.dimension,
+            self.mip_level_count(size),
+        )
+    }
+
+    /// View dimension matching the texture dimension, arrays for several layers
+    pub fn view_dimension(&self, depth_or_array_layers: u32) -> wgpu::TextureViewDimension {
+        match self.dimension {
+            wgpu::TextureDimension::D1 => wgpu::TextureViewDimension::D1,
+            wgpu::TextureDimension::D2 if depth_or_array_layers > 1 => {
+                wgpu::TextureViewDimension::D2Array
+            }
+            wgpu::TextureDimension::D2 => wgpu::TextureViewDimension::D2,
+            wgpu::TextureDimension::D3 => wgpu::TextureViewDimension::D3,
+        }
+    }
+
+    /// The wgpu descriptor of a texture of `size`
+    pub fn to_wgpu<'a>(
+        &self,
+        label: Option<&'a str>,
+        size: wgpu::Extent3d,
+    ) -> wgpu::TextureDescriptor<'a> {
+        wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: self.mip_level_count(size),
+            sample_count: self.sample_count,
+            dimension: self.dimension,
+            format: self.format,
+            usage: self.usage,
+        }
+    }
+}
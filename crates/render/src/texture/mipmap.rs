@@ -0,0 +1,605 @@
+use super::{bytes_per_row, rows_per_image};
+use crate::context::Context;
+use crate::pipeline::RenderPipeline;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipFilter {
+    /// Average of the covered pixels
+    Box,
+    /// Sharper, three lobes windowed sinc
+    Lanczos3,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MipmapOptions {
+    pub filter: MipFilter,
+    /// Colors are sRGB encoded and filtered in linear space
+    pub srgb: bool,
+    /// Alpha test reference. Alpha is scaled on each level to keep the share
+    /// of pixels passing the test, so cutouts do not fade with distance.
+    pub alpha_coverage: Option<f32>,
+    /// Levels including the base one, the full chain when `None`
+    pub level_count: Option<u32>,
+}
+
+impl Default for MipmapOptions {
+    fn default() -> Self {
+        Self {
+            filter: MipFilter::Box,
+            srgb: true,
+            alpha_coverage: None,
+            level_count: None,
+        }
+    }
+}
+
+/// Levels of a full chain, down to one pixel
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Size of a level, halved and rounded down, at least one pixel
+pub fn mip_level_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+/// Levels an atlas can have before filtering mixes a frame with its
+/// neighbours. Frames are only repeated `extrude` pixels around them.
+pub fn atlas_mip_level_count(extrude: u32) -> u32 {
+    32 - (extrude + 1).leading_zeros()
+}
+
+pub(super) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(super) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn lanczos3(x: f32) -> f32 {
+    let sinc = |x: f32| {
+        if x.abs() < 1e-6 {
+            1.0
+        } else {
+            let x = x * std::f32::consts::PI;
+            x.sin() / x
+        }
+    };
+    if x.abs() < 3.0 {
+        sinc(x) * sinc(x / 3.0)
+    } else {
+        0.0
+    }
+}
+
+/// Source pixels and weights of each destination pixel along one axis
+fn weights(source: u32, destination: u32, filter: MipFilter) -> Vec<Vec<(usize, f32)>> {
+    let ratio = source as f32 / destination as f32;
+    (0..destination)
+        .map(|index| {
+            let (start, end) = (index as f32 * ratio, (index + 1) as f32 * ratio);
+            let mut taps = Vec::new();
+            match filter {
+                MipFilter::Box => {
+                    let mut pixel = start.floor();
+                    while pixel < end {
+                        let weight = (pixel + 1.0).min(end) - pixel.max(start);
+                        taps.push((pixel as usize, weight));
+                        pixel += 1.0;
+                    }
+                }
+                MipFilter::Lanczos3 => {
+                    let center = (start + end) / 2.0;
+                    let support = 3.0 * ratio.max(1.0);
+                    let first = (center - support).floor() as i64;
+                    let last = (center + support).ceil() as i64;
+                    for pixel in first..=last {
+                        let weight = lanczos3((pixel as f32 + 0.5 - center) / ratio.max(1.0));
+                        if weight != 0.0 {
+                            let pixel = pixel.clamp(0, source as i64 - 1) as usize;
+                            taps.push((pixel, weight));
+                        }
+                    }
+                }
+            }
+            let total = taps.iter().map(|(_, weight)| weight).sum::<f32>();
+            for (_, weight) in taps.iter_mut() {
+                *weight /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+/// Straight alpha pixels in linear space
+#[derive(Clone)]
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    fn decode(bytes: &[u8], width: u32, height: u32, srgb: bool) -> Self {
+        let table = (0..256)
+            .map(|value| {
+                let value = value as f32 / 255.0;
+                if srgb {
+                    srgb_to_linear(value)
+                } else {
+                    value
+                }
+            })
+            .collect::<Vec<_>>();
+        let pixels = bytes
+            .chunks_exact(4)
+            .map(|pixel| {
+                [
+                    table[pixel[0] as usize],
+                    table[pixel[1] as usize],
+                    table[pixel[2] as usize],
+                    pixel[3] as f32 / 255.0,
+                ]
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn encode(&self, srgb: bool) -> Vec<u8> {
+        let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        self.pixels
+            .iter()
+            .flat_map(|[r, g, b, a]| {
+                let color = [*r, *g, *b].map(|value| {
+                    if srgb {
+                        quantize(linear_to_srgb(value.clamp(0.0, 1.0)))
+                    } else {
+                        quantize(value)
+                    }
+                });
+                [color[0], color[1], color[2], quantize(*a)]
+            })
+            .collect()
+    }
+
+    /// Separable resampling of premultiplied colors
+    fn resize(&self, width: u32, height: u32, filter: MipFilter) -> Self {
+        let columns = weights(self.width, width, filter);
+        let rows = weights(self.height, height, filter);
+        let premultiplied = self
+            .pixels
+            .iter()
+            .map(|[r, g, b, a]| [r * a, g * a, b * a, *a])
+            .collect::<Vec<_>>();
+
+        let mut horizontal = vec![[0.0f32; 4]; (width * self.height) as usize];
+        for y in 0..self.height as usize {
+            for (x, taps) in columns.iter().enumerate() {
+                let pixel = &mut horizontal[y * width as usize + x];
+                for (source, weight) in taps {
+                    let value = premultiplied[y * self.width as usize + source];
+                    for channel in 0..4 {
+                        pixel[channel] += value[channel] * weight;
+                    }
+                }
+            }
+        }
+
+        let mut pixels = vec![[0.0f32; 4]; (width * height) as usize];
+        for (y, taps) in rows.iter().enumerate() {
+            for x in 0..width as usize {
+                let pixel = &mut pixels[y * width as usize + x];
+                for (source, weight) in taps {
+                    let value = horizontal[source * width as usize + x];
+                    for channel in 0..4 {
+                        pixel[channel] += value[channel] * weight;
+                    }
+                }
+                let alpha = pixel[3].clamp(0.0, 1.0);
+                for channel in pixel.iter_mut().take(3) {
+                    *channel = if alpha > 1e-6 {
+                        (*channel / alpha).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                }
+                pixel[3] = alpha;
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Share of pixels whose alpha, scaled, passes `reference`
+    fn coverage(&self, reference: f32, scale: f32) -> f32 {
+        let passing = self
+            .pixels
+            .iter()
+            .filter(|pixel| pixel[3] * scale > reference)
+            .count();
+        passing as f32 / self.pixels.len() as f32
+    }
+
+    /// Scale alpha so that `coverage` of the pixels pass `reference`
+    fn preserve_coverage(&mut self, reference: f32, coverage: f32) {
+        let (mut low, mut high) = (0.0f32, 4.0f32);
+        for _ in 0..16 {
+            let middle = (low + high) / 2.0;
+            if self.coverage(reference, middle) < coverage {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        let scale = (low + high) / 2.0;
+        for pixel in self.pixels.iter_mut() {
+            pixel[3] = (pixel[3] * scale).min(1.0);
+        }
+    }
+}
+
+/// Levels after the base one of a `width` by `height` image of 4 bytes pixels,
+/// alpha last, each level made from the previous one.
+pub fn generate_mip_chain(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    options: &MipmapOptions,
+) -> Vec<Vec<u8>> {
+    assert_eq!(
+        bytes.len(),
+        (width * height * 4) as usize,
+        "[fine::graphic::Mipmap] bytes do not match the image size."
+    );
+    let full = mip_level_count(width, height);
+    let level_count = options.level_count.unwrap_or(full).clamp(1, full);
+
+    let mut image = Image::decode(bytes, width, height, options.srgb);
+    let coverage = options
+        .alpha_coverage
+        .map(|reference| (reference, image.coverage(reference, 1.0)));
+
+    (1..level_count)
+        .map(|level| {
+            let (level_width, level_height) = mip_level_size(width, height, level);
+            image = image.resize(level_width, level_height, options.filter);
+            let mut level = image.clone();
+            if let Some((reference, coverage)) = coverage {
+                level.preserve_coverage(reference, coverage);
+            }
+            level.encode(options.srgb)
+        })
+        .collect()
+}
+
+/// Upload tightly packed rows to one level of a texture, from its `layer`th
+/// layer or depth slice. Compressed levels are copied whole blocks at a time.
+pub(super) fn write_mip_level(
+    gpu: &Context,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    level: u32,
+    layer: u32,
+    size: wgpu::Extent3d,
+    bytes: &[u8],
+) {
+    let (block_width, block_height) = format.describe().block_dimensions;
+    gpu.enqueue_write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(bytes_per_row(format, size.width)),
+            rows_per_image: NonZeroU32::new(rows_per_image(format, size.height)),
+        },
+        wgpu::Extent3d {
+            width: round_up(size.width, block_width as u32),
+            height: round_up(size.height, block_height as u32),
+            depth_or_array_layers: size.depth_or_array_layers,
+        },
+    );
+}
+
+fn round_up(value: u32, multiple: u32) -> u32 {
+    value.div_ceil(multiple) * multiple
+}
+
+const MIPMAP_SHADER: &str = r#"
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+fn to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+// Area weighted average of the source pixels under a target pixel
+fn downsample(position: vec2<f32>, srgb: bool) -> vec4<f32> {
+    let source_size = vec2<f32>(textureDimensions(source));
+    let target_size = max(floor(source_size / 2.0), vec2<f32>(1.0));
+    let ratio = source_size / target_size;
+    let start = floor(position) * ratio;
+    let end = start + ratio;
+
+    var premultiplied = vec3<f32>(0.0);
+    var straight = vec3<f32>(0.0);
+    var alpha = 0.0;
+    var total = 0.0;
+    for (var y = floor(start.y); y < end.y; y = y + 1.0) {
+        let weight_y = min(y + 1.0, end.y) - max(y, start.y);
+        for (var x = floor(start.x); x < end.x; x = x + 1.0) {
+            let weight = (min(x + 1.0, end.x) - max(x, start.x)) * weight_y;
+            let texel = textureLoad(source, vec2<i32>(i32(x), i32(y)), 0);
+            var color = texel.rgb;
+            if (srgb) {
+                color = to_linear(color);
+            }
+            premultiplied = premultiplied + color * texel.a * weight;
+            straight = straight + color * weight;
+            alpha = alpha + texel.a * weight;
+            total = total + weight;
+        }
+    }
+
+    var color = straight / total;
+    if (alpha > 0.000001) {
+        color = premultiplied / alpha;
+    }
+    if (srgb) {
+        color = to_srgb(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)));
+    }
+    return vec4<f32>(color, alpha / total);
+}
+
+@fragment
+fn fs_linear(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return downsample(position.xy, false);
+}
+
+@fragment
+fn fs_srgb(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return downsample(position.xy, true);
+}
+"#;
+
+/// Fills the levels of textures on the GPU, each level averaging the
+/// previous one. sRGB encoded colors are averaged in linear space, use
+/// [`generate_mip_chain`] for the Lanczos filter or alpha coverage.
+/// Textures need `TEXTURE_BINDING` and `RENDER_ATTACHMENT` usages.
+#[derive(Default)]
+pub struct MipmapGenerator {
+    pipelines: HashMap<(wgpu::TextureFormat, bool), RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pipeline(
+        &mut self,
+        gpu: &Context,
+        format: wgpu::TextureFormat,
+        srgb: bool,
+    ) -> &RenderPipeline {
+        self.pipelines.entry((format, srgb)).or_insert_with(|| {
+            let shader = gpu.create_wgsl_shader(MIPMAP_SHADER);
+            gpu.create_pipeline()
+                .create_bind_group_layout(&[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }])
+                .into_render_pipeline(
+                    wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                    },
+                    Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: if srgb { "fs_srgb" } else { "fs_linear" },
+                        targets: &[Some(format.into())],
+                    }),
+                )
+        })
+    }
+
+    /// Fill levels `1..level_count` of the layers of a `width` by `height`
+    /// texture from its base level. `srgb` tells whether the colors of a
+    /// linear format are sRGB encoded, sRGB formats are always decoded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate(
+        &mut self,
+        gpu: &mut Context,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+        (width, height): (u32, u32),
+        layer_count: u32,
+        level_count: u32,
+        srgb: bool,
+    ) {
+        // sRGB formats are decoded when sampled and encoded when written
+        let srgb = srgb && !format.describe().srgb;
+        let level_count = level_count.min(mip_level_count(width, height));
+
+        let view = |level: u32, layer: u32| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("MipmapGenerator::generate"),
+                format: Some(format),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: level,
+                mip_level_count: NonZeroU32::new(1),
+                base_array_layer: layer,
+                array_layer_count: NonZeroU32::new(1),
+            })
+        };
+
+        let pipeline = self.pipeline(gpu, format, srgb);
+        for layer in 0..layer_count {
+            for level in 1..level_count {
+                let source = view(level - 1, layer);
+                let target = view(level, layer);
+                let bind_group = pipeline
+                    .create_bind_group(
+                        0,
+                        &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&source),
+                        }],
+                    )
+                    .unwrap();
+
+                let mut pass = gpu.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("MipmapGenerator::generate"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                pipeline.use_pass(&mut pass);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixels of 4 bytes from a function of their position
+    fn image(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| pixel(x, y))
+            .collect()
+    }
+
+    /// Share of the pixels whose alpha passes `reference`
+    fn coverage(bytes: &[u8], reference: f32) -> f32 {
+        let alphas = bytes.chunks_exact(4).map(|pixel| pixel[3] as f32 / 255.0);
+        alphas.clone().filter(|alpha| *alpha > reference).count() as f32 / alphas.count() as f32
+    }
+
+    #[test]
+    fn levels_are_halved_down_to_one_pixel() {
+        let bytes = image(16, 4, |_, _| [0; 4]);
+        let chain = generate_mip_chain(&bytes, 16, 4, &MipmapOptions::default());
+        let sizes = chain
+            .iter()
+            .map(|level| level.len() / 4)
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [8 * 2, 4, 2, 1]);
+        assert_eq!(mip_level_count(16, 4), chain.len() as u32 + 1);
+        assert_eq!(mip_level_size(16, 4, 3), (2, 1));
+
+        let options = MipmapOptions {
+            level_count: Some(2),
+            ..MipmapOptions::default()
+        };
+        assert_eq!(generate_mip_chain(&bytes, 16, 4, &options).len(), 1);
+        assert!(generate_mip_chain(&[0; 4], 1, 1, &MipmapOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn flat_colors_stay_flat() {
+        let color = [200, 30, 90, 255];
+        let bytes = image(12, 7, |_, _| color);
+        for filter in [MipFilter::Box, MipFilter::Lanczos3] {
+            for srgb in [false, true] {
+                let options = MipmapOptions {
+                    filter,
+                    srgb,
+                    ..MipmapOptions::default()
+                };
+                for level in generate_mip_chain(&bytes, 12, 7, &options) {
+                    for pixel in level.chunks_exact(4) {
+                        let difference = pixel.iter().zip(color).map(|(a, b)| a.abs_diff(b));
+                        assert!(difference.max().unwrap() <= 1, "{filter:?} {pixel:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_coverage_is_kept() {
+        // Noisy alpha, a fifth of the pixels pass the test
+        let mut state = 1u32;
+        let noise = (0..64 * 64)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect::<Vec<_>>();
+        let bytes = image(64, 64, |x, y| [255, 255, 255, noise[(y * 64 + x) as usize]]);
+        let reference = 0.8;
+        let base = coverage(&bytes, reference);
+
+        let options = MipmapOptions {
+            alpha_coverage: Some(reference),
+            ..MipmapOptions::default()
+        };
+        let kept = generate_mip_chain(&bytes, 64, 64, &options);
+        let faded = generate_mip_chain(&bytes, 64, 64, &MipmapOptions::default());
+        // Down to 8 by 8, smaller levels have too few pixels
+        for (kept, faded) in kept.iter().zip(faded.iter()).take(3) {
+            assert!((coverage(kept, reference) - base).abs() < 0.05);
+            assert!(coverage(faded, reference) < base / 2.0);
+        }
+    }
+}
@@ -1,9 +1,32 @@
 mod atlas;
+mod cache;
+mod container;
+mod cube;
+#[cfg(feature = "use-image")]
+mod decode;
+mod decompress;
+mod descriptor;
+mod mipmap;
+mod packer;
+#[allow(clippy::module_inception)]
 mod texture;
 mod raw;
-pub use atlas::TextureAtlas;
+mod sampler;
+pub use atlas::{
+    FrameRotation, TextureAtlas, TextureAtlasFromBytes, TexturePosition, TextureRect, UvRect,
+};
+pub use cache::*;
+pub use container::*;
+pub use cube::*;
+#[cfg(feature = "use-image")]
+pub use decode::*;
+pub use decompress::*;
+pub use descriptor::*;
+pub use mipmap::*;
 pub use texture::Texture;
 pub use raw::RawTexture;
+pub use sampler::*;
+pub use packer::*;
 use crate::context::Context;
 
 pub trait AsTextureView {
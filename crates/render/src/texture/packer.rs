@@ -0,0 +1,584 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackingAlgorithm {
+    /// Fast, keeps a skyline of the placed rectangles and fills bottom-left
+    Skyline,
+    /// Slower, tracks every free rectangle and picks the best short side fit
+    MaxRects,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PackerOptions {
+    pub algorithm: PackingAlgorithm,
+    /// Empty pixels between two rectangles
+    pub padding: u32,
+    /// Pixels repeating the edges of a rectangle around it
+    pub extrude: u32,
+    /// Allow rectangles to be turned 90 degrees clockwise
+    pub allow_rotation: bool,
+}
+
+impl Default for PackerOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: PackingAlgorithm::Skyline,
+            padding: 0,
+            extrude: 0,
+            allow_rotation: false,
+        }
+    }
+}
+
+/// Placement of a rectangle, without padding and extrusion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedRect {
+    pub layer: u32,
+    pub x: u32,
+    pub y: u32,
+    /// Width in the layer, swapped with the height when rotated
+    pub width: u32,
+    pub height: u32,
+    pub rotated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Skyline {
+    /// `(x, y, width)` segments, left to right
+    segments: Vec<(u32, u32, u32)>,
+}
+
+impl Skyline {
+    fn new(width: u32) -> Self {
+        Self {
+            segments: vec![(0, 0, width)],
+        }
+    }
+
+    /// Lowest top of a `width` wide rectangle starting at segment `index`
+    fn fit(&self, index: usize, width: u32, height: u32, bounds: (u32, u32)) -> Option<u32> {
+        let x = self.segments[index].0;
+        if x + width > bounds.0 {
+            return None;
+        }
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for segment in self.segments[index..].iter() {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment.1);
+            remaining -= segment.2 as i64;
+        }
+        (y + height <= bounds.1).then_some(y)
+    }
+
+    /// Best `(score, index, y)`, the lowest bottom then the narrowest segment
+    fn find(
+        &self,
+        width: u32,
+        height: u32,
+        bounds: (u32, u32),
+    ) -> Option<((u32, u32), usize, u32)> {
+        (0..self.segments.len())
+            .filter_map(|index| {
+                self.fit(index, width, height, bounds)
+                    .map(|y| ((y + height, self.segments[index].2), index, y))
+            })
+            .min_by_key(|(score, _, _)| *score)
+    }
+
+    fn place(&mut self, index: usize, rect: Rect) {
+        self.segments
+            .insert(index, (rect.x, rect.bottom(), rect.width));
+
+        // Shrink or remove the segments now under the rectangle
+        let end = rect.right();
+        let next = index + 1;
+        while next < self.segments.len() {
+            let (x, y, width) = self.segments[next];
+            if x >= end {
+                break;
+            }
+            let overlap = end - x;
+            if overlap >= width {
+                self.segments.remove(next);
+            } else {
+                self.segments[next] = (end, y, width - overlap);
+                break;
+            }
+        }
+
+        self.merge();
+    }
+
+    /// Raise the segments under a rectangle placed outside of the skyline
+    fn reserve(&mut self, rect: Rect) {
+        let end = rect.right();
+        let mut segments = Vec::with_capacity(self.segments.len() + 2);
+        for &(x, y, width) in self.segments.iter() {
+            let right = x + width;
+            if right <= rect.x || x >= end {
+                segments.push((x, y, width));
+                continue;
+            }
+            if x < rect.x {
+                segments.push((x, y, rect.x - x));
+            }
+            let start = x.max(rect.x);
+            let covered = right.min(end) - start;
+            segments.push((start, y.max(rect.bottom()), covered));
+            if right > end {
+                segments.push((end, y, right - end));
+            }
+        }
+        self.segments = segments;
+        self.merge();
+    }
+
+    /// Merge neighbours of the same height
+    fn merge(&mut self) {
+        let mut index = 0;
+        while index + 1 < self.segments.len() {
+            if self.segments[index].1 == self.segments[index + 1].1 {
+                self.segments[index].2 += self.segments[index + 1].2;
+                self.segments.remove(index + 1);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MaxRects {
+    free: Vec<Rect>,
+}
+
+impl MaxRects {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            free: vec![Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        }
+    }
+
+    /// Best `(score, x, y)` by short then long leftover side
+    fn find(&self, width: u32, height: u32) -> Option<((u32, u32), u32, u32)> {
+        self.free
+            .iter()
+            .filter(|free| free.width >= width && free.height >= height)
+            .map(|free| {
+                let (a, b) = (free.width - width, free.height - height);
+                ((a.min(b), a.max(b)), free.x, free.y)
+            })
+            .min_by_key(|(score, _, _)| *score)
+    }
+
+    fn place(&mut self, rect: Rect) {
+        let mut index = 0;
+        while index < self.free.len() {
+            let free = self.free[index];
+            if !free.intersects(&rect) {
+                index += 1;
+                continue;
+            }
+            self.free.swap_remove(index);
+            if rect.x > free.x {
+                self.free.push(Rect {
+                    width: rect.x - free.x,
+                    ..free
+                });
+            }
+            if rect.right() < free.right() {
+                self.free.push(Rect {
+                    x: rect.right(),
+                    width: free.right() - rect.right(),
+                    ..free
+                });
+            }
+            if rect.y > free.y {
+                self.free.push(Rect {
+                    height: rect.y - free.y,
+                    ..free
+                });
+            }
+            if rect.bottom() < free.bottom() {
+                self.free.push(Rect {
+                    y: rect.bottom(),
+                    height: free.bottom() - rect.bottom(),
+                    ..free
+                });
+            }
+        }
+
+        self.prune();
+    }
+
+    /// Give back the area of a placed rectangle
+    fn free(&mut self, rect: Rect) {
+        self.free.push(rect);
+
+        // Merge free rectangles sharing a whole edge
+        let mut merged = true;
+        while merged {
+            merged = false;
+            'search: for a in 0..self.free.len() {
+                for b in a + 1..self.free.len() {
+                    let (first, second) = (self.free[a], self.free[b]);
+                    let union = if first.y == second.y && first.height == second.height {
+                        (first.right() == second.x || second.right() == first.x).then_some(Rect {
+                            x: first.x.min(second.x),
+                            width: first.width + second.width,
+                            ..first
+                        })
+                    } else if first.x == second.x && first.width == second.width {
+                        (first.bottom() == second.y || second.bottom() == first.y).then_some(Rect {
+                            y: first.y.min(second.y),
+                            height: first.height + second.height,
+                            ..first
+                        })
+                    } else {
+                        None
+                    };
+                    if let Some(union) = union {
+                        self.free[a] = union;
+                        self.free.swap_remove(b);
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        self.prune();
+    }
+
+    /// Drop free rectangles inside another one
+    fn prune(&mut self) {
+        let mut index = 0;
+        while index < self.free.len() {
+            let current = self.free[index];
+            // Keep the first of identical rectangles
+            let contained = self.free.iter().enumerate().any(|(other, free)| {
+                if free == &current {
+                    other < index
+                } else {
+                    free.contains(&current)
+                }
+            });
+            if contained {
+                self.free.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum LayerPacker {
+    Skyline(Skyline),
+    MaxRects(MaxRects),
+}
+
+#[derive(Debug, Clone)]
+struct Layer {
+    packer: LayerPacker,
+    /// Area of the packed rectangles, without padding and extrusion
+    used_area: u64,
+    count: u32,
+}
+
+/// Places rectangles in the layers of an atlas, opening the next layer
+/// when one is full
+#[derive(Debug, Clone)]
+pub struct AtlasPacker {
+    options: PackerOptions,
+    width: u32,
+    height: u32,
+    layer_count: u32,
+    layers: Vec<Layer>,
+}
+
+impl AtlasPacker {
+    pub fn new(width: u32, height: u32, layer_count: u32, options: PackerOptions) -> Self {
+        Self {
+            options,
+            width,
+            height,
+            layer_count,
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &PackerOptions {
+        &self.options
+    }
+
+    /// Width and height of a layer
+    pub fn layer_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Layers holding at least one rectangle
+    pub fn layers_used(&self) -> u32 {
+        self.layers.len() as u32
+    }
+
+    /// Forget every placement
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    fn layer(&mut self, layer: usize) -> &mut Layer {
+        while self.layers.len() <= layer {
+            let packer = self.new_layer_packer();
+            self.layers.push(Layer {
+                packer,
+                used_area: 0,
+                count: 0,
+            });
+        }
+        &mut self.layers[layer]
+    }
+
+    fn new_layer_packer(&self) -> LayerPacker {
+        match self.options.algorithm {
+            PackingAlgorithm::Skyline => {
+                LayerPacker::Skyline(Skyline::new(self.width + self.options.padding))
+            }
+            PackingAlgorithm::MaxRects => LayerPacker::MaxRects(MaxRects::new(
+                self.width + self.options.padding,
+                self.height + self.options.padding,
+            )),
+        }
+    }
+
+    /// Place a `width` by `height` rectangle in `first_layer` or a following one.
+    /// Rotation is only tried when `allow_rotation` is set both here and in the options.
+    pub fn pack(
+        &mut self,
+        width: u32,
+        height: u32,
+        first_layer: u32,
+        allow_rotation: bool,
+    ) -> Option<PackedRect> {
+        let border = self.options.extrude * 2;
+        let padding = self.options.padding;
+        let rotate = allow_rotation && self.options.allow_rotation && width != height;
+        let bounds = (self.width + padding, self.height + padding);
+
+        for layer in first_layer..self.layer_count {
+            let mut sizes = vec![(width, height, false)];
+            if rotate {
+                sizes.push((height, width, true));
+            }
+
+            let layer_packer = &mut self.layer(layer as usize).packer;
+            let placement = match layer_packer {
+                LayerPacker::Skyline(skyline) => sizes
+                    .iter()
+                    .filter_map(|(w, h, rotated)| {
+                        let (w, h) = (w + border + padding, h + border + padding);
+                        skyline.find(w, h, bounds).map(|(score, index, y)| {
+                            let x = skyline.segments[index].0;
+                            (
+                                score,
+                                Some(index),
+                                Rect {
+                                    x,
+                                    y,
+                                    width: w,
+                                    height: h,
+                                },
+                                *rotated,
+                            )
+                        })
+                    })
+                    .min_by_key(|(score, _, _, _)| *score),
+                LayerPacker::MaxRects(max_rects) => sizes
+                    .iter()
+                    .filter_map(|(w, h, rotated)| {
+                        let (w, h) = (w + border + padding, h + border + padding);
+                        max_rects.find(w, h).map(|(score, x, y)| {
+                            (
+                                score,
+                                None,
+                                Rect {
+                                    x,
+                                    y,
+                                    width: w,
+                                    height: h,
+                                },
+                                *rotated,
+                            )
+                        })
+                    })
+                    .min_by_key(|(score, _, _, _)| *score),
+            };
+
+            let (_, index, rect, rotated) = match placement {
+                Some(placement) => placement,
+                None => continue,
+            };
+            match layer_packer {
+                LayerPacker::Skyline(skyline) => skyline.place(index.unwrap_or_default(), rect),
+                LayerPacker::MaxRects(max_rects) => max_rects.place(rect),
+            }
+            self.layers[layer as usize].used_area += width as u64 * height as u64;
+            self.layers[layer as usize].count += 1;
+
+            let (width, height) = if rotated {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            return Some(PackedRect {
+                layer,
+                x: rect.x + self.options.extrude,
+                y: rect.y + self.options.extrude,
+                width,
+                height,
+                rotated,
+            });
+        }
+        None
+    }
+
+    /// Mark a `width` by `height` rectangle at `(x, y)` of `layer` as used,
+    /// for frames placed by hand. Skyline also loses the room under it. The
+    /// room is given back by [`AtlasPacker::unreserve`].
+    pub fn reserve(&mut self, layer: u32, x: u32, y: u32, width: u32, height: u32) {
+        let inside = |start: u32, size: u32, end: u32| {
+            start.checked_add(size).is_some_and(|last| last <= end)
+        };
+        assert!(
+            layer < self.layer_count
+                && inside(x, width, self.width)
+                && inside(y, height, self.height),
+            "[fine::graphic::AtlasPacker] Reserved rectangle is outside of the atlas."
+        );
+        let rect = Rect {
+            x,
+            y,
+            width: width + self.options.padding,
+            height: height + self.options.padding,
+        };
+        let layer_packer = self.layer(layer as usize);
+        match &mut layer_packer.packer {
+            LayerPacker::Skyline(skyline) => skyline.reserve(rect),
+            LayerPacker::MaxRects(max_rects) => max_rects.place(rect),
+        }
+        layer_packer.used_area += width as u64 * height as u64;
+        layer_packer.count += 1;
+    }
+
+    /// Give back the room of a rectangle returned by [`AtlasPacker::pack`].
+    /// MaxRects reuses it right away, Skyline only once its layer is empty.
+    pub fn free(&mut self, packed: &PackedRect) {
+        let (extrude, padding) = (self.options.extrude, self.options.padding);
+        let rect = Rect {
+            x: packed.x - extrude,
+            y: packed.y - extrude,
+            width: packed.width + extrude * 2 + padding,
+            height: packed.height + extrude * 2 + padding,
+        };
+        self.release(
+            packed.layer,
+            rect,
+            packed.width as u64 * packed.height as u64,
+        );
+    }
+
+    /// Give back the room of a rectangle marked by [`AtlasPacker::reserve`],
+    /// as [`AtlasPacker::free`] does
+    pub fn unreserve(&mut self, layer: u32, x: u32, y: u32, width: u32, height: u32) {
+        let rect = Rect {
+            x,
+            y,
+            width: width + self.options.padding,
+            height: height + self.options.padding,
+        };
+        self.release(layer, rect, width as u64 * height as u64);
+    }
+
+    fn release(&mut self, layer_index: u32, rect: Rect, area: u64) {
+        let layer = match self.layers.get_mut(layer_index as usize) {
+            Some(layer) if layer.count > 0 => layer,
+            _ => return,
+        };
+        layer.count -= 1;
+        layer.used_area = layer.used_area.saturating_sub(area);
+
+        if layer.count == 0 {
+            let packer = self.new_layer_packer();
+            let layer = &mut self.layers[layer_index as usize];
+            layer.packer = packer;
+            layer.used_area = 0;
+        } else if let LayerPacker::MaxRects(max_rects) = &mut layer.packer {
+            max_rects.free(rect);
+        }
+
+        // Layers past the last used one are opened again when needed
+        while self
+            .layers
+            .last()
+            .map(|layer| layer.count == 0)
+            .unwrap_or(false)
+        {
+            self.layers.pop();
+        }
+    }
+
+    /// Packed area over the area of the used layers, between 0 and 1
+    pub fn efficiency(&self) -> f32 {
+        if self.layers.is_empty() {
+            return 0.0;
+        }
+        let used = self.layers.iter().map(|layer| layer.used_area).sum::<u64>();
+        used as f32 / (self.layers.len() as u64 * self.width as u64 * self.height as u64) as f32
+    }
+
+    /// Packed area of one layer over its area
+    pub fn layer_efficiency(&self, layer: u32) -> f32 {
+        self.layers
+            .get(layer as usize)
+            .map(|layer| layer.used_area as f32 / (self.width as u64 * self.height as u64) as f32)
+            .unwrap_or(0.0)
+    }
+}
@@ -1,17 +1,85 @@
-use crate::{context::Context, prelude::DEFAULT_TEXTURE_FORMAT};
-use std::num::NonZeroU32;
+use super::mipmap::write_mip_level;
+use super::{
+    generate_mip_chain, mip_level_size, MipmapGenerator, MipmapOptions, TextureDescriptor,
+    TextureFile, TextureFileError,
+};
+use crate::context::Context;
 
 pub struct RawTexture {
     raw: wgpu::Texture,
     width: u32,
     height: u32,
+    depth_or_array_layers: u32,
+    mip_level_count: u32,
+    descriptor: TextureDescriptor,
 }
 
 impl RawTexture {
+    /// Wrap a 2D texture created with the default [`TextureDescriptor`]
     pub fn new(raw: wgpu::Texture, width: u32, height: u32) -> Self {
-        Self { raw, width, height }
+        Self::from_raw_parts(raw, width, height, 1, TextureDescriptor::new())
     }
 
+    /// Wrap a texture created from `descriptor`
+    pub fn from_raw_parts(
+        raw: wgpu::Texture,
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        descriptor: TextureDescriptor,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers,
+        };
+        Self {
+            raw,
+            width,
+            height,
+            depth_or_array_layers,
+            mip_level_count: descriptor.mip_level_count(size),
+            descriptor,
+        }
+    }
+
+    /// Texture without data, `depth_or_array_layers` is the depth of 3D
+    /// textures and the layer count of the others
+    pub fn with_descriptor(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        depth_or_array_layers: u32,
+        descriptor: &TextureDescriptor,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers,
+        };
+        let raw = gpu.create_texture(&descriptor.to_wgpu(Some("RawTexture::with_descriptor"), size));
+        Self::from_raw_parts(raw, width, height, depth_or_array_layers, *descriptor)
+    }
+
+    /// 2D texture holding `bytes` in its base level, rows tightly packed
+    pub fn from_bytes_with_descriptor(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        bytes: &[u8],
+    ) -> Self {
+        let texture = Self::with_descriptor(gpu, width, height, 1, descriptor);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        write_mip_level(gpu, &texture.raw, descriptor.format(), 0, 0, size, bytes);
+        texture
+    }
+
+    /// [`DEFAULT_TEXTURE_FORMAT`](crate::prelude::DEFAULT_TEXTURE_FORMAT) texture
     pub fn from_bytes(
         gpu: &mut Context,
         width: u32,
@@ -19,42 +87,165 @@ impl RawTexture {
         usage: wgpu::TextureUsages,
         bytes: &[u8],
     ) -> Self {
-        // Create texture
-        let raw = gpu.create_texture(&wgpu::TextureDescriptor {
-            label: Some("RawTexture::from_bytes"),
-            size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: DEFAULT_TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::COPY_DST | usage,
-        });
-
-        gpu.enqueue_write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &raw,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            bytes,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: NonZeroU32::new(4 * width),
-                rows_per_image: NonZeroU32::new(0),
-            },
-            wgpu::Extent3d {
+        let descriptor = TextureDescriptor::new().with_usage(usage);
+        Self::from_bytes_with_descriptor(gpu, width, height, &descriptor, bytes)
+    }
+
+    /// Texture with a mip chain made on the CPU from `bytes`
+    pub fn from_bytes_with_mipmaps(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+        bytes: &[u8],
+        options: &MipmapOptions,
+    ) -> Self {
+        let levels = generate_mip_chain(bytes, width, height, options);
+        let descriptor = TextureDescriptor::new()
+            .with_usage(usage)
+            .with_mip_level_count(levels.len() as u32 + 1);
+        let texture = Self::from_bytes_with_descriptor(gpu, width, height, &descriptor, bytes);
+        for (level, bytes) in levels.iter().enumerate() {
+            let level = level as u32 + 1;
+            let (width, height) = mip_level_size(width, height, level);
+            let size = wgpu::Extent3d {
                 width,
                 height,
                 depth_or_array_layers: 1,
-            },
+            };
+            write_mip_level(gpu, &texture.raw, descriptor.format(), level, 0, size, bytes);
+        }
+        texture
+    }
+
+    /// Texture holding every level, layer and face of a KTX2 or DDS file.
+    /// Formats the device does not support are decompressed on the CPU.
+    pub fn from_texture_file(
+        gpu: &mut Context,
+        usage: wgpu::TextureUsages,
+        file: &TextureFile,
+    ) -> Result<Self, TextureFileError> {
+        let file = file.for_features(gpu.features())?;
+        let descriptor = file.descriptor().with_usage(usage);
+        let size = file.size();
+        let texture = Self::with_descriptor(
+            gpu,
+            size.width,
+            size.height,
+            size.depth_or_array_layers,
+            &descriptor,
         );
+        for (level, bytes) in file.levels.iter().enumerate() {
+            let level = level as u32;
+            let size = file.level_size(level);
+            write_mip_level(gpu, &texture.raw, file.format, level, 0, size, bytes);
+        }
+        Ok(texture)
+    }
 
-        Self::new(raw, width, height)
+    /// 2D array texture, or cube maps six layers at a time, with one layer
+    /// of `layers` in the base level of each
+    pub fn from_layers(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        layers: &[&[u8]],
+    ) -> Self {
+        assert!(!layers.is_empty(), "[fine::graphic::RawTexture] No layers.");
+        let descriptor = descriptor.with_dimension(wgpu::TextureDimension::D2);
+        let texture = Self::with_descriptor(gpu, width, height, layers.len() as u32, &descriptor);
+        for (layer, bytes) in layers.iter().enumerate() {
+            texture.write_layer(gpu, 0, layer as u32, bytes);
+        }
+        texture
+    }
+
+    /// 3D texture with one depth slice of `slices` in the base level of each
+    pub fn from_slices(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        slices: &[&[u8]],
+    ) -> Self {
+        assert!(!slices.is_empty(), "[fine::graphic::RawTexture] No slices.");
+        let descriptor = descriptor.with_dimension(wgpu::TextureDimension::D3);
+        let texture = Self::with_descriptor(gpu, width, height, slices.len() as u32, &descriptor);
+        for (slice, bytes) in slices.iter().enumerate() {
+            texture.write_layer(gpu, 0, slice as u32, bytes);
+        }
+        texture
+    }
+
+    /// Upload tightly packed rows to one layer of a level, or one depth slice
+    /// of 3D textures
+    pub fn write_layer(&self, gpu: &Context, level: u32, layer: u32, bytes: &[u8]) {
+        let (width, height) = mip_level_size(self.width, self.height, level);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        write_mip_level(gpu, &self.raw, self.format(), level, layer, size, bytes);
+    }
+
+    /// Texture with room for a full mip chain, filled by [`RawTexture::generate_mipmaps`]
+    pub fn with_mip_levels(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+        bytes: &[u8],
+    ) -> Self {
+        let descriptor = TextureDescriptor::new()
+            .with_usage(
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | usage,
+            )
+            .with_mip_level_count(u32::MAX);
+        Self::from_bytes_with_descriptor(gpu, width, height, &descriptor, bytes)
+    }
+
+    /// Fill the levels after the base one on the GPU. The texture needs
+    /// `TEXTURE_BINDING` and `RENDER_ATTACHMENT` usages, see
+    /// [`RawTexture::with_mip_levels`].
+    pub fn generate_mipmaps(&self, gpu: &mut Context, generator: &mut MipmapGenerator, srgb: bool) {
+        generator.generate(
+            gpu,
+            &self.raw,
+            self.descriptor.format(),
+            (self.width, self.height),
+            self.layer_count(),
+            self.mip_level_count,
+            srgb,
+        );
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.descriptor.format()
+    }
+
+    /// Depth of 3D textures
+    pub fn depth_or_array_layers(&self) -> u32 {
+        self.depth_or_array_layers
+    }
+
+    /// Array layers, `1` for 3D textures
+    pub fn layer_count(&self) -> u32 {
+        match self.descriptor.dimension() {
+            wgpu::TextureDimension::D3 => 1,
+            _ => self.depth_or_array_layers,
+        }
     }
 
     pub fn width(&self) -> u32 {
@@ -75,11 +266,14 @@ impl RawTexture {
 }
 
 #[cfg(feature = "use-image")]
-impl Raw {
-    pub fn from_image(gpu: &mut Gpu, usage: wgpu::TextureUsage, img: &image::DynamicImage) -> Self {
-        let img = img.to_bgra();
-        let (width, height) = img.dimensions();
-        let bytes = &img.into_raw()[..];
-        Self::from_bytes(gpu, width, height, usage, bytes)
+impl RawTexture {
+    /// Texture of the image format, see [`DecodedImage`](super::DecodedImage)
+    pub fn from_image(
+        gpu: &mut Context,
+        usage: wgpu::TextureUsages,
+        image: &super::DecodedImage,
+    ) -> Self {
+        let descriptor = image.descriptor().with_usage(usage);
+        Self::from_bytes_with_descriptor(gpu, image.width, image.height, &descriptor, &image.bytes)
     }
 }
@@ -0,0 +1,307 @@
+use crate::context::Context;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroU8;
+use std::rc::Rc;
+
+/// How textures are sampled. Hashable, so that a [`SamplerCache`] shares one
+/// sampler between equal descriptors.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDescriptor {
+    address_modes: [wgpu::AddressMode; 3],
+    mag_filter: wgpu::FilterMode,
+    min_filter: wgpu::FilterMode,
+    mipmap_filter: wgpu::FilterMode,
+    lod_min_clamp: f32,
+    lod_max_clamp: f32,
+    compare: Option<wgpu::CompareFunction>,
+    anisotropy_clamp: Option<NonZeroU8>,
+    border_color: Option<wgpu::SamplerBorderColor>,
+}
+
+impl Default for SamplerDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamplerDescriptor {
+    /// Bilinear filtering clamped to the edges, see [`SamplerDescriptor::linear`]
+    pub fn new() -> Self {
+        Self::linear()
+    }
+
+    /// Nearest texel and mip level, for pixel art
+    pub fn nearest() -> Self {
+        Self {
+            address_modes: [wgpu::AddressMode::ClampToEdge; 3],
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            compare: None,
+            anisotropy_clamp: None,
+            border_color: None,
+        }
+    }
+
+    /// Texels blended, nearest mip level
+    pub fn linear() -> Self {
+        Self::nearest().with_filter(
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Nearest,
+        )
+    }
+
+    /// Texels and mip levels blended
+    pub fn trilinear() -> Self {
+        Self::nearest().with_filter(
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Linear,
+        )
+    }
+
+    /// Trilinear filtering with up to `max_anisotropy` samples, one of 1, 2,
+    /// 4, 8 or 16
+    pub fn anisotropic(max_anisotropy: u8) -> Self {
+        Self::trilinear().with_anisotropy(max_anisotropy)
+    }
+
+    /// Linear filtering for filterable float formats, nearest for the formats
+    /// that cannot be filtered such as `Rgba32Float`, depth or integer formats
+    pub fn for_format(format: wgpu::TextureFormat) -> Self {
+        match format.describe().sample_type {
+            wgpu::TextureSampleType::Float { filterable: true } => Self::linear(),
+            _ => Self::nearest(),
+        }
+    }
+
+    /// Comparison against a depth reference, such as `LessEqual` for shadow
+    /// maps. Filtered to blend the results of neighbouring texels.
+    pub fn comparison(compare: wgpu::CompareFunction) -> Self {
+        Self::linear().with_compare(Some(compare))
+    }
+
+    /// Same address mode in every direction
+    pub fn with_address_mode(mut self, mode: wgpu::AddressMode) -> Self {
+        self.address_modes = [mode; 3];
+        self
+    }
+
+    pub fn with_address_modes(
+        mut self,
+        u: wgpu::AddressMode,
+        v: wgpu::AddressMode,
+        w: wgpu::AddressMode,
+    ) -> Self {
+        self.address_modes = [u, v, w];
+        self
+    }
+
+    pub fn clamp_to_edge(self) -> Self {
+        self.with_address_mode(wgpu::AddressMode::ClampToEdge)
+    }
+
+    pub fn repeat(self) -> Self {
+        self.with_address_mode(wgpu::AddressMode::Repeat)
+    }
+
+    pub fn mirror_repeat(self) -> Self {
+        self.with_address_mode(wgpu::AddressMode::MirrorRepeat)
+    }
+
+    /// Out of bounds samples return `color`. Needs the
+    /// `ADDRESS_MODE_CLAMP_TO_BORDER` device feature.
+    pub fn clamp_to_border(mut self, color: wgpu::SamplerBorderColor) -> Self {
+        self.border_color = Some(color);
+        self.with_address_mode(wgpu::AddressMode::ClampToBorder)
+    }
+
+    /// Anisotropy is turned off unless every filter is `Linear`
+    pub fn with_filter(
+        mut self,
+        mag_filter: wgpu::FilterMode,
+        min_filter: wgpu::FilterMode,
+        mipmap_filter: wgpu::FilterMode,
+    ) -> Self {
+        self.mag_filter = mag_filter;
+        self.min_filter = min_filter;
+        self.mipmap_filter = mipmap_filter;
+        if [mag_filter, min_filter, mipmap_filter].contains(&wgpu::FilterMode::Nearest) {
+            self.anisotropy_clamp = None;
+        }
+        self
+    }
+
+    /// Range of mip levels sampled
+    pub fn with_lod_clamp(mut self, min: f32, max: f32) -> Self {
+        assert!(
+            min >= 0.0 && min <= max,
+            "[fine::graphic::SamplerDescriptor] Invalid level of detail range."
+        );
+        // Equal descriptors must hash the same, -0.0 included
+        self.lod_min_clamp = min + 0.0;
+        self.lod_max_clamp = max + 0.0;
+        self
+    }
+
+    pub fn with_compare(mut self, compare: Option<wgpu::CompareFunction>) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Samples taken along the slope of the texture, one of 1, 2, 4, 8 or 16.
+    /// Above 1 the filters become trilinear, as wgpu requires.
+    pub fn with_anisotropy(mut self, max_anisotropy: u8) -> Self {
+        assert!(
+            max_anisotropy.is_power_of_two() && max_anisotropy <= 16,
+            "[fine::graphic::SamplerDescriptor] Anisotropy must be 1, 2, 4, 8 or 16."
+        );
+        self.anisotropy_clamp = NonZeroU8::new(max_anisotropy).filter(|clamp| clamp.get() > 1);
+        if self.anisotropy_clamp.is_some() {
+            self.mag_filter = wgpu::FilterMode::Linear;
+            self.min_filter = wgpu::FilterMode::Linear;
+            self.mipmap_filter = wgpu::FilterMode::Linear;
+        }
+        self
+    }
+
+    pub fn compare(&self) -> Option<wgpu::CompareFunction> {
+        self.compare
+    }
+
+    /// Binding type to declare in bind group layouts next to a texture of
+    /// `sample_type`. Only filterable float textures take a linear filter.
+    pub fn binding_type(&self, sample_type: wgpu::TextureSampleType) -> wgpu::SamplerBindingType {
+        let filtering = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .contains(&wgpu::FilterMode::Linear);
+        if self.compare.is_some() {
+            wgpu::SamplerBindingType::Comparison
+        } else if filtering {
+            assert!(
+                sample_type == wgpu::TextureSampleType::Float { filterable: true },
+                "[fine::graphic::SamplerDescriptor] {sample_type:?} textures cannot be filtered, use a nearest sampler."
+            );
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        }
+    }
+
+    /// The wgpu descriptor
+    pub fn to_wgpu<'a>(&self, label: Option<&'a str>) -> wgpu::SamplerDescriptor<'a> {
+        let [address_mode_u, address_mode_v, address_mode_w] = self.address_modes;
+        wgpu::SamplerDescriptor {
+            label,
+            address_mode_u,
+            address_mode_v,
+            address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: self.border_color,
+        }
+    }
+
+    /// Fields compared and hashed, level of detail clamps by their bits
+    fn key(&self) -> impl Hash + Eq {
+        (
+            self.address_modes,
+            [self.mag_filter, self.min_filter, self.mipmap_filter],
+            [self.lod_min_clamp.to_bits(), self.lod_max_clamp.to_bits()],
+            self.compare,
+            self.anisotropy_clamp,
+            self.border_color,
+        )
+    }
+}
+
+impl PartialEq for SamplerDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SamplerDescriptor {}
+
+impl Hash for SamplerDescriptor {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Shared sampler with the descriptor it was created from
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    raw: Rc<wgpu::Sampler>,
+    descriptor: SamplerDescriptor,
+}
+
+impl Sampler {
+    pub fn new(gpu: &Context, descriptor: &SamplerDescriptor) -> Self {
+        Self {
+            raw: Rc::new(gpu.create_sampler(&descriptor.to_wgpu(Some("Sampler::new")))),
+            descriptor: *descriptor,
+        }
+    }
+
+    pub fn descriptor(&self) -> &SamplerDescriptor {
+        &self.descriptor
+    }
+
+    pub fn as_raw(&self) -> &wgpu::Sampler {
+        &self.raw
+    }
+}
+
+/// Samplers created once per descriptor
+#[derive(Debug, Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerDescriptor, Sampler>,
+}
+
+impl SamplerCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sampler of `descriptor`, created on first use
+    pub fn get(&mut self, gpu: &Context, descriptor: &SamplerDescriptor) -> Sampler {
+        self.samplers
+            .entry(*descriptor)
+            .or_insert_with(|| Sampler::new(gpu, descriptor))
+            .clone()
+    }
+
+    pub fn nearest(&mut self, gpu: &Context) -> Sampler {
+        self.get(gpu, &SamplerDescriptor::nearest())
+    }
+
+    pub fn linear(&mut self, gpu: &Context) -> Sampler {
+        self.get(gpu, &SamplerDescriptor::linear())
+    }
+
+    pub fn trilinear(&mut self, gpu: &Context) -> Sampler {
+        self.get(gpu, &SamplerDescriptor::trilinear())
+    }
+
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    /// Forget every sampler, those still held elsewhere stay alive
+    pub fn clear(&mut self) {
+        self.samplers.clear();
+    }
+}
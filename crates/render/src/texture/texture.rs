@@ -1,37 +1,213 @@
-use super::RawTexture;
+use super::{
+    AsTextureView, CubeCross, EquirectangularConverter, MipmapOptions, RawTexture, Sampler,
+    SamplerDescriptor, TextureDescriptor, TextureFile, TextureFileError,
+};
 use std::num::NonZeroU32;
-use crate::{context::Context, prelude::DEFAULT_TEXTURE_FORMAT};
+use crate::context::Context;
 
 pub struct Texture {
     view: wgpu::TextureView,
     width: u32,
     height: u32,
+    format: wgpu::TextureFormat,
+    view_dimension: wgpu::TextureViewDimension,
+    multisampled: bool,
+    sampler: Sampler,
 }
 
 impl Texture {
-    pub fn from_raw(raw: &RawTexture, width: u32, height: u32) -> Self {
+    pub fn from_raw(gpu: &Context, raw: &RawTexture, width: u32, height: u32) -> Self {
+        let dimension = raw.descriptor().view_dimension(raw.layer_count());
+        Self::from_raw_with_view_dimension(gpu, raw, width, height, dimension)
+    }
+
+    /// View of every layer of `raw` as `dimension`, such as a cube map.
+    /// Sampled with [`SamplerDescriptor::for_format`] until another sampler is set.
+    pub fn from_raw_with_view_dimension(
+        gpu: &Context,
+        raw: &RawTexture,
+        width: u32,
+        height: u32,
+        dimension: wgpu::TextureViewDimension,
+    ) -> Self {
         // Create texture view
         let view = raw.as_raw().create_view(&wgpu::TextureViewDescriptor {
             label: None,
-            format: Some(DEFAULT_TEXTURE_FORMAT),
-            dimension: Some(wgpu::TextureViewDimension::D2),
+            format: Some(raw.format()),
+            dimension: Some(dimension),
             aspect: wgpu::TextureAspect::default(),
             base_mip_level: 0,
-            mip_level_count: NonZeroU32::new(1),
+            mip_level_count: NonZeroU32::new(raw.mip_level_count()),
             base_array_layer: 0,
-            array_layer_count: NonZeroU32::new(1),
+            array_layer_count: NonZeroU32::new(raw.layer_count()),
         });
 
         Self {
             view,
             width,
             height,
+            format: raw.format(),
+            view_dimension: dimension,
+            multisampled: raw.descriptor().sample_count() > 1,
+            sampler: Sampler::new(gpu, &SamplerDescriptor::for_format(raw.format())),
         }
     }
 
+    /// Texture without data, such as a render target or a depth buffer
+    pub fn with_descriptor(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+    ) -> Self {
+        let raw = RawTexture::with_descriptor(gpu, width, height, 1, descriptor);
+        Self::from_raw(gpu, &raw, width, height)
+    }
+
+    /// Texture holding `bytes` in its base level, rows tightly packed
+    pub fn from_bytes_with_descriptor(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        bytes: &[u8],
+    ) -> Self {
+        let raw = RawTexture::from_bytes_with_descriptor(gpu, width, height, descriptor, bytes);
+        Self::from_raw(gpu, &raw, width, height)
+    }
+
     pub fn from_bytes(gpu: &mut Context, width: u32, height: u32, bytes: &[u8]) -> Self {
         let raw = RawTexture::from_bytes(gpu, width, height, wgpu::TextureUsages::TEXTURE_BINDING, bytes);
-        Self::from_raw(&raw, width, height)
+        Self::from_raw(gpu, &raw, width, height)
+    }
+
+    /// Texture with a mip chain made on the CPU, see [`MipmapOptions`]
+    pub fn from_bytes_with_mipmaps(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        bytes: &[u8],
+        options: &MipmapOptions,
+    ) -> Self {
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING;
+        let raw = RawTexture::from_bytes_with_mipmaps(gpu, width, height, usage, bytes, options);
+        Self::from_raw(gpu, &raw, width, height)
+    }
+
+    /// 2D array texture, one layer per slice of `layers`
+    pub fn array(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        layers: &[&[u8]],
+    ) -> Self {
+        let raw = RawTexture::from_layers(gpu, width, height, descriptor, layers);
+        let dimension = wgpu::TextureViewDimension::D2Array;
+        Self::from_raw_with_view_dimension(gpu, &raw, width, height, dimension)
+    }
+
+    /// Cube map from its faces in the +X, -X, +Y, -Y, +Z, -Z order
+    pub fn cube(
+        gpu: &mut Context,
+        size: u32,
+        descriptor: &TextureDescriptor,
+        faces: &[&[u8]; 6],
+    ) -> Self {
+        let raw = RawTexture::from_layers(gpu, size, size, descriptor, faces);
+        Self::from_raw_with_view_dimension(gpu, &raw, size, size, wgpu::TextureViewDimension::Cube)
+    }
+
+    /// Array of cube maps, six faces per cube as in [`Texture::cube`]
+    pub fn cube_array(
+        gpu: &mut Context,
+        size: u32,
+        descriptor: &TextureDescriptor,
+        faces: &[&[u8]],
+    ) -> Self {
+        assert!(
+            faces.len().is_multiple_of(6),
+            "[fine::graphic::Texture] Cube arrays need six faces per cube."
+        );
+        let raw = RawTexture::from_layers(gpu, size, size, descriptor, faces);
+        let dimension = wgpu::TextureViewDimension::CubeArray;
+        Self::from_raw_with_view_dimension(gpu, &raw, size, size, dimension)
+    }
+
+    /// Cube map cut out of a horizontal or vertical cross, see [`CubeCross`]
+    pub fn cube_from_cross(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        bytes: &[u8],
+    ) -> Self {
+        assert!(
+            !descriptor.format().describe().is_compressed(),
+            "[fine::graphic::Texture] compressed formats are not supported."
+        );
+        let cross = CubeCross::detect(width, height)
+            .expect("[fine::graphic::Texture] Image is not a cube cross.");
+        let faces = cross.split(width, descriptor.block_size() as usize, bytes);
+        let [px, nx, py, ny, pz, nz] = faces.each_ref().map(Vec::as_slice);
+        Self::cube(gpu, cross.face_size(width), descriptor, &[px, nx, py, ny, pz, nz])
+    }
+
+    /// Cube map rendered from an equirectangular panorama, see
+    /// [`EquirectangularConverter`]
+    pub fn cube_from_equirectangular<S>(
+        gpu: &mut Context,
+        converter: &mut EquirectangularConverter,
+        source: &S,
+        size: u32,
+        descriptor: &TextureDescriptor,
+    ) -> Self
+    where
+        S: AsTextureView,
+    {
+        let descriptor = descriptor
+            .with_dimension(wgpu::TextureDimension::D2)
+            .add_usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+        let raw = RawTexture::with_descriptor(gpu, size, size, 6, &descriptor);
+        converter.convert(gpu, source, &raw);
+        Self::from_raw_with_view_dimension(gpu, &raw, size, size, wgpu::TextureViewDimension::Cube)
+    }
+
+    /// 3D texture, one depth slice per slice of `slices`
+    pub fn volume(
+        gpu: &mut Context,
+        width: u32,
+        height: u32,
+        descriptor: &TextureDescriptor,
+        slices: &[&[u8]],
+    ) -> Self {
+        let raw = RawTexture::from_slices(gpu, width, height, descriptor, slices);
+        Self::from_raw_with_view_dimension(gpu, &raw, width, height, wgpu::TextureViewDimension::D3)
+    }
+
+    /// Load a KTX2 or DDS file, see [`RawTexture::from_texture_file`]
+    pub fn load_texture_file<P>(gpu: &mut Context, path: P) -> Result<Self, TextureFileError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_texture_file(gpu, &TextureFile::load(path)?)
+    }
+
+    pub fn from_texture_file(
+        gpu: &mut Context,
+        file: &TextureFile,
+    ) -> Result<Self, TextureFileError> {
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING;
+        let raw = RawTexture::from_texture_file(gpu, usage, file)?;
+        Ok(Self::from_raw_with_view_dimension(
+            gpu,
+            &raw,
+            file.width,
+            file.height,
+            file.view_dimension(),
+        ))
     }
 
     pub fn width(&self) -> u32 {
@@ -45,6 +221,69 @@ impl Texture {
     pub fn dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
     }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn view_dimension(&self) -> wgpu::TextureViewDimension {
+        self.view_dimension
+    }
+
+    /// Sampler bound next to the view, see [`SamplerCache`](super::SamplerCache)
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.sampler = sampler;
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Layout entries of the view at `binding` and the sampler at `binding + 1`
+    pub fn bind_group_layout_entries(
+        &self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> [wgpu::BindGroupLayoutEntry; 2] {
+        let sample_type = self.format.describe().sample_type;
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    sample_type,
+                    view_dimension: self.view_dimension,
+                    multisampled: self.multisampled,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: binding + 1,
+                visibility,
+                ty: wgpu::BindingType::Sampler(self.sampler.descriptor().binding_type(sample_type)),
+                count: None,
+            },
+        ]
+    }
+
+    /// The view at `binding` and the sampler at `binding + 1`
+    pub fn bind_group_entries(&self, binding: u32) -> [wgpu::BindGroupEntry<'_>; 2] {
+        [
+            wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(&self.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: wgpu::BindingResource::Sampler(self.sampler.as_raw()),
+            },
+        ]
+    }
 }
 
 impl super::AsTextureView for Texture {
@@ -61,10 +300,22 @@ impl super::AsTextureView for &Texture {
 
 #[cfg(feature = "use-image")]
 impl Texture {
-    pub fn from_image(gpu: &mut Gpu, img: &image::DynamicImage) -> Self {
-        let img = img.to_bgra();
-        let (width, height) = img.dimensions();
-        let bytes = &img.into_raw()[..];
-        Self::from_bytes(gpu, width, height, bytes)
+    pub fn from_image(gpu: &mut Context, image: &super::DecodedImage) -> Self {
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING;
+        let raw = RawTexture::from_image(gpu, usage, image);
+        Self::from_raw(gpu, &raw, image.width, image.height)
+    }
+
+    /// Load and decode an image file, see [`DecodedImage::load`](super::DecodedImage::load)
+    pub fn load<P>(
+        gpu: &mut Context,
+        path: P,
+        options: &super::ImageOptions,
+    ) -> Result<Self, super::ImageError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let image = super::DecodedImage::load(path, options)?;
+        Ok(Self::from_image(gpu, &image))
     }
 }
@@ -0,0 +1,175 @@
+/// First shader location used by instance attributes, after the vertex attributes.
+/// Geometries with more attributes move them with `InstanceBuffer::attributes_after`.
+pub const INSTANCE_SHADER_LOCATION: u32 = 5;
+
+pub mod transform {
+    use super::INSTANCE_SHADER_LOCATION;
+    use crate::util::VertexAttribute;
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Instance {
+        pub model: [[f32; 4]; 4],
+    }
+
+    unsafe impl Pod for Instance {}
+    unsafe impl Zeroable for Instance {}
+
+    impl From<[f32; 16]> for Instance {
+        fn from(matrix: [f32; 16]) -> Self {
+            Self {
+                model: bytemuck::cast(matrix),
+            }
+        }
+    }
+
+    impl VertexAttribute for Instance {
+        const STRIDE: wgpu::BufferAddress = std::mem::size_of::<Instance>() as _;
+        const STEP_MODE: wgpu::VertexStepMode = wgpu::VertexStepMode::Instance;
+        const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: INSTANCE_SHADER_LOCATION,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 2 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 3 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 3,
+            },
+        ];
+    }
+}
+
+pub mod transform_color {
+    use super::INSTANCE_SHADER_LOCATION;
+    use crate::util::VertexAttribute;
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Instance {
+        pub model: [[f32; 4]; 4],
+        pub color: [f32; 4],
+    }
+
+    unsafe impl Pod for Instance {}
+    unsafe impl Zeroable for Instance {}
+
+    impl From<[f32; 16]> for Instance {
+        fn from(matrix: [f32; 16]) -> Self {
+            Self {
+                model: bytemuck::cast(matrix),
+                color: [1.0; 4],
+            }
+        }
+    }
+
+    impl VertexAttribute for Instance {
+        const STRIDE: wgpu::BufferAddress = std::mem::size_of::<Instance>() as _;
+        const STEP_MODE: wgpu::VertexStepMode = wgpu::VertexStepMode::Instance;
+        const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: INSTANCE_SHADER_LOCATION,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 2 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 3 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 4 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 4,
+            },
+        ];
+    }
+}
+
+pub mod transform_color_frame {
+    use super::INSTANCE_SHADER_LOCATION;
+    use crate::util::VertexAttribute;
+    use bytemuck::{Pod, Zeroable};
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Instance {
+        pub model: [[f32; 4]; 4],
+        pub color: [f32; 4],
+        /// Index of the atlas frame to sample
+        pub frame: u32,
+    }
+
+    unsafe impl Pod for Instance {}
+    unsafe impl Zeroable for Instance {}
+
+    impl From<[f32; 16]> for Instance {
+        fn from(matrix: [f32; 16]) -> Self {
+            Self {
+                model: bytemuck::cast(matrix),
+                color: [1.0; 4],
+                frame: 0,
+            }
+        }
+    }
+
+    impl VertexAttribute for Instance {
+        const STRIDE: wgpu::BufferAddress = std::mem::size_of::<Instance>() as _;
+        const STEP_MODE: wgpu::VertexStepMode = wgpu::VertexStepMode::Instance;
+        const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: INSTANCE_SHADER_LOCATION,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 2 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 3 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 4 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 4,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: 5 * 4 * 4,
+                shader_location: INSTANCE_SHADER_LOCATION + 5,
+            },
+        ];
+    }
+}
@@ -1,4 +1,9 @@
+mod instance_attribute;
 mod vertex_attribute;
+pub use instance_attribute::INSTANCE_SHADER_LOCATION;
+pub use instance_attribute::transform::Instance as InstanceTransform;
+pub use instance_attribute::transform_color::Instance as InstanceTransformColor;
+pub use instance_attribute::transform_color_frame::Instance as InstanceTransformColorFrame;
 pub use vertex_attribute::VertexAttribute;
 pub use vertex_attribute::position::Vertex as VertexPosition;
 pub use vertex_attribute::position_texcoord::Vertex as VertexPositionTexCoord;
@@ -3,6 +3,17 @@ pub trait VertexAttribute {
     const STRIDE: wgpu::BufferAddress;
     /// A description of each of the vertex's attributes.
     const ATTRIBUTES: &'static [wgpu::VertexAttribute];
+    /// How often the buffer is stepped through, per vertex or per instance.
+    const STEP_MODE: wgpu::VertexStepMode = wgpu::VertexStepMode::Vertex;
+
+    /// Layout of a buffer made of these elements.
+    fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: Self::STRIDE,
+            step_mode: Self::STEP_MODE,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
 }
 
 pub mod position {
@@ -85,7 +85,7 @@ impl Node {
             if let Some(parent) = parent {
                 self.world_matrix = parent.world_matrix.mul_mat4(&self.local_matrix);
             } else {
-                self.world_matrix = self.local_matrix.clone();
+                self.world_matrix = self.local_matrix;
             }
         }
     }
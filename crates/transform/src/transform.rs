@@ -41,19 +41,19 @@ impl Transform {
     }
 
     pub fn rotate(&mut self, rotation: Quat) {
-        self.rotation = self.rotation * rotation;
+        self.rotation *= rotation;
     }
 
     pub fn rotate_x(&mut self, angle: f32) {
-        self.rotation = self.rotation * Quat::from_rotation_x(angle);
+        self.rotation *= Quat::from_rotation_x(angle);
     }
 
     pub fn rotate_y(&mut self, angle: f32) {
-        self.rotation = self.rotation * Quat::from_rotation_y(angle);
+        self.rotation *= Quat::from_rotation_y(angle);
     }
 
     pub fn rotate_z(&mut self, angle: f32) {
-        self.rotation = self.rotation * Quat::from_rotation_z(angle);
+        self.rotation *= Quat::from_rotation_z(angle);
     }
 
     pub fn look_at(&mut self, target: Vec3, up: Vec3) {